@@ -1,9 +1,10 @@
 use axum::{
     Json,
     http::StatusCode,
-    extract::{State, Path},
+    extract::{State, Path, Query},
     response::IntoResponse,
 };
+use chrono::Timelike;
 use serde::{Deserialize, Serialize};
 use std::sync::Arc;
 
@@ -15,9 +16,18 @@ pub struct AppState {
     pub decision_engine: Arc<DecisionEngine>,
     pub anti_hallucination: Arc<AntiHallucinationEngine>,
     pub conversation_engine: Arc<ConversationEngine>,
-    pub quota_engine: Arc<tokio::sync::Mutex<QuotaEngine>>,
+    pub quotas: Arc<QuotaAccounts>,
     pub knowledge_base: Arc<tokio::sync::Mutex<KnowledgeBaseEngine>>,
     pub audit_engine: Arc<AuditEngine>,
+    pub language_detector: Arc<LanguageDetector>,
+    pub storage: Arc<StorageEngine>,
+    pub event_classifier: Arc<EventClassifier>,
+    pub sentiment_engine: Arc<SentimentEngine>,
+    pub alert_engine: Arc<AlertEngine>,
+    pub lifecycle: Arc<LifecycleEngine>,
+    pub analytics: Arc<AnalyticsEngine>,
+    pub send_scheduler: Arc<SendScheduler>,
+    pub outbound_queue: Arc<OutboundQueue>,
 }
 
 // ============== REQUEST/RESPONSE MODELS ==============
@@ -28,18 +38,83 @@ pub struct DecisionRequest {
     pub conversation_state: String,
     pub quotas_available: bool,
     pub is_active_hours: bool,
+    /// Detected by the sentiment engine when null
     pub sentiment_detected: Option<String>,
+    /// Customer language ("fr", "en", "ar"); detected from the message if absent
+    #[serde(default)]
+    pub locale: Option<String>,
+    /// Stored conversation, used to honour a human takeover
+    #[serde(default)]
+    pub conversation_id: Option<String>,
+    /// Customer number; finds the stored conversation when `conversation_id` is not given
+    #[serde(default)]
+    pub phone_number: Option<String>,
+    /// Consecutive messages the bot did not understand
+    #[serde(default)]
+    pub misunderstandings: u32,
 }
 
 #[derive(Debug, Serialize)]
 pub struct DecisionResponse {
     pub action: String,
     pub details: Option<String>,
+    /// Policy rule that chose the action
+    pub rule: String,
+    /// Rules evaluated in order, with each condition and its input
+    pub trace: Vec<policy::RuleTrace>,
+    /// Language the reply must be generated in
+    pub locale: String,
+    /// Catalog version the decision was based on, to pass on to the audit log
+    pub catalog_version: Option<u32>,
+}
+
+/// Action as typed JSON, tagged by `type` (v2 response format)
+/// The policy files keep the `Action` serialization
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type")]
+pub enum ActionPayload {
+    RespondText { text: String },
+    RespondWithMedia { text: String, media_id: String },
+    Ignore,
+    Delay { seconds: u64 },
+    AlertHuman { reason: String },
+    StopAutomation,
+}
+
+impl From<decision::Action> for ActionPayload {
+    fn from(action: decision::Action) -> Self {
+        match action {
+            decision::Action::RespondText { text } => ActionPayload::RespondText { text },
+            decision::Action::RespondWithMedia { text, media_id } => ActionPayload::RespondWithMedia { text, media_id },
+            decision::Action::Ignore => ActionPayload::Ignore,
+            decision::Action::Delay { seconds } => ActionPayload::Delay { seconds },
+            decision::Action::AlertHuman { reason } => ActionPayload::AlertHuman { reason },
+            decision::Action::StopAutomation => ActionPayload::StopAutomation,
+        }
+    }
+}
+
+#[derive(Debug, Serialize)]
+pub struct DecisionResponseV2 {
+    pub action: ActionPayload,
+    /// Policy rule that chose the action
+    pub rule: String,
+    /// Rules evaluated in order, with each condition and its input
+    pub trace: Vec<policy::RuleTrace>,
+    /// Conversation state after the decision (the stored one when `conversation_id` is given)
+    pub conversation_state: String,
+    /// Language the reply must be generated in
+    pub locale: String,
+    /// Catalog version the decision was based on, to pass on to the audit log
+    pub catalog_version: Option<u32>,
 }
 
 #[derive(Debug, Deserialize)]
 pub struct ValidationRequest {
     pub text: String,
+    /// Language of the reply ("fr", "en", "ar"); detected from the text if absent
+    #[serde(default)]
+    pub locale: Option<String>,
 }
 
 #[derive(Debug, Serialize)]
@@ -47,17 +122,64 @@ pub struct ValidationResponse {
     pub valid: bool,
     pub validated_text: Option<String>,
     pub error: Option<String>,
+    /// Neutral message in the reply's locale, to send instead of an invalid text
+    pub fallback_text: Option<String>,
 }
 
 #[derive(Debug, Deserialize)]
 pub struct QuotaCheckRequest {
     pub message_type: String, // "text", "image", "video"
+    /// Phone number of the contact, enables the per-contact limits
+    #[serde(default)]
+    pub recipient: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct QuotaRecordRequest {
+    #[serde(default = "default_message_type")]
+    pub message_type: String,
+    #[serde(default)]
+    pub recipient: Option<String>,
+}
+
+fn default_message_type() -> String {
+    "text".to_string()
 }
 
 #[derive(Debug, Serialize)]
 pub struct QuotaCheckResponse {
     pub can_send: bool,
     pub delay_seconds: Option<u64>,
+    /// Earliest time the send is allowed; null while waiting for a reply
+    pub allowed_at: Option<chrono::DateTime<chrono::Utc>>,
+    /// Limit that delays the send, when `can_send` is false
+    pub blocked_by: Option<crate::engines::quota::QuotaBlock>,
+}
+
+/// `?account=` of the quota routes
+#[derive(Debug, Deserialize)]
+pub struct AccountQuery {
+    /// WhatsApp account (instance ID); the default account when omitted
+    #[serde(default)]
+    pub account: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct QuotaFeedbackRequest {
+    pub kind: crate::engines::quota::DeliveryFeedback,
+}
+
+#[derive(Debug, Serialize)]
+pub struct QuotaThrottleResponse {
+    pub throttle_factor: f64,
+    pub effective_limits: crate::engines::quota::QuotaLimits,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct WarmupRequest {
+    /// Connection date of the number; now when omitted
+    #[serde(default)]
+    pub connected_at: Option<chrono::DateTime<chrono::Utc>>,
 }
 
 #[derive(Debug, Deserialize)]
@@ -82,51 +204,384 @@ pub async fn health_check() -> impl IntoResponse {
     })))
 }
 
+/// Decision with what every version of the decision route reports alongside it
+struct DecisionOutcome {
+    decision: decision::Decision,
+    locale: language::Locale,
+    /// Conversation state the decision leaves
+    conversation_state: String,
+    catalog_version: Option<u32>,
+}
+
+/// Decision for a request; shared by every version of the decision route
+async fn run_decision(
+    state: &AppState,
+    account: &AccountQuery,
+    req: DecisionRequest,
+) -> Result<DecisionOutcome, (StatusCode, String)> {
+    let locale = match req.locale.as_deref() {
+        Some(code) => language::Locale::parse(code)
+            .ok_or_else(|| (StatusCode::BAD_REQUEST, format!("Unsupported locale: {}", code)))?,
+        None => state.language_detector.detect(&req.incoming_message),
+    };
+    
+    let now = chrono::Utc::now();
+    let conversation = match (&req.conversation_id, &req.phone_number) {
+        (Some(id), _) => Some(load_conversation(state, id)?),
+        (None, Some(phone_number)) => state.storage.find_conversation_by_phone(phone_number).map_err(storage_error)?,
+        (None, None) => None,
+    };
+    let human_takeover = conversation.as_ref().is_some_and(|c| c.is_under_takeover(now));
+    // A stored conversation is decided on its own state, not the one sent by the caller
+    let (conversation_state, current_state) = match &conversation {
+        Some(c) => (c.state.to_string(), Some(c.state.clone())),
+        None => {
+            let current = state.conversation_engine.parse_state(&req.conversation_state).ok();
+            (req.conversation_state, current)
+        }
+    };
+    
+    let (kb_matches, catalog_version) = kb_matches(state, &req.incoming_message, locale).await;
+    
+    let media_requested = state.decision_engine.media_policy().is_request(&req.incoming_message);
+    let media_id = match &current_state {
+        Some(current) if media_requested => {
+            let quota = account_quota(state, account)?;
+            let quota = quota.lock().await;
+            let recipient = conversation.as_ref().map(|c| c.phone_number.as_str());
+            allowed_media(state, current, &kb_matches, &quota, recipient, now).await
+        }
+        _ => None,
+    };
+    
+    // A stored conversation carries its own misunderstanding count
+    let misunderstandings = conversation.as_ref().map_or(req.misunderstandings, |c| c.misunderstanding_count);
+    // Detected locally unless the caller gives one
+    let sentiment_detected = req.sentiment_detected
+        .or_else(|| state.sentiment_engine.detect(&req.incoming_message));
+    
+    let misunderstanding_limit_reached = state.decision_engine.misunderstanding_limit_reached(misunderstandings);
+    let context = decision::DecisionContext {
+        incoming_message: req.incoming_message,
+        conversation_state: conversation_state.clone(),
+        quotas_available: req.quotas_available,
+        is_active_hours: req.is_active_hours,
+        sentiment_detected,
+        human_takeover,
+        kb_matches,
+        local_hour: local_hour(state),
+        misunderstandings,
+        misunderstanding_limit_reached,
+        terminal_state: current_state.as_ref().is_some_and(|s| state.conversation_engine.is_terminal_state(s)),
+        media_requested,
+        media_id,
+    };
+    
+    // The decision itself never moves the conversation
+    let decision = state.decision_engine.decide(context)
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+    
+    // The alert for repeated misunderstandings is raised once: the count is
+    // cleared so the next messages fall through to the other rules
+    // (an alert from another rule leaves it for the misunderstanding alert)
+    if let (Some(stored), decision::REPEATED_MISUNDERSTANDING_RULE) = (&conversation, decision.rule.as_str()) {
+        update_conversation(state, &stored.id, |conversation| {
+            conversation.reset_misunderstandings();
+            vec![]
+        })?;
+    }
+    
+    // Record decision metric
+    crate::api::metrics::DECISIONS_TOTAL
+        .with_label_values(&[describe_action(&decision.action).0])
+        .inc();
+    
+    Ok(DecisionOutcome {
+        decision,
+        locale,
+        conversation_state,
+        catalog_version,
+    })
+}
+
 /// Make a decision based on context
 pub async fn make_decision(
     State(state): State<AppState>,
+    Query(account): Query<AccountQuery>,
     Json(req): Json<DecisionRequest>,
 ) -> Result<Json<DecisionResponse>, (StatusCode, String)> {
+    let outcome = run_decision(&state, &account, req).await?;
+    let (action_type, details) = describe_action(&outcome.decision.action);
+    
+    Ok(Json(DecisionResponse {
+        action: action_type.to_string(),
+        details,
+        rule: outcome.decision.rule,
+        trace: outcome.decision.trace,
+        locale: outcome.locale.code().to_string(),
+        catalog_version: outcome.catalog_version,
+    }))
+}
+
+/// Make a decision based on context (v2: typed action)
+pub async fn make_decision_v2(
+    State(state): State<AppState>,
+    Query(account): Query<AccountQuery>,
+    Json(req): Json<DecisionRequest>,
+) -> Result<Json<DecisionResponseV2>, (StatusCode, String)> {
+    let outcome = run_decision(&state, &account, req).await?;
+    
+    Ok(Json(DecisionResponseV2 {
+        action: outcome.decision.action.into(),
+        rule: outcome.decision.rule,
+        trace: outcome.decision.trace,
+        conversation_state: outcome.conversation_state,
+        locale: outcome.locale.code().to_string(),
+        catalog_version: outcome.catalog_version,
+    }))
+}
+
+/// Action name and its parameters as text (v1 response format)
+fn describe_action(action: &decision::Action) -> (&'static str, Option<String>) {
+    match action {
+        decision::Action::RespondText { text } => ("RespondText", Some(text.clone())),
+        decision::Action::RespondWithMedia { text, media_id } => {
+            ("RespondWithMedia", Some(format!("{} (media: {})", text, media_id)))
+        }
+        decision::Action::Ignore => ("Ignore", None),
+        decision::Action::Delay { seconds } => {
+            ("Delay", Some(format!("{} seconds", seconds)))
+        }
+        decision::Action::AlertHuman { reason } => ("AlertHuman", Some(reason.clone())),
+        decision::Action::StopAutomation => ("StopAutomation", None),
+    }
+}
+
+/// Products of the knowledge base mentioned in a message, with the catalog version they come from
+async fn kb_matches(state: &AppState, message: &str, locale: language::Locale) -> (Vec<String>, Option<u32>) {
+    let kb = state.knowledge_base.lock().await;
+    let classifications = state.event_classifier.classify(message, &kb);
+    (products_in_locale(&kb, &classifications, locale), kb.current_version().map(|v| v.version))
+}
+
+/// Products mentioned by the classifications that can be presented in `locale`
+fn products_in_locale(
+    kb: &KnowledgeBaseEngine,
+    classifications: &[classifier::Classification],
+    locale: language::Locale,
+) -> Vec<String> {
+    let offered = kb.get_products_for_locale(locale);
+    classifications.iter()
+        .filter_map(|c| c.product_id.clone())
+        .filter(|id| offered.iter().any(|p| &p.id == id))
+        .collect()
+}
+
+/// Media to show for the products mentioned, if both the media policy and the account quotas allow it
+async fn allowed_media(
+    state: &AppState,
+    current: &conversation::ConversationState,
+    kb_matches: &[String],
+    quota: &QuotaEngine,
+    recipient: Option<&str>,
+    now: chrono::DateTime<chrono::Utc>,
+) -> Option<String> {
+    let kb = state.knowledge_base.lock().await;
+    let products: Vec<_> = kb_matches.iter().filter_map(|id| kb.get_product(id)).collect();
+    let media = state.decision_engine.media_policy().select(current, &products)?;
+    let is_video = matches!(media.media_type, knowledge_base::MediaType::Video);
+    quota.can_send_media(recipient, is_video, now).is_allowed().then(|| media.id.clone())
+}
+
+/// Current hour in the business timezone
+fn local_hour(state: &AppState) -> u32 {
+    chrono::Utc::now().with_timezone(&state.quotas.calendar().timezone()).hour()
+}
+
+/// Token budget of the knowledge base block in simulated prompts
+const PROMPT_TOKEN_BUDGET: usize = 800;
+
+#[derive(Debug, Deserialize)]
+pub struct SimulationRequest {
+    pub message: String,
+    /// Stored conversation to start from; it is never modified
+    #[serde(default)]
+    pub conversation_id: Option<String>,
+    /// Starting state without `conversation_id` (initial state by default)
+    #[serde(default)]
+    pub conversation_state: Option<String>,
+    #[serde(default)]
+    pub locale: Option<String>,
+    /// Overrides; otherwise read from the account quotas and the active hours
+    #[serde(default)]
+    pub quotas_available: Option<bool>,
+    #[serde(default)]
+    pub is_active_hours: Option<bool>,
+    /// Overrides the sentiment engine
+    #[serde(default)]
+    pub sentiment_detected: Option<String>,
+    /// Count before this message; otherwise read from the conversation
+    #[serde(default)]
+    pub misunderstandings: Option<u32>,
+    /// Candidate AI reply, run through the anti-hallucination checks
+    #[serde(default)]
+    pub reply: Option<String>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct SimulationResponse {
+    pub locale: String,
+    pub classifications: Vec<classifier::Classification>,
+    /// Transitions that would be applied, in order
+    pub transitions: Vec<conversation::TransitionRecord>,
+    /// State after the transitions, used for the decision
+    pub conversation_state: String,
+    pub decision: decision::Decision,
+    /// Prompt sent to the IA Gateway, when the action generates text
+    pub prompt: Option<String>,
+    /// Products exposed in the prompt
+    pub grounding_product_ids: Vec<String>,
+    pub validation: Option<ValidationResponse>,
+}
+
+/// Dry run of the inbound pipeline: classification, transitions, decision, prompt and validation
+/// Nothing is stored, counted or sent
+pub async fn simulate_decision(
+    State(state): State<AppState>,
+    Query(account): Query<AccountQuery>,
+    Json(req): Json<SimulationRequest>,
+) -> Result<Json<SimulationResponse>, (StatusCode, String)> {
+    let now = chrono::Utc::now();
+    let locale = match req.locale.as_deref() {
+        Some(code) => language::Locale::parse(code)
+            .ok_or_else(|| (StatusCode::BAD_REQUEST, format!("Unsupported locale: {}", code)))?,
+        None => state.language_detector.detect(&req.message),
+    };
+    
+    let mut conversation = match (&req.conversation_id, &req.conversation_state) {
+        (Some(_), Some(_)) => {
+            return Err((StatusCode::BAD_REQUEST, "conversation_state cannot be used with conversation_id".to_string()));
+        }
+        (Some(id), None) => load_conversation(&state, id)?,
+        (None, Some(name)) => {
+            let initial = state.conversation_engine.parse_state(name)
+                .map_err(|e| (StatusCode::BAD_REQUEST, e.to_string()))?;
+            conversation::Conversation::new("simulation", initial)
+        }
+        (None, None) => conversation::Conversation::new("simulation", state.conversation_engine.initial_state()),
+    };
+    if let Some(count) = req.misunderstandings {
+        conversation.misunderstanding_count = count;
+    }
+    
+    // Same steps as an inbound message, on a copy
+    let classifications = {
+        let kb = state.knowledge_base.lock().await;
+        state.event_classifier.classify(&req.message, &kb)
+    };
+    let mut transitions = vec![];
+    if let Some(transition) = state.lifecycle.reopen_on_inbound(&state.conversation_engine, &mut conversation, now) {
+        transitions.push(transition);
+    }
+    if let Some(product_id) = classifications.iter().find_map(|c| c.product_id.clone()) {
+        conversation.focus_product_id = Some(product_id);
+    }
+    if let Some(primary) = classifier::EventClassifier::primary(&classifications) {
+        transitions.push(state.conversation_engine.apply(&mut conversation, primary.event.clone()));
+    }
+    if !classifications.is_empty() {
+        conversation.reset_misunderstandings();
+    }
+    let kb_matches = products_in_locale(&*state.knowledge_base.lock().await, &classifications, locale);
+    
+    // Checked on a detached copy, after the reply of the contact as on a real inbound message
+    let mut quota = account_quota(&state, &account)?.lock().await.detached();
+    quota.record_reply(&conversation.phone_number).map_err(storage_error)?;
+    let quota = &quota;
+    let recipient = Some(conversation.phone_number.as_str());
+    let quotas_available = req.quotas_available
+        .unwrap_or_else(|| quota.can_send_message(recipient, now).is_allowed());
+    let media_requested = state.decision_engine.media_policy().is_request(&req.message);
+    let media_id = if media_requested {
+        allowed_media(&state, &conversation.state, &kb_matches, quota, recipient, now).await
+    } else {
+        None
+    };
+    
     let context = decision::DecisionContext {
-        incoming_message: req.incoming_message,
-        conversation_state: req.conversation_state.clone(),
-        quotas_available: req.quotas_available,
-        is_active_hours: req.is_active_hours,
-        sentiment_detected: req.sentiment_detected,
+        incoming_message: req.message.clone(),
+        conversation_state: conversation.state.to_string(),
+        quotas_available,
+        is_active_hours: req.is_active_hours.unwrap_or_else(|| state.outbound_queue.active_hours().contains(now)),
+        sentiment_detected: req.sentiment_detected.or_else(|| state.sentiment_engine.detect(&req.message)),
+        human_takeover: conversation.is_under_takeover(now),
+        kb_matches: kb_matches.clone(),
+        local_hour: local_hour(&state),
+        misunderstandings: conversation.misunderstanding_count,
+        misunderstanding_limit_reached: state.decision_engine.misunderstanding_limit_reached(conversation.misunderstanding_count),
+        terminal_state: state.conversation_engine.is_terminal_state(&conversation.state),
+        media_requested,
+        media_id,
     };
+    let decision = state.decision_engine.decide(context)
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
     
-    match state.decision_engine.decide(context) {
-        Ok(action) => {
-            let (action_type, details) = match action {
-                decision::Action::RespondText { text } => ("RespondText", Some(text)),
-                decision::Action::RespondWithMedia { text, media_id } => {
-                    ("RespondWithMedia", Some(format!("{} (media: {})", text, media_id)))
-                }
-                decision::Action::Ignore => ("Ignore", None),
-                decision::Action::Delay { seconds } => {
-                    ("Delay", Some(format!("{} seconds", seconds)))
-                }
-                decision::Action::AlertHuman { reason } => ("AlertHuman", Some(reason)),
-                decision::Action::StopAutomation => ("StopAutomation", None),
+    let kb = state.knowledge_base.lock().await;
+    let (prompt, grounding_product_ids) = match decision.action {
+        decision::Action::RespondText { .. } | decision::Action::RespondWithMedia { .. } => {
+            let products: Vec<_> = kb_matches.iter().filter_map(|id| kb.get_product(id)).collect();
+            let grounding = grounding::ContextBuilder::new(PROMPT_TOKEN_BUDGET, locale).build(&products, now);
+            let exposed = grounding.exposed_product_ids.clone();
+            let constraints = ia_gateway::AIConstraints {
+                max_length: state.anti_hallucination.max_length(),
+                tone: "professionnel".to_string(),
+                objective: format!("Reply to: {}", req.message),
+                prohibitions: state.anti_hallucination.forbidden_words_for(locale).to_vec(),
+                locale,
+                grounding,
             };
-            
-            // Record decision metric
-            crate::api::metrics::DECISIONS_TOTAL
-                .with_label_values(&[action_type])
-                .inc();
-            
-            // Update conversation state gauge
-            crate::api::metrics::CONVERSATION_STATE
-                .with_label_values(&[&req.conversation_state])
-                .set(1.0);
-            
-            Ok(Json(DecisionResponse {
-                action: action_type.to_string(),
-                details,
-            }))
+            (Some(IAGateway::default().build_prompt(&constraints)), exposed)
         }
-        Err(e) => Err((StatusCode::INTERNAL_SERVER_ERROR, e.to_string())),
-    }
+        _ => (None, vec![]),
+    };
+    
+    let validation = req.reply.as_deref().map(|reply| {
+        let allowed_prices = kb.quotable_prices(now);
+        match state.anti_hallucination.validate_after_ai(reply, locale)
+            .and_then(|text| state.anti_hallucination.validate_prices(&text, &allowed_prices).map(|_| text))
+        {
+            Ok(text) => ValidationResponse {
+                valid: true,
+                validated_text: Some(text),
+                error: None,
+                fallback_text: None,
+            },
+            Err(e) => ValidationResponse {
+                valid: false,
+                validated_text: None,
+                error: Some(e.to_string()),
+                fallback_text: Some(state.anti_hallucination.get_fallback_message(locale)),
+            },
+        }
+    });
+    
+    Ok(Json(SimulationResponse {
+        locale: locale.code().to_string(),
+        classifications,
+        transitions,
+        conversation_state: conversation.state.to_string(),
+        decision,
+        prompt,
+        grounding_product_ids,
+        validation,
+    }))
+}
+
+/// Get the decision policy in use
+pub async fn get_decision_policy(
+    State(state): State<AppState>,
+) -> Json<policy::PolicyDefinition> {
+    Json(state.decision_engine.policy().definition().clone())
 }
 
 /// Validate AI-generated text
@@ -134,7 +589,22 @@ pub async fn validate_text(
     State(state): State<AppState>,
     Json(req): Json<ValidationRequest>,
 ) -> Result<Json<ValidationResponse>, (StatusCode, String)> {
-    match state.anti_hallucination.validate_after_ai(&req.text) {
+    let locale = match req.locale.as_deref() {
+        Some(code) => language::Locale::parse(code)
+            .ok_or_else(|| (StatusCode::BAD_REQUEST, format!("Unsupported locale: {}", code)))?,
+        None => state.language_detector.detect(&req.text),
+    };
+    
+    // Prices can only come from the knowledge base
+    let allowed_prices = state.knowledge_base.lock().await.quotable_prices(chrono::Utc::now());
+    
+    let result = state.anti_hallucination.validate_after_ai(&req.text, locale)
+        .and_then(|text| {
+            state.anti_hallucination.validate_prices(&text, &allowed_prices)?;
+            Ok(text)
+        });
+    
+    match result {
         Ok(validated) => {
             // Record successful validation
             crate::api::metrics::VALIDATION_RESULTS
@@ -145,6 +615,7 @@ pub async fn validate_text(
                 valid: true,
                 validated_text: Some(validated),
                 error: None,
+                fallback_text: None,
             }))
         }
         Err(e) => {
@@ -162,6 +633,7 @@ pub async fn validate_text(
                 valid: false,
                 validated_text: None,
                 error: Some(e.to_string()),
+                fallback_text: Some(state.anti_hallucination.get_fallback_message(locale)),
             }))
         }
     }
@@ -170,80 +642,497 @@ pub async fn validate_text(
 /// Check if message can be sent (quota)
 pub async fn check_quota(
     State(state): State<AppState>,
+    Query(account): Query<AccountQuery>,
     Json(req): Json<QuotaCheckRequest>,
 ) -> Result<Json<QuotaCheckResponse>, (StatusCode, String)> {
-    let quota = state.quota_engine.lock().await;
+    let quota = account_quota(&state, &account)?;
+    let quota = quota.lock().await;
+    let now = chrono::Utc::now();
     
-    let can_send = match req.message_type.as_str() {
-        "text" => quota.can_send_message(),
-        "image" => quota.can_send_media(false),
-        "video" => quota.can_send_media(true),
+    let recipient = req.recipient.as_deref();
+    let check = match req.message_type.as_str() {
+        "text" => quota.can_send_message(recipient, now),
+        "image" => quota.can_send_media(recipient, false, now),
+        "video" => quota.can_send_media(recipient, true, now),
         _ => return Err((StatusCode::BAD_REQUEST, "Invalid message type".to_string())),
     };
     
-    let delay = if can_send {
+    let delay = if check.is_allowed() {
         Some(quota.calculate_delay())
     } else {
         None
     };
     
     Ok(Json(QuotaCheckResponse {
-        can_send,
+        can_send: check.is_allowed(),
         delay_seconds: delay,
+        allowed_at: check.allowed_at,
+        blocked_by: check.blocked_by,
     }))
 }
 
-/// Record message sent
+/// Record message sent (the body is optional: a text message without recipient)
 pub async fn record_message(
     State(state): State<AppState>,
+    Query(account): Query<AccountQuery>,
+    req: Option<Json<QuotaRecordRequest>>,
 ) -> Result<StatusCode, (StatusCode, String)> {
-    let mut quota = state.quota_engine.lock().await;
+    let quota = account_quota(&state, &account)?;
+    let mut quota = quota.lock().await;
+    let (message_type, recipient) = match &req {
+        Some(Json(req)) => (req.message_type.as_str(), req.recipient.as_deref()),
+        None => ("text", None),
+    };
     
-    quota.record_message()
-        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+    match message_type {
+        "text" => quota.record_message(recipient),
+        "image" => quota.record_media(recipient, false),
+        "video" => quota.record_media(recipient, true),
+        _ => return Err((StatusCode::BAD_REQUEST, "Invalid message type".to_string())),
+    }
+    .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
     
     // Update quota metrics
-    let usage = quota.get_usage();
-    crate::api::metrics::QUOTA_MESSAGES_TODAY.set(usage.messages_today as f64);
-    crate::api::metrics::QUOTA_MESSAGES_WEEK.set(usage.messages_this_week as f64);
+    crate::api::metrics::publish_quota(&quota, chrono::Utc::now());
     
     Ok(StatusCode::OK)
 }
 
+/// Report gateway feedback (rate limiting, blocks, failures) to tighten the quotas
+pub async fn record_quota_feedback(
+    State(state): State<AppState>,
+    Query(account): Query<AccountQuery>,
+    Json(req): Json<QuotaFeedbackRequest>,
+) -> Result<Json<QuotaThrottleResponse>, (StatusCode, String)> {
+    let quota = account_quota(&state, &account)?;
+    let mut quota = quota.lock().await;
+    let now = chrono::Utc::now();
+    
+    quota.record_feedback(req.kind, now)
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+    crate::api::metrics::publish_quota(&quota, now);
+    
+    Ok(Json(QuotaThrottleResponse {
+        throttle_factor: quota.throttle_factor(now),
+        effective_limits: quota.effective_limits(now),
+    }))
+}
+
+/// Quota engine of the requested account (400 for an invalid account ID)
+fn account_quota(state: &AppState, query: &AccountQuery) -> Result<quota_accounts::SharedQuota, (StatusCode, String)> {
+    known_account(state, query.account.as_deref().unwrap_or(quota::DEFAULT_ACCOUNT))
+}
+
+/// Quota engine of a known account: 400 for an invalid ID, 404 for an account
+/// neither configured nor already known (never created by a request)
+fn known_account(state: &AppState, account: &str) -> Result<quota_accounts::SharedQuota, (StatusCode, String)> {
+    state.quotas
+        .get(account)
+        .map_err(|e| (StatusCode::BAD_REQUEST, e.to_string()))?
+        .ok_or_else(|| (StatusCode::NOT_FOUND, format!("Unknown account: {}", account)))
+}
+
 /// Transition conversation state
 pub async fn transition_state(
     State(state): State<AppState>,
     Json(req): Json<TransitionRequest>,
 ) -> Result<Json<TransitionResponse>, (StatusCode, String)> {
-    // Parse state
-    let current_state = match req.current_state.as_str() {
-        "Discovery" => conversation::ConversationState::Discovery,
-        "Interest" => conversation::ConversationState::Interest,
-        "Intent" => conversation::ConversationState::Intent,
-        "Objection" => conversation::ConversationState::Objection,
-        "Negative" => conversation::ConversationState::Negative,
-        "Escalated" => conversation::ConversationState::Escalated,
-        "Frozen" => conversation::ConversationState::Frozen,
-        _ => return Err((StatusCode::BAD_REQUEST, "Invalid state".to_string())),
-    };
-    
-    // Parse event
-    let event = match req.event.as_str() {
-        "ProductQuestion" => conversation::ConversationEvent::ProductQuestion,
-        "PriceInterest" => conversation::ConversationEvent::PriceInterest,
-        "PurchaseIntent" => conversation::ConversationEvent::PurchaseIntent,
-        "ObjectionRaised" => conversation::ConversationEvent::ObjectionRaised,
-        "NegativeResponse" => conversation::ConversationEvent::NegativeResponse,
-        "ThreatDetected" => conversation::ConversationEvent::ThreatDetected,
-        "Freeze" => conversation::ConversationEvent::Freeze,
-        _ => return Err((StatusCode::BAD_REQUEST, "Invalid event".to_string())),
-    };
+    // Parse state and event
+    let current_state = state.conversation_engine.parse_state(&req.current_state)
+        .map_err(|_| (StatusCode::BAD_REQUEST, "Invalid state".to_string()))?;
+    let event = state.conversation_engine.parse_event(&req.event)
+        .map_err(|_| (StatusCode::BAD_REQUEST, "Invalid event".to_string()))?;
     
     let new_state = state.conversation_engine.transition(&current_state, event);
-    let state_name = format!("{:?}", new_state);
     
     Ok(Json(TransitionResponse {
-        new_state: state_name,
+        new_state: new_state.to_string(),
+    }))
+}
+
+/// Get the state machine definition in use
+pub async fn get_state_machine(
+    State(state): State<AppState>,
+) -> Json<state_machine::StateMachineDefinition> {
+    Json(state.conversation_engine.table().definition().clone())
+}
+
+// ============== CONVERSATION ENTITY HANDLERS ==============
+
+#[derive(Debug, Deserialize)]
+pub struct CreateConversationRequest {
+    pub phone_number: String,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ConversationEventRequest {
+    pub event: String,
+    /// Product now in focus (kept if omitted)
+    #[serde(default)]
+    pub product_id: Option<String>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct ConversationEventResponse {
+    pub conversation: conversation::Conversation,
+    pub transition: conversation::TransitionRecord,
+}
+
+fn storage_error(e: anyhow::Error) -> (StatusCode, String) {
+    (StatusCode::INTERNAL_SERVER_ERROR, e.to_string())
+}
+
+fn load_conversation(state: &AppState, id: &str) -> Result<conversation::Conversation, (StatusCode, String)> {
+    state.storage.get_conversation(id)
+        .map_err(storage_error)?
+        .ok_or_else(|| (StatusCode::NOT_FOUND, "Conversation not found".to_string()))
+}
+
+/// Counts a misunderstanding; escalates the conversation when the count reaches the threshold
+/// Returns the escalation to persist, if any
+fn count_misunderstanding(state: &AppState, conversation: &mut conversation::Conversation) -> Option<conversation::TransitionRecord> {
+    let count = conversation.record_misunderstanding();
+    if !state.decision_engine.misunderstanding_limit_reached(count)
+        || state.conversation_engine.is_terminal_state(&conversation.state)
+    {
+        return None;
+    }
+    // A custom state machine may have no Escalated state: the decision still alerts a human
+    state.conversation_engine
+        .escalate(conversation, format!("{} consecutive misunderstandings", count))
+        .ok()
+}
+
+/// Applies `change` to a stored conversation in one storage transaction (re-read, change, save)
+fn update_conversation<F>(
+    state: &AppState,
+    id: &str,
+    change: F,
+) -> Result<(conversation::Conversation, Vec<conversation::TransitionRecord>), (StatusCode, String)>
+where
+    F: FnOnce(&mut conversation::Conversation) -> Vec<conversation::TransitionRecord>,
+{
+    state.storage.update_conversation(id, |conversation| Ok(change(conversation)))
+        .map_err(storage_error)?
+        .ok_or_else(|| (StatusCode::NOT_FOUND, "Conversation not found".to_string()))
+}
+
+/// Audits the transitions made for a reason (reopening, escalation), once they are saved
+fn audit_transitions(state: &AppState, transitions: &[conversation::TransitionRecord]) {
+    for transition in transitions.iter().filter(|t| t.reason.is_some()) {
+        let _ = state.audit_engine.log_transition(transition);
+        if transition.to_state == conversation::ConversationState::Escalated {
+            log::warn!("Conversation {} escalated: {}", transition.conversation_id, transition.reason.as_deref().unwrap_or_default());
+        }
+    }
+}
+
+/// Get the conversation for a phone number, creating it if needed
+pub async fn create_conversation(
+    State(state): State<AppState>,
+    Json(req): Json<CreateConversationRequest>,
+) -> Result<(StatusCode, Json<conversation::Conversation>), (StatusCode, String)> {
+    let conversation = conversation::Conversation::new(
+        &req.phone_number,
+        state.conversation_engine.initial_state(),
+    );
+    // Concurrent requests for the same number all get the one conversation stored
+    let (conversation, created) = state.storage.create_conversation(&conversation).map_err(storage_error)?;
+    let status = if created { StatusCode::CREATED } else { StatusCode::OK };
+    Ok((status, Json(conversation)))
+}
+
+/// Get conversation by ID
+pub async fn get_conversation(
+    State(state): State<AppState>,
+    Path(id): Path<String>,
+) -> Result<Json<conversation::Conversation>, (StatusCode, String)> {
+    load_conversation(&state, &id).map(Json)
+}
+
+/// Get the transition history of a conversation
+pub async fn get_conversation_history(
+    State(state): State<AppState>,
+    Path(id): Path<String>,
+) -> Result<Json<Vec<conversation::TransitionRecord>>, (StatusCode, String)> {
+    load_conversation(&state, &id)?;
+    state.storage.get_transitions(&id)
+        .map(Json)
+        .map_err(storage_error)
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ClassifyRequest {
+    pub message: String,
+}
+
+#[derive(Debug, Serialize)]
+pub struct ClassifyResponse {
+    pub classifications: Vec<classifier::Classification>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct InboundMessageResponse {
+    pub conversation: conversation::Conversation,
+    pub classifications: Vec<classifier::Classification>,
+    pub transitions: Vec<conversation::TransitionRecord>,
+    /// Alert sent to the operators for this message (threat, anger, legal situation)
+    pub alert: Option<alert::AlertTrigger>,
+}
+
+/// Classify a raw message into conversation events (no state change)
+pub async fn classify_message(
+    State(state): State<AppState>,
+    Json(req): Json<ClassifyRequest>,
+) -> Result<Json<ClassifyResponse>, (StatusCode, String)> {
+    let kb = state.knowledge_base.lock().await;
+    let classifications = state.event_classifier.classify(&req.message, &kb);
+    Ok(Json(ClassifyResponse { classifications }))
+}
+
+/// Detect the sentiment of a message (local lexicons, no state change)
+pub async fn analyze_sentiment(
+    State(state): State<AppState>,
+    Json(req): Json<ClassifyRequest>,
+) -> Result<Json<sentiment::SentimentAnalysis>, (StatusCode, String)> {
+    Ok(Json(state.sentiment_engine.analyze(&req.message)))
+}
+
+/// Classify an inbound message and apply the resulting events to a stored conversation
+pub async fn handle_inbound_message(
+    State(state): State<AppState>,
+    Path(id): Path<String>,
+    Query(account): Query<AccountQuery>,
+    Json(req): Json<ClassifyRequest>,
+) -> Result<Json<InboundMessageResponse>, (StatusCode, String)> {
+    let phone_number = load_conversation(&state, &id)?.phone_number;
+    let classifications = {
+        let kb = state.knowledge_base.lock().await;
+        state.event_classifier.classify(&req.message, &kb)
+    };
+    
+    // The contact replied: lifts the per-contact "awaiting reply" block of the account
+    account_quota(&state, &account)?.lock().await
+        .record_reply(&phone_number)
+        .map_err(storage_error)?;
+    
+    let (conversation, transitions) = update_conversation(&state, &id, |conversation| {
+        let mut transitions = vec![];
+        transitions.extend(state.lifecycle.reopen_on_inbound(&state.conversation_engine, conversation, chrono::Utc::now()));
+        
+        if let Some(product_id) = classifications.iter().find_map(|c| c.product_id.clone()) {
+            conversation.focus_product_id = Some(product_id);
+        }
+        // One event per message: the others would undo it ("trop cher" then the product keyword)
+        if let Some(primary) = classifier::EventClassifier::primary(&classifications) {
+            transitions.push(state.conversation_engine.apply(conversation, primary.event.clone()));
+        }
+        
+        // Any classified message is a successful exchange; an unclassified one
+        // ("Bonjour", "Merci") is not a misunderstanding by itself: only the
+        // fallback reply of the bot is counted, once per exchange
+        if !classifications.is_empty() {
+            conversation.reset_misunderstandings();
+        }
+        transitions
+    })?;
+    audit_transitions(&state, &transitions);
+    
+    // Operators are told without the contact noticing; a failed alert does not lose the message
+    let alert = state.alert_engine.detect_trigger(&req.message);
+    if let Some(trigger) = &alert {
+        if let Err(e) = state.alert_engine.send_alert(trigger.clone(), &conversation.id).await {
+            log::error!("Failed to send alert for conversation {}: {}", conversation.id, e);
+        }
+    }
+    
+    Ok(Json(InboundMessageResponse {
+        conversation,
+        classifications,
+        transitions,
+        alert,
+    }))
+}
+
+#[derive(Debug, Serialize)]
+pub struct MisunderstandingResponse {
+    pub conversation: conversation::Conversation,
+    /// Set when this misunderstanding escalated the conversation
+    pub escalation: Option<conversation::TransitionRecord>,
+}
+
+/// The bot sent a fallback reply ("I did not understand"): counts a misunderstanding
+pub async fn record_misunderstanding(
+    State(state): State<AppState>,
+    Path(id): Path<String>,
+) -> Result<Json<MisunderstandingResponse>, (StatusCode, String)> {
+    let (conversation, transitions) = update_conversation(&state, &id, |conversation| {
+        count_misunderstanding(&state, conversation).into_iter().collect()
+    })?;
+    audit_transitions(&state, &transitions);
+    
+    Ok(Json(MisunderstandingResponse {
+        conversation,
+        escalation: transitions.into_iter().next(),
+    }))
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ReleaseRequest {
+    /// State the conversation goes back to (must not be terminal)
+    pub state: String,
+    pub operator: String,
+    #[serde(default)]
+    pub reason: Option<String>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct LifecycleRunResponse {
+    pub transitions: Vec<conversation::TransitionRecord>,
+}
+
+/// Operator "release to automation" of an Escalated conversation
+pub async fn release_conversation(
+    State(state): State<AppState>,
+    Path(id): Path<String>,
+    Json(req): Json<ReleaseRequest>,
+) -> Result<Json<ConversationEventResponse>, (StatusCode, String)> {
+    let target = state.conversation_engine.parse_state(&req.state)
+        .map_err(|_| (StatusCode::BAD_REQUEST, "Invalid state".to_string()))?;
+    
+    // Checked and applied in one transaction: a concurrent change cannot be overwritten
+    let mut rejection = None;
+    let (conversation, transitions) = state.storage
+        .update_conversation(&id, |conversation| {
+            if conversation.state != conversation::ConversationState::Escalated {
+                let message = format!("Conversation is {}, not Escalated", conversation.state);
+                rejection = Some((StatusCode::CONFLICT, message.clone()));
+                return Err(anyhow::anyhow!(message));
+            }
+            state.conversation_engine
+                .release(conversation, target, &req.operator, req.reason)
+                .map(|transition| vec![transition])
+                .inspect_err(|e| rejection = Some((StatusCode::BAD_REQUEST, e.to_string())))
+        })
+        .map_err(|e| rejection.take().unwrap_or_else(|| storage_error(e)))?
+        .ok_or_else(|| (StatusCode::NOT_FOUND, "Conversation not found".to_string()))?;
+    let transition = transitions.into_iter().next().expect("one release applied");
+    let _ = state.audit_engine.log_transition(&transition);
+    
+    Ok(Json(ConversationEventResponse {
+        conversation,
+        transition,
+    }))
+}
+
+/// Run the lifecycle rules now (normally done by the scheduled job)
+pub async fn run_lifecycle(
+    State(state): State<AppState>,
+) -> Result<Json<LifecycleRunResponse>, (StatusCode, String)> {
+    let transitions = state.lifecycle
+        .run(&state.storage, &state.conversation_engine, &state.audit_engine, chrono::Utc::now())
+        .map_err(storage_error)?;
+    Ok(Json(LifecycleRunResponse { transitions }))
+}
+
+#[derive(Debug, Deserialize)]
+pub struct TakeoverRequest {
+    pub operator: String,
+    /// Lease length; the bot resumes when it expires
+    #[serde(default)]
+    pub lease_minutes: Option<i64>,
+}
+
+/// Claim (or renew) a conversation for a human operator
+pub async fn claim_takeover(
+    State(state): State<AppState>,
+    Path(id): Path<String>,
+    Json(req): Json<TakeoverRequest>,
+) -> Result<Json<conversation::Conversation>, (StatusCode, String)> {
+    let lease_minutes = req.lease_minutes.unwrap_or(conversation::DEFAULT_TAKEOVER_LEASE_MINUTES);
+    if lease_minutes <= 0 {
+        return Err((StatusCode::BAD_REQUEST, "lease_minutes must be positive".to_string()));
+    }
+    if lease_minutes > conversation::MAX_TAKEOVER_LEASE_MINUTES {
+        return Err((
+            StatusCode::BAD_REQUEST,
+            format!("lease_minutes must be at most {}", conversation::MAX_TAKEOVER_LEASE_MINUTES),
+        ));
+    }
+    let lease = chrono::TimeDelta::try_minutes(lease_minutes)
+        .ok_or_else(|| (StatusCode::BAD_REQUEST, "lease_minutes is out of range".to_string()))?;
+    if req.operator.trim().is_empty() {
+        return Err((StatusCode::BAD_REQUEST, "Operator is required".to_string()));
+    }
+    
+    let conversation = state.storage
+        .claim_takeover(&id, &req.operator, lease, chrono::Utc::now())
+        .map_err(storage_error)?
+        .ok_or_else(|| (StatusCode::NOT_FOUND, "Conversation not found".to_string()))?;
+    match &conversation.takeover {
+        Some(takeover) if takeover.operator == req.operator => {
+            log::info!("Takeover: conversation {} claimed by {}", conversation.id, req.operator);
+            Ok(Json(conversation))
+        }
+        Some(takeover) => Err((StatusCode::CONFLICT, format!("Conversation already handled by {}", takeover.operator))),
+        None => Err((StatusCode::INTERNAL_SERVER_ERROR, "Takeover not recorded".to_string())),
+    }
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ReleaseTakeoverQuery {
+    pub operator: String,
+}
+
+/// Hand a conversation back to the bot (only the operator holding it can)
+pub async fn release_takeover(
+    State(state): State<AppState>,
+    Path(id): Path<String>,
+    Query(req): Query<ReleaseTakeoverQuery>,
+) -> Result<Json<conversation::Conversation>, (StatusCode, String)> {
+    let now = chrono::Utc::now();
+    let conversation = state.storage.release_takeover(&id, &req.operator, now)
+        .map_err(storage_error)?
+        .ok_or_else(|| (StatusCode::NOT_FOUND, "Conversation not found".to_string()))?;
+    match &conversation.takeover {
+        Some(takeover) if takeover.expires_at > now => {
+            Err((StatusCode::CONFLICT, format!("Conversation handled by {}", takeover.operator)))
+        }
+        _ => {
+            log::info!("Takeover: conversation {} released by {}", conversation.id, req.operator);
+            Ok(Json(conversation))
+        }
+    }
+}
+
+/// List conversations currently under human control
+pub async fn list_takeovers(
+    State(state): State<AppState>,
+) -> Result<Json<Vec<conversation::Conversation>>, (StatusCode, String)> {
+    state.storage.find_conversations_under_takeover(chrono::Utc::now())
+        .map(Json)
+        .map_err(storage_error)
+}
+
+/// Apply an event to a stored conversation and record the transition
+pub async fn apply_conversation_event(
+    State(state): State<AppState>,
+    Path(id): Path<String>,
+    Json(req): Json<ConversationEventRequest>,
+) -> Result<Json<ConversationEventResponse>, (StatusCode, String)> {
+    let event = state.conversation_engine.parse_event(&req.event)
+        .map_err(|_| (StatusCode::BAD_REQUEST, "Invalid event".to_string()))?;
+    
+    let (conversation, transitions) = update_conversation(&state, &id, |conversation| {
+        if req.product_id.is_some() {
+            conversation.focus_product_id = req.product_id;
+        }
+        vec![state.conversation_engine.apply(conversation, event)]
+    })?;
+    
+    Ok(Json(ConversationEventResponse {
+        conversation,
+        transition: transitions.into_iter().next().expect("one event applied"),
     }))
 }
 
@@ -269,11 +1158,111 @@ pub async fn get_product(
     }
 }
 
+/// Replace the catalog (creates a new catalog version if content changed)
+pub async fn replace_products(
+    State(state): State<AppState>,
+    Json(products): Json<Vec<knowledge_base::Product>>,
+) -> Result<Json<CatalogVersionSummary>, (StatusCode, String)> {
+    let mut kb = state.knowledge_base.lock().await;
+    kb.load_products(products)
+        .map_err(|e| (StatusCode::BAD_REQUEST, e.to_string()))?;
+    
+    let current = kb.current_version()
+        .ok_or_else(|| (StatusCode::INTERNAL_SERVER_ERROR, "No catalog version".to_string()))?;
+    Ok(Json(CatalogVersionSummary::from(current)))
+}
+
+// ============== CATALOG VERSION HANDLERS ==============
+
+#[derive(Debug, Serialize)]
+pub struct CatalogVersionSummary {
+    pub version: u32,
+    pub hash: String,
+    pub created_at: String,
+    pub restored_from: Option<u32>,
+    pub product_count: usize,
+}
+
+impl From<&knowledge_base::CatalogVersion> for CatalogVersionSummary {
+    fn from(version: &knowledge_base::CatalogVersion) -> Self {
+        Self {
+            version: version.version,
+            hash: version.hash.clone(),
+            created_at: version.created_at.to_rfc3339(),
+            restored_from: version.restored_from,
+            product_count: version.products.len(),
+        }
+    }
+}
+
+#[derive(Debug, Deserialize)]
+pub struct CatalogDiffQuery {
+    pub from: u32,
+    pub to: u32,
+}
+
+/// List catalog versions
+pub async fn list_catalog_versions(
+    State(state): State<AppState>,
+) -> Result<Json<Vec<CatalogVersionSummary>>, (StatusCode, String)> {
+    let kb = state.knowledge_base.lock().await;
+    Ok(Json(kb.get_versions().iter().map(CatalogVersionSummary::from).collect()))
+}
+
+/// Get a full catalog snapshot
+pub async fn get_catalog_version(
+    State(state): State<AppState>,
+    Path(version): Path<u32>,
+) -> Result<Json<knowledge_base::CatalogVersion>, (StatusCode, String)> {
+    let kb = state.knowledge_base.lock().await;
+    
+    match kb.get_version(version) {
+        Some(snapshot) => Ok(Json(snapshot.clone())),
+        None => Err((StatusCode::NOT_FOUND, "Catalog version not found".to_string())),
+    }
+}
+
+/// Diff two catalog versions
+pub async fn diff_catalog_versions(
+    State(state): State<AppState>,
+    Query(query): Query<CatalogDiffQuery>,
+) -> Result<Json<knowledge_base::CatalogDiff>, (StatusCode, String)> {
+    let kb = state.knowledge_base.lock().await;
+    kb.diff_versions(query.from, query.to)
+        .map(Json)
+        .map_err(|e| (StatusCode::NOT_FOUND, e.to_string()))
+}
+
+/// Roll the catalog back to an earlier version
+pub async fn rollback_catalog(
+    State(state): State<AppState>,
+    Path(version): Path<u32>,
+) -> Result<Json<CatalogVersionSummary>, (StatusCode, String)> {
+    let mut kb = state.knowledge_base.lock().await;
+    kb.rollback_to(version)
+        .map_err(|e| (StatusCode::NOT_FOUND, e.to_string()))?;
+    
+    let current = kb.current_version()
+        .ok_or_else(|| (StatusCode::INTERNAL_SERVER_ERROR, "No catalog version".to_string()))?;
+    Ok(Json(CatalogVersionSummary::from(current)))
+}
+
 /// Log audit entry
 pub async fn log_audit(
     State(state): State<AppState>,
-    Json(log): Json<audit::AuditLog>,
+    Json(mut log): Json<audit::AuditLog>,
 ) -> Result<StatusCode, (StatusCode, String)> {
+    // The catalog version comes from the decision (it may have changed since); its hash is filled in here
+    if let Some(version) = log.catalog_version {
+        let kb = state.knowledge_base.lock().await;
+        let snapshot = kb.get_version(version)
+            .ok_or_else(|| (StatusCode::BAD_REQUEST, format!("Unknown catalog version: {}", version)))?;
+        if log.catalog_hash.as_ref().is_some_and(|hash| *hash != snapshot.hash) {
+            return Err((StatusCode::BAD_REQUEST, format!("Catalog hash does not match version {}", version)));
+        }
+        log.catalog_hash = Some(snapshot.hash.clone());
+    }
+    
     state.audit_engine.log_message_flow(log)
         .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
     
@@ -284,6 +1273,7 @@ pub async fn log_audit(
 
 #[derive(Debug, Serialize)]
 pub struct QuotaResetResponse {
+    pub account: String,
     pub reset_type: String,
     pub timestamp: String,
 }
@@ -291,21 +1281,22 @@ pub struct QuotaResetResponse {
 /// Manual daily quota reset
 pub async fn reset_daily_quota(
     State(state): State<AppState>,
+    Query(account): Query<AccountQuery>,
 ) -> Result<Json<QuotaResetResponse>, (StatusCode, String)> {
-    let mut quota = state.quota_engine.lock().await;
+    let quota = account_quota(&state, &account)?;
+    let mut quota = quota.lock().await;
     quota.reset_daily();
     
     // Record reset metric
     crate::api::metrics::QUOTA_RESETS_TOTAL
-        .with_label_values(&["daily"])
+        .with_label_values(&[quota.account(), "daily"])
         .inc();
     
     // Update quota gauges
-    let usage = quota.get_usage();
-    crate::api::metrics::QUOTA_MESSAGES_TODAY.set(usage.messages_today as f64);
-    crate::api::metrics::QUOTA_IMAGES_TODAY.set(usage.images_today as f64);
+    crate::api::metrics::publish_quota(&quota, chrono::Utc::now());
     
     Ok(Json(QuotaResetResponse {
+        account: quota.account().to_string(),
         reset_type: "daily".to_string(),
         timestamp: chrono::Utc::now().to_rfc3339(),
     }))
@@ -314,21 +1305,22 @@ pub async fn reset_daily_quota(
 /// Manual weekly quota reset
 pub async fn reset_weekly_quota(
     State(state): State<AppState>,
+    Query(account): Query<AccountQuery>,
 ) -> Result<Json<QuotaResetResponse>, (StatusCode, String)> {
-    let mut quota = state.quota_engine.lock().await;
+    let quota = account_quota(&state, &account)?;
+    let mut quota = quota.lock().await;
     quota.reset_weekly();
     
     // Record reset metric
     crate::api::metrics::QUOTA_RESETS_TOTAL
-        .with_label_values(&["weekly"])
+        .with_label_values(&[quota.account(), "weekly"])
         .inc();
     
     // Update quota gauges
-    let usage = quota.get_usage();
-    crate::api::metrics::QUOTA_MESSAGES_WEEK.set(usage.messages_this_week as f64);
-    crate::api::metrics::QUOTA_VIDEOS_WEEK.set(usage.videos_this_week as f64);
+    crate::api::metrics::publish_quota(&quota, chrono::Utc::now());
     
     Ok(Json(QuotaResetResponse {
+        account: quota.account().to_string(),
         reset_type: "weekly".to_string(),
         timestamp: chrono::Utc::now().to_rfc3339(),
     }))
@@ -337,6 +1329,7 @@ pub async fn reset_weekly_quota(
 /// Get quota status
 #[derive(Debug, Serialize)]
 pub struct QuotaStatusResponse {
+    pub account: String,
     pub messages_today: u32,
     pub messages_this_week: u32,
     pub images_today: u32,
@@ -344,35 +1337,239 @@ pub struct QuotaStatusResponse {
     pub last_reset: String,
     pub needs_daily_reset: bool,
     pub needs_weekly_reset: bool,
+    /// Business timezone of the quota periods (`ActiveHours.timezone`)
+    pub timezone: String,
+    pub next_daily_reset: chrono::DateTime<chrono::Utc>,
+    pub next_weekly_reset: chrono::DateTime<chrono::Utc>,
+    /// Share of the configured limits currently allowed (1.0 = not throttled)
+    pub throttle_factor: f64,
+    /// Share of the contacts messaged in the last 24h still waiting to reply
+    pub unanswered_ratio: Option<f64>,
+    /// Limits enforced right now, after warm-up and throttling
+    pub effective_limits: crate::engines::quota::QuotaLimits,
+    pub warmup: crate::engines::quota::WarmupStatus,
 }
 
 pub async fn get_quota_status(
     State(state): State<AppState>,
+    Query(account): Query<AccountQuery>,
 ) -> Result<Json<QuotaStatusResponse>, (StatusCode, String)> {
-    let quota = state.quota_engine.lock().await;
+    let quota = account_quota(&state, &account)?;
+    let quota = quota.lock().await;
+    Ok(Json(quota_status(&quota, chrono::Utc::now())))
+}
+
+/// Status of every loaded account, sorted by account ID
+pub async fn list_quota_accounts(
+    State(state): State<AppState>,
+) -> Result<Json<Vec<QuotaStatusResponse>>, (StatusCode, String)> {
+    let now = chrono::Utc::now();
+    let mut statuses = vec![];
+    for (_, quota) in state.quotas.accounts() {
+        statuses.push(quota_status(&*quota.lock().await, now));
+    }
+    Ok(Json(statuses))
+}
+
+/// Builds the status of one account and refreshes its gauges
+fn quota_status(quota: &QuotaEngine, now: chrono::DateTime<chrono::Utc>) -> QuotaStatusResponse {
     let usage = quota.get_usage();
     
     // Update quota gauges for monitoring
-    crate::api::metrics::QUOTA_MESSAGES_TODAY.set(usage.messages_today as f64);
-    crate::api::metrics::QUOTA_MESSAGES_WEEK.set(usage.messages_this_week as f64);
-    crate::api::metrics::QUOTA_IMAGES_TODAY.set(usage.images_today as f64);
-    crate::api::metrics::QUOTA_VIDEOS_WEEK.set(usage.videos_this_week as f64);
+    crate::api::metrics::publish_quota(quota, now);
     
-    Ok(Json(QuotaStatusResponse {
+    QuotaStatusResponse {
+        account: quota.account().to_string(),
         messages_today: usage.messages_today,
         messages_this_week: usage.messages_this_week,
         images_today: usage.images_today,
         videos_this_week: usage.videos_this_week,
         last_reset: usage.last_reset.to_rfc3339(),
-        needs_daily_reset: quota.needs_daily_reset(),
-        needs_weekly_reset: quota.needs_weekly_reset(),
-    }))
+        needs_daily_reset: quota.needs_daily_reset_at(now),
+        needs_weekly_reset: quota.needs_weekly_reset_at(now),
+        timezone: quota.calendar().timezone().to_string(),
+        next_daily_reset: quota.calendar().next_day(now),
+        next_weekly_reset: quota.calendar().next_week(now),
+        throttle_factor: quota.throttle_factor(now),
+        unanswered_ratio: quota.unanswered_ratio(now),
+        effective_limits: quota.effective_limits(now),
+        warmup: quota.warmup_status(now),
+    }
+}
+
+/// Start the warm-up of a newly connected number
+pub async fn start_quota_warmup(
+    State(state): State<AppState>,
+    Query(account): Query<AccountQuery>,
+    req: Option<Json<WarmupRequest>>,
+) -> Result<Json<crate::engines::quota::WarmupStatus>, (StatusCode, String)> {
+    let quota = account_quota(&state, &account)?;
+    let mut quota = quota.lock().await;
+    let now = chrono::Utc::now();
+    let connected_at = req.and_then(|Json(req)| req.connected_at).unwrap_or(now);
+    if connected_at > now {
+        return Err((StatusCode::BAD_REQUEST, "connected_at is in the future".to_string()));
+    }
+    
+    quota.set_connected_at(Some(connected_at))
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+    crate::api::metrics::publish_quota(&quota, now);
+    
+    Ok(Json(quota.warmup_status(now)))
 }
 
 // ============== METRICS HANDLER ==============
 
 /// Prometheus metrics endpoint
-pub async fn metrics() -> Result<String, (StatusCode, String)> {
+/// Funnel gauges are the ones published by the funnel job, not recomputed on each scrape
+pub async fn metrics(State(state): State<AppState>) -> Result<String, (StatusCode, String)> {
+    // Throttling recovers and warm-up progresses over time, not only on requests
+    let now = chrono::Utc::now();
+    for (_, quota) in state.quotas.accounts() {
+        crate::api::metrics::publish_quota(&*quota.lock().await, now);
+    }
+    
     crate::api::metrics::gather_metrics()
         .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))
 }
+
+// ============== ANALYTICS HANDLERS ==============
+
+/// Computes the funnel from the whole history and publishes it as gauges
+pub(crate) fn refresh_funnel(state: &AppState) -> anyhow::Result<analytics::FunnelReport> {
+    let conversations = state.storage.list_conversations()?;
+    let transitions = state.storage.get_all_transitions()?;
+    let report = state.analytics.funnel(&conversations, &transitions);
+    crate::api::metrics::publish_funnel(&report);
+    Ok(report)
+}
+
+/// Conversation funnel computed from the transition history
+pub async fn get_funnel(
+    State(state): State<AppState>,
+) -> Result<Json<analytics::FunnelReport>, (StatusCode, String)> {
+    refresh_funnel(&state).map(Json).map_err(storage_error)
+}
+
+// ============== SEND SCHEDULING HANDLERS ==============
+
+#[derive(Debug, Deserialize)]
+pub struct SendPlanRequest {
+    pub text: String,
+    /// When the inbound message arrived; defaults to now
+    #[serde(default)]
+    pub start_at: Option<chrono::DateTime<chrono::Utc>>,
+}
+
+/// Timeline of gateway actions (mark-read, composing, send) for a reply
+pub async fn plan_send(
+    State(state): State<AppState>,
+    Json(req): Json<SendPlanRequest>,
+) -> Result<Json<send_scheduler::SendPlan>, (StatusCode, String)> {
+    if req.text.trim().is_empty() {
+        return Err((StatusCode::BAD_REQUEST, "Text is empty".to_string()));
+    }
+    let start = req.start_at.unwrap_or_else(chrono::Utc::now);
+    Ok(Json(state.send_scheduler.plan(&req.text, start)))
+}
+
+// ============== OUTBOUND QUEUE HANDLERS ==============
+
+#[derive(Debug, Deserialize)]
+pub struct EnqueueOutboundRequest {
+    /// Retrying a request with the same key does not queue the reply twice
+    pub idempotency_key: String,
+    pub text: String,
+    #[serde(default)]
+    pub media_id: Option<String>,
+    #[serde(default)]
+    pub is_video: bool,
+    /// Send time; takes precedence over `delay_seconds`
+    #[serde(default)]
+    pub send_at: Option<chrono::DateTime<chrono::Utc>>,
+    /// Delay from now, e.g. the seconds of a Delay action
+    #[serde(default)]
+    pub delay_seconds: Option<u64>,
+    /// WhatsApp account (instance ID) that sends the reply; the default account when omitted
+    #[serde(default)]
+    pub account: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct OutboundListQuery {
+    #[serde(default)]
+    pub status: Option<String>,
+}
+
+/// Longest `delay_seconds` accepted for a queued reply (30 days)
+const MAX_OUTBOUND_DELAY_SECONDS: u64 = 30 * 24 * 3600;
+
+/// Queue a reply for delayed delivery (201 if queued, 200 if the key was already queued)
+pub async fn enqueue_outbound(
+    State(state): State<AppState>,
+    Path(id): Path<String>,
+    Json(req): Json<EnqueueOutboundRequest>,
+) -> Result<(StatusCode, Json<outbound::OutboundMessage>), (StatusCode, String)> {
+    let conversation = load_conversation(&state, &id)?;
+    if req.idempotency_key.trim().is_empty() {
+        return Err((StatusCode::BAD_REQUEST, "idempotency_key is required".to_string()));
+    }
+    let account = req.account.unwrap_or_else(|| quota::DEFAULT_ACCOUNT.to_string());
+    known_account(&state, &account)?;
+    
+    let payload = match req.media_id {
+        Some(media_id) => outbound::OutboundPayload::Media {
+            text: req.text,
+            media_id,
+            is_video: req.is_video,
+        },
+        None => outbound::OutboundPayload::Text { text: req.text },
+    };
+    let scheduled_at = match req.send_at {
+        Some(send_at) => send_at,
+        None => {
+            let delay = req.delay_seconds.unwrap_or(0);
+            if delay > MAX_OUTBOUND_DELAY_SECONDS {
+                return Err((StatusCode::BAD_REQUEST, format!("delay_seconds must be at most {}", MAX_OUTBOUND_DELAY_SECONDS)));
+            }
+            chrono::TimeDelta::try_seconds(delay as i64)
+                .and_then(|delay| chrono::Utc::now().checked_add_signed(delay))
+                .ok_or_else(|| (StatusCode::BAD_REQUEST, "delay_seconds is out of range".to_string()))?
+        }
+    };
+    
+    let (message, created) = state.outbound_queue
+        .enqueue(&conversation, &account, &req.idempotency_key, payload, scheduled_at)
+        .map_err(storage_error)?;
+    let status = if created { StatusCode::CREATED } else { StatusCode::OK };
+    Ok((status, Json(message)))
+}
+
+/// List queued replies, optionally filtered by status
+pub async fn list_outbound(
+    State(state): State<AppState>,
+    Query(query): Query<OutboundListQuery>,
+) -> Result<Json<Vec<outbound::OutboundMessage>>, (StatusCode, String)> {
+    let status = query.status
+        .map(|s| s.parse::<outbound::OutboundStatus>())
+        .transpose()
+        .map_err(|e| (StatusCode::BAD_REQUEST, e.to_string()))?;
+    state.outbound_queue.list(status).map(Json).map_err(storage_error)
+}
+
+/// Cancel a pending reply (409 if already sent, failed or cancelled)
+pub async fn cancel_outbound(
+    State(state): State<AppState>,
+    Path(id): Path<String>,
+) -> Result<Json<outbound::OutboundMessage>, (StatusCode, String)> {
+    let message = state.outbound_queue.get(&id)
+        .map_err(storage_error)?
+        .ok_or_else(|| (StatusCode::NOT_FOUND, format!("Outbound message {} not found", id)))?;
+    if message.status != outbound::OutboundStatus::Pending {
+        return Err((StatusCode::CONFLICT, format!("Outbound message {} is {}", id, message.status)));
+    }
+    state.outbound_queue.cancel(&id, "Cancelled through the API")
+        .map_err(storage_error)?
+        .map(Json)
+        .ok_or_else(|| (StatusCode::NOT_FOUND, format!("Outbound message {} not found", id)))
+}
@@ -1,5 +1,5 @@
 use prometheus::{
-    Counter, CounterVec, Gauge, GaugeVec, Histogram, HistogramVec, Registry, Encoder, TextEncoder,
+    Counter, CounterVec, GaugeVec, Histogram, HistogramVec, Registry, Encoder, TextEncoder,
     Opts, HistogramOpts,
 };
 use lazy_static::lazy_static;
@@ -19,40 +19,72 @@ lazy_static! {
 
     /// Current conversation state distribution
     pub static ref CONVERSATION_STATE: GaugeVec = GaugeVec::new(
-        Opts::new("sellify_conversation_state", "Current conversation state distribution"),
+        Opts::new("sellify_conversation_state", "Number of conversations currently in each state"),
         &["state"]
     ).expect("Failed to create CONVERSATION_STATE metric");
 
+    // ============== FUNNEL ANALYTICS METRICS ==============
+    
+    /// Conversion rate between two states
+    pub static ref FUNNEL_CONVERSION_RATE: GaugeVec = GaugeVec::new(
+        Opts::new("sellify_funnel_conversion_rate", "Share of conversations that entered a state and moved to another"),
+        &["from", "to"]
+    ).expect("Failed to create FUNNEL_CONVERSION_RATE metric");
+
+    /// Median time spent in each state
+    pub static ref STATE_MEDIAN_SECONDS: GaugeVec = GaugeVec::new(
+        Opts::new("sellify_state_median_seconds", "Median duration of completed stays in each state"),
+        &["state"]
+    ).expect("Failed to create STATE_MEDIAN_SECONDS metric");
+
+    /// Objections raised per product
+    pub static ref PRODUCT_OBJECTIONS: GaugeVec = GaugeVec::new(
+        Opts::new("sellify_product_objections", "Objections raised per product"),
+        &["product"]
+    ).expect("Failed to create PRODUCT_OBJECTIONS metric");
+
     // ============== QUOTA ENGINE METRICS ==============
     
     /// Current quota usage for messages today
-    pub static ref QUOTA_MESSAGES_TODAY: Gauge = Gauge::new(
-        "sellify_quota_messages_today",
-        "Current number of messages sent today"
+    pub static ref QUOTA_MESSAGES_TODAY: GaugeVec = GaugeVec::new(
+        Opts::new("sellify_quota_messages_today", "Current number of messages sent today"),
+        &["account"]
     ).expect("Failed to create QUOTA_MESSAGES_TODAY metric");
 
     /// Current quota usage for messages this week
-    pub static ref QUOTA_MESSAGES_WEEK: Gauge = Gauge::new(
-        "sellify_quota_messages_week",
-        "Current number of messages sent this week"
+    pub static ref QUOTA_MESSAGES_WEEK: GaugeVec = GaugeVec::new(
+        Opts::new("sellify_quota_messages_week", "Current number of messages sent this week"),
+        &["account"]
     ).expect("Failed to create QUOTA_MESSAGES_WEEK metric");
 
     /// Current quota usage for images today
-    pub static ref QUOTA_IMAGES_TODAY: Gauge = Gauge::new(
-        "sellify_quota_images_today",
-        "Current number of images sent today"
+    pub static ref QUOTA_IMAGES_TODAY: GaugeVec = GaugeVec::new(
+        Opts::new("sellify_quota_images_today", "Current number of images sent today"),
+        &["account"]
     ).expect("Failed to create QUOTA_IMAGES_TODAY metric");
 
     /// Current quota usage for videos this week
-    pub static ref QUOTA_VIDEOS_WEEK: Gauge = Gauge::new(
-        "sellify_quota_videos_week",
-        "Current number of videos sent this week"
+    pub static ref QUOTA_VIDEOS_WEEK: GaugeVec = GaugeVec::new(
+        Opts::new("sellify_quota_videos_week", "Current number of videos sent this week"),
+        &["account"]
     ).expect("Failed to create QUOTA_VIDEOS_WEEK metric");
 
+    /// Share of the configured limits currently allowed (1.0 = not throttled)
+    pub static ref QUOTA_THROTTLE_FACTOR: GaugeVec = GaugeVec::new(
+        Opts::new("sellify_quota_throttle_factor", "Adaptive throttling factor applied to the quota limits"),
+        &["account"]
+    ).expect("Failed to create QUOTA_THROTTLE_FACTOR metric");
+
+    /// Limits after warm-up and adaptive throttling
+    pub static ref QUOTA_EFFECTIVE_LIMIT: GaugeVec = GaugeVec::new(
+        Opts::new("sellify_quota_effective_limit", "Quota limits currently enforced, after warm-up and throttling"),
+        &["account", "limit"]
+    ).expect("Failed to create QUOTA_EFFECTIVE_LIMIT metric");
+
     /// Total quota resets
     pub static ref QUOTA_RESETS_TOTAL: CounterVec = CounterVec::new(
         Opts::new("sellify_quota_resets_total", "Total quota resets by type"),
-        &["reset_type"]
+        &["account", "reset_type"]
     ).expect("Failed to create QUOTA_RESETS_TOTAL metric");
 
     /// Quota limit reached events
@@ -129,11 +161,18 @@ pub fn init_metrics() -> Result<()> {
     REGISTRY.register(Box::new(DECISIONS_TOTAL.clone()))?;
     REGISTRY.register(Box::new(CONVERSATION_STATE.clone()))?;
 
+    // Funnel analytics metrics
+    REGISTRY.register(Box::new(FUNNEL_CONVERSION_RATE.clone()))?;
+    REGISTRY.register(Box::new(STATE_MEDIAN_SECONDS.clone()))?;
+    REGISTRY.register(Box::new(PRODUCT_OBJECTIONS.clone()))?;
+
     // Quota Engine metrics
     REGISTRY.register(Box::new(QUOTA_MESSAGES_TODAY.clone()))?;
     REGISTRY.register(Box::new(QUOTA_MESSAGES_WEEK.clone()))?;
     REGISTRY.register(Box::new(QUOTA_IMAGES_TODAY.clone()))?;
     REGISTRY.register(Box::new(QUOTA_VIDEOS_WEEK.clone()))?;
+    REGISTRY.register(Box::new(QUOTA_THROTTLE_FACTOR.clone()))?;
+    REGISTRY.register(Box::new(QUOTA_EFFECTIVE_LIMIT.clone()))?;
     REGISTRY.register(Box::new(QUOTA_RESETS_TOTAL.clone()))?;
     REGISTRY.register(Box::new(QUOTA_LIMIT_REACHED.clone()))?;
 
@@ -157,6 +196,58 @@ pub fn init_metrics() -> Result<()> {
     Ok(())
 }
 
+/// Publish a funnel report, replacing the previous values
+/// (labels that disappeared, e.g. an emptied state, are dropped)
+pub fn publish_funnel(report: &crate::engines::analytics::FunnelReport) {
+    CONVERSATION_STATE.reset();
+    for (state, count) in &report.state_distribution {
+        CONVERSATION_STATE.with_label_values(&[state]).set(*count as f64);
+    }
+
+    FUNNEL_CONVERSION_RATE.reset();
+    for conversion in &report.conversions {
+        FUNNEL_CONVERSION_RATE
+            .with_label_values(&[&conversion.from, &conversion.to])
+            .set(conversion.rate);
+    }
+
+    STATE_MEDIAN_SECONDS.reset();
+    for (state, seconds) in &report.median_seconds_in_state {
+        STATE_MEDIAN_SECONDS.with_label_values(&[state]).set(*seconds as f64);
+    }
+
+    PRODUCT_OBJECTIONS.reset();
+    for (product, count) in &report.objections_per_product {
+        PRODUCT_OBJECTIONS.with_label_values(&[product]).set(*count as f64);
+    }
+}
+
+/// Publish the usage, throttling factor and effective limits of one account
+pub fn publish_quota(quota: &crate::engines::quota::QuotaEngine, now: chrono::DateTime<chrono::Utc>) {
+    let account = quota.account();
+    let usage = quota.get_usage();
+    QUOTA_MESSAGES_TODAY.with_label_values(&[account]).set(usage.messages_today as f64);
+    QUOTA_MESSAGES_WEEK.with_label_values(&[account]).set(usage.messages_this_week as f64);
+    QUOTA_IMAGES_TODAY.with_label_values(&[account]).set(usage.images_today as f64);
+    QUOTA_VIDEOS_WEEK.with_label_values(&[account]).set(usage.videos_this_week as f64);
+
+    QUOTA_THROTTLE_FACTOR.with_label_values(&[account]).set(quota.throttle_factor(now));
+    let limits = quota.effective_limits(now);
+    for (limit, value) in [
+        ("messages_per_day", limits.messages_per_day as f64),
+        ("messages_per_week", limits.messages_per_week as f64),
+        ("messages_per_hour", limits.messages_per_hour as f64),
+        ("images_per_day", limits.images_per_day as f64),
+        ("videos_per_week", limits.videos_per_week as f64),
+        ("messages_per_contact_per_hour", limits.messages_per_contact_per_hour as f64),
+        ("new_contacts_per_day", limits.new_contacts_per_day as f64),
+        ("min_gap_seconds", limits.min_gap_seconds as f64),
+        ("media_cooldown_seconds", limits.media_cooldown_seconds as f64),
+    ] {
+        QUOTA_EFFECTIVE_LIMIT.with_label_values(&[account, limit]).set(value);
+    }
+}
+
 /// Gather all metrics in Prometheus text format
 pub fn gather_metrics() -> Result<String> {
     let encoder = TextEncoder::new();
@@ -188,8 +279,23 @@ mod tests {
 
     #[test]
     fn test_quota_gauge_set() {
-        QUOTA_MESSAGES_TODAY.set(42.0);
-        assert_eq!(QUOTA_MESSAGES_TODAY.get(), 42.0);
+        QUOTA_MESSAGES_TODAY.with_label_values(&["default"]).set(42.0);
+        assert_eq!(QUOTA_MESSAGES_TODAY.with_label_values(&["default"]).get(), 42.0);
+    }
+
+    #[test]
+    fn test_quota_gauges_per_account() {
+        let accounts = crate::engines::QuotaAccounts::new(
+            crate::engines::quota::QuotaLimits::default(),
+            std::collections::HashMap::from([("metrics-test".to_string(), crate::engines::quota::QuotaLimits::default())]),
+            crate::engines::quota::QuotaCalendar::default(),
+        );
+        let quota = accounts.get("metrics-test").unwrap().unwrap();
+        let mut quota = quota.blocking_lock();
+        quota.record_message(None).unwrap();
+        publish_quota(&quota, chrono::Utc::now());
+        assert_eq!(QUOTA_MESSAGES_TODAY.with_label_values(&["metrics-test"]).get(), 1.0);
+        assert_eq!(QUOTA_THROTTLE_FACTOR.with_label_values(&["metrics-test"]).get(), 1.0);
     }
 
     #[test]
@@ -213,8 +319,8 @@ mod tests {
 
     #[test]
     fn test_quota_reset_counter() {
-        QUOTA_RESETS_TOTAL.with_label_values(&["daily"]).inc();
-        let value = QUOTA_RESETS_TOTAL.with_label_values(&["daily"]).get();
+        QUOTA_RESETS_TOTAL.with_label_values(&["default", "daily"]).inc();
+        let value = QUOTA_RESETS_TOTAL.with_label_values(&["default", "daily"]).get();
         assert!(value >= 1.0);
     }
 }
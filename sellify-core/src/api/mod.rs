@@ -24,7 +24,7 @@ pub mod scheduler;
 pub mod metrics;
 
 #[cfg(feature = "http-server")]
-pub use server::create_app;
+pub use server::{create_app, create_app_state, create_app_state_with_config, create_app_with_state};
 
 #[cfg(feature = "http-server")]
-pub use scheduler::{QuotaScheduler, setup_auto_reset, SharedQuotaEngine};
+pub use scheduler::{QuotaScheduler, setup_auto_reset, setup_funnel_job, setup_lifecycle_job, setup_outbound_worker, SharedQuotaEngine};
@@ -1,6 +1,6 @@
 use axum::{
     Router,
-    routing::{get, post},
+    routing::{delete, get, post},
 };
 use crate::api::handlers::{self, AppState};
 
@@ -15,6 +15,9 @@ pub fn create_router() -> Router<AppState> {
         
         // Decision Engine routes
         .route("/api/v1/decision", post(handlers::make_decision))
+        .route("/api/v1/decision/policy", get(handlers::get_decision_policy))
+        .route("/api/v1/decision/simulate", post(handlers::simulate_decision))
+        .route("/api/v2/decision", post(handlers::make_decision_v2))
         
         // Validation routes
         .route("/api/v1/validate", post(handlers::validate_text))
@@ -23,15 +26,46 @@ pub fn create_router() -> Router<AppState> {
         .route("/api/v1/quota/check", post(handlers::check_quota))
         .route("/api/v1/quota/record", post(handlers::record_message))
         .route("/api/v1/quota/status", get(handlers::get_quota_status))
+        .route("/api/v1/quota/accounts", get(handlers::list_quota_accounts))
+        .route("/api/v1/quota/feedback", post(handlers::record_quota_feedback))
+        .route("/api/v1/quota/warmup", post(handlers::start_quota_warmup))
         .route("/api/v1/quota/reset/daily", post(handlers::reset_daily_quota))
         .route("/api/v1/quota/reset/weekly", post(handlers::reset_weekly_quota))
         
+        // Send scheduling routes
+        .route("/api/v1/send/plan", post(handlers::plan_send))
+        
+        // Outbound queue routes
+        .route("/api/v1/conversations/:id/outbound", post(handlers::enqueue_outbound))
+        .route("/api/v1/outbound", get(handlers::list_outbound))
+        .route("/api/v1/outbound/:id", delete(handlers::cancel_outbound))
+        
         // Conversation routes
         .route("/api/v1/conversation/transition", post(handlers::transition_state))
+        .route("/api/v1/conversation/state-machine", get(handlers::get_state_machine))
+        .route("/api/v1/conversations", post(handlers::create_conversation))
+        .route("/api/v1/conversations/:id", get(handlers::get_conversation))
+        .route("/api/v1/conversations/:id/history", get(handlers::get_conversation_history))
+        .route("/api/v1/conversations/:id/events", post(handlers::apply_conversation_event))
+        .route("/api/v1/conversations/:id/messages", post(handlers::handle_inbound_message))
+        .route("/api/v1/conversations/:id/misunderstandings", post(handlers::record_misunderstanding))
+        .route("/api/v1/conversations/:id/release", post(handlers::release_conversation))
+        .route("/api/v1/conversations/:id/takeover", post(handlers::claim_takeover).delete(handlers::release_takeover))
+        .route("/api/v1/takeovers", get(handlers::list_takeovers))
+        .route("/api/v1/conversation/lifecycle/run", post(handlers::run_lifecycle))
+        .route("/api/v1/conversation/classify", post(handlers::classify_message))
+        .route("/api/v1/conversation/sentiment", post(handlers::analyze_sentiment))
         
         // Knowledge Base routes
-        .route("/api/v1/products", get(handlers::list_products))
+        .route("/api/v1/products", get(handlers::list_products).put(handlers::replace_products))
         .route("/api/v1/products/:id", get(handlers::get_product))
+        .route("/api/v1/catalog/versions", get(handlers::list_catalog_versions))
+        .route("/api/v1/catalog/versions/:version", get(handlers::get_catalog_version))
+        .route("/api/v1/catalog/diff", get(handlers::diff_catalog_versions))
+        .route("/api/v1/catalog/rollback/:version", post(handlers::rollback_catalog))
+        
+        // Analytics routes
+        .route("/api/v1/analytics/funnel", get(handlers::get_funnel))
         
         // Audit routes
         .route("/api/v1/audit/log", post(handlers::log_audit))
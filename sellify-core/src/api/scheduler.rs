@@ -3,6 +3,8 @@ use tokio::sync::Mutex;
 use tokio_cron_scheduler::{Job, JobScheduler};
 use anyhow::Result;
 
+use crate::engines::quota_accounts::QuotaAccounts;
+
 /// Quota reset scheduler - handles daily and weekly resets
 pub struct QuotaScheduler {
     scheduler: JobScheduler,
@@ -15,47 +17,24 @@ impl QuotaScheduler {
         Ok(Self { scheduler })
     }
 
-    /// Start daily reset job (00:00 UTC)
-    /// Resets: messages_today, images_today
-    pub async fn start_daily_reset<F>(&mut self, callback: F) -> Result<()>
+    /// Start the period rollover job (every minute)
+    /// Cron schedules use a fixed UTC offset, which drifts across DST changes,
+    /// so the callback checks whether a local day or week started instead
+    pub async fn start_rollover<F>(&mut self, callback: F) -> Result<()>
     where
         F: Fn() + Send + Sync + 'static,
     {
         let callback = Arc::new(callback);
         
-        let job = Job::new_async("0 0 0 * * *", move |_uuid, _l| {
+        let job = Job::new_async("0 * * * * *", move |_uuid, _l| {
             let callback = Arc::clone(&callback);
             Box::pin(async move {
-                log::info!("🔄 Running daily quota reset (00:00 UTC)");
                 callback();
-                log::info!("✅ Daily quota reset completed");
             })
         })?;
 
         self.scheduler.add(job).await?;
-        log::info!("📅 Daily reset job scheduled (00:00 UTC)");
-        Ok(())
-    }
-
-    /// Start weekly reset job (Monday 00:00 UTC)
-    /// Resets: messages_this_week, videos_this_week
-    pub async fn start_weekly_reset<F>(&mut self, callback: F) -> Result<()>
-    where
-        F: Fn() + Send + Sync + 'static,
-    {
-        let callback = Arc::new(callback);
-        
-        let job = Job::new_async("0 0 0 * * Mon", move |_uuid, _l| {
-            let callback = Arc::clone(&callback);
-            Box::pin(async move {
-                log::info!("🔄 Running weekly quota reset (Monday 00:00 UTC)");
-                callback();
-                log::info!("✅ Weekly quota reset completed");
-            })
-        })?;
-
-        self.scheduler.add(job).await?;
-        log::info!("📅 Weekly reset job scheduled (Monday 00:00 UTC)");
+        log::info!("📅 Quota rollover job scheduled (every minute)");
         Ok(())
     }
 
@@ -77,60 +56,132 @@ impl QuotaScheduler {
 /// Shared state for quota engine with thread-safe access
 pub type SharedQuotaEngine = Arc<Mutex<crate::engines::quota::QuotaEngine>>;
 
-/// Setup automatic quota resets for a quota engine
+/// Setup automatic quota resets for every account
 /// Returns the scheduler (must be kept alive)
-pub async fn setup_auto_reset(quota_engine: SharedQuotaEngine) -> Result<QuotaScheduler> {
+pub async fn setup_auto_reset(quotas: Arc<QuotaAccounts>) -> Result<QuotaScheduler> {
     let mut scheduler = QuotaScheduler::new().await?;
 
-    // Daily reset job
+    // Daily and weekly resets at local midnight / Monday in the business timezone
     {
-        let quota_engine = Arc::clone(&quota_engine);
+        let quotas = Arc::clone(&quotas);
         scheduler
-            .start_daily_reset(move || {
-                let quota_engine = Arc::clone(&quota_engine);
+            .start_rollover(move || {
+                let quotas = Arc::clone(&quotas);
                 tokio::spawn(async move {
-                    let mut engine = quota_engine.lock().await;
-                    engine.reset_daily();
-                    
-                    // Record metric
-                    crate::api::metrics::QUOTA_RESETS_TOTAL
-                        .with_label_values(&["daily"])
-                        .inc();
-                    
-                    // Update gauges
-                    let usage = engine.get_usage();
-                    crate::api::metrics::QUOTA_MESSAGES_TODAY.set(usage.messages_today as f64);
-                    crate::api::metrics::QUOTA_IMAGES_TODAY.set(usage.images_today as f64);
+                    for (account, quota) in quotas.accounts() {
+                        let mut engine = quota.lock().await;
+                        let (daily, weekly) = engine.apply_pending_resets();
+                        
+                        if daily {
+                            log::info!("🔄 Daily quota reset for {} ({})", account, engine.calendar().timezone());
+                            crate::api::metrics::QUOTA_RESETS_TOTAL
+                                .with_label_values(&[&account, "daily"])
+                                .inc();
+                        }
+                        if weekly {
+                            log::info!("🔄 Weekly quota reset for {} ({})", account, engine.calendar().timezone());
+                            crate::api::metrics::QUOTA_RESETS_TOTAL
+                                .with_label_values(&[&account, "weekly"])
+                                .inc();
+                        }
+                        if daily || weekly {
+                            crate::api::metrics::publish_quota(&engine, chrono::Utc::now());
+                        }
+                    }
                 });
             })
             .await?;
     }
 
-    // Weekly reset job
-    {
-        let quota_engine = Arc::clone(&quota_engine);
-        scheduler
-            .start_weekly_reset(move || {
-                let quota_engine = Arc::clone(&quota_engine);
-                tokio::spawn(async move {
-                    let mut engine = quota_engine.lock().await;
-                    engine.reset_weekly();
-                    
-                    // Record metric
-                    crate::api::metrics::QUOTA_RESETS_TOTAL
-                        .with_label_values(&["weekly"])
-                        .inc();
-                    
-                    // Update gauges
-                    let usage = engine.get_usage();
-                    crate::api::metrics::QUOTA_MESSAGES_WEEK.set(usage.messages_this_week as f64);
-                    crate::api::metrics::QUOTA_VIDEOS_WEEK.set(usage.videos_this_week as f64);
-                });
-            })
-            .await?;
-    }
+    scheduler.start().await?;
+    Ok(scheduler)
+}
+
+/// Setup the conversation lifecycle job (every 15 minutes)
+/// Applies the time-based rules, e.g. Negative for 48h → Frozen
+/// Returns the scheduler (must be kept alive)
+pub async fn setup_lifecycle_job(state: crate::api::handlers::AppState) -> Result<JobScheduler> {
+    let scheduler = JobScheduler::new().await?;
+    
+    let job = Job::new_async("0 */15 * * * *", move |_uuid, _l| {
+        let state = state.clone();
+        Box::pin(async move {
+            match state.lifecycle.run(
+                &state.storage,
+                &state.conversation_engine,
+                &state.audit_engine,
+                chrono::Utc::now(),
+            ) {
+                Ok(transitions) if !transitions.is_empty() => {
+                    log::info!("🧊 Lifecycle job: {} conversation(s) frozen", transitions.len());
+                }
+                Ok(_) => {}
+                Err(e) => log::error!("Lifecycle job failed: {}", e),
+            }
+        })
+    })?;
+    
+    scheduler.add(job).await?;
+    scheduler.start().await?;
+    log::info!("📅 Lifecycle job scheduled (every 15 minutes)");
+    Ok(scheduler)
+}
+
+/// Setup the funnel job (every 5 minutes)
+/// Reading the whole history is too costly for each /metrics scrape, which serves these gauges
+/// Returns the scheduler (must be kept alive)
+pub async fn setup_funnel_job(state: crate::api::handlers::AppState) -> Result<JobScheduler> {
+    // Gauges are filled before the first scrape
+    crate::api::handlers::refresh_funnel(&state)?;
+    
+    let scheduler = JobScheduler::new().await?;
+    let job = Job::new_async("0 */5 * * * *", move |_uuid, _l| {
+        let state = state.clone();
+        Box::pin(async move {
+            if let Err(e) = crate::api::handlers::refresh_funnel(&state) {
+                log::error!("Funnel job failed: {}", e);
+            }
+        })
+    })?;
+    
+    scheduler.add(job).await?;
+    scheduler.start().await?;
+    log::info!("📅 Funnel job scheduled (every 5 minutes)");
+    Ok(scheduler)
+}
 
+/// Setup the outbound queue worker (every 10 seconds)
+/// Sends the due replies through `sender`, re-checking quotas and active hours
+/// Returns the scheduler (must be kept alive)
+pub async fn setup_outbound_worker<S>(state: crate::api::handlers::AppState, sender: Arc<S>) -> Result<JobScheduler>
+where
+    S: crate::engines::outbound::OutboundSender + Send + Sync + 'static,
+{
+    let scheduler = JobScheduler::new().await?;
+    // A slow gateway must not let two drains send the same entries
+    let running = Arc::new(Mutex::new(()));
+    
+    let job = Job::new_async("*/10 * * * * *", move |_uuid, _l| {
+        let state = state.clone();
+        let sender = Arc::clone(&sender);
+        let running = Arc::clone(&running);
+        Box::pin(async move {
+            let Ok(_guard) = running.try_lock() else {
+                return;
+            };
+            match state.outbound_queue.drain(sender.as_ref(), &state.quotas, chrono::Utc::now()).await {
+                Ok(report) if report != Default::default() => {
+                    log::info!("📤 Outbound worker: {:?}", report);
+                }
+                Ok(_) => {}
+                Err(e) => log::error!("Outbound worker failed: {}", e),
+            }
+        })
+    })?;
+    
+    scheduler.add(job).await?;
     scheduler.start().await?;
+    log::info!("📅 Outbound worker scheduled (every 10 seconds)");
     Ok(scheduler)
 }
 
@@ -145,31 +196,38 @@ mod tests {
     }
 
     #[tokio::test]
-    async fn test_daily_reset_job_added() {
+    async fn test_rollover_job_added() {
         let mut scheduler = QuotaScheduler::new().await.unwrap();
-        let result = scheduler.start_daily_reset(|| {}).await;
+        let result = scheduler.start_rollover(|| {}).await;
         assert!(result.is_ok());
     }
 
     #[tokio::test]
-    async fn test_weekly_reset_job_added() {
-        let mut scheduler = QuotaScheduler::new().await.unwrap();
-        let result = scheduler.start_weekly_reset(|| {}).await;
-        assert!(result.is_ok());
+    async fn test_setup_lifecycle_job() {
+        let state = crate::api::create_app_state();
+        let mut scheduler = setup_lifecycle_job(state).await.unwrap();
+        scheduler.shutdown().await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_setup_outbound_worker() {
+        let state = crate::api::create_app_state();
+        let sender = Arc::new(crate::engines::outbound::WebhookSender::new("http://127.0.0.1:9/send").unwrap());
+        let mut scheduler = setup_outbound_worker(state, sender).await.unwrap();
+        scheduler.shutdown().await.unwrap();
     }
 
     #[tokio::test]
     async fn test_setup_auto_reset() {
-        use crate::engines::quota::{QuotaEngine, QuotaLimits};
+        use crate::engines::quota::QuotaLimits;
         
-        let quota_engine = Arc::new(Mutex::new(QuotaEngine::new(QuotaLimits {
-            messages_per_day: 100,
-            messages_per_week: 500,
-            images_per_day: 20,
-            videos_per_week: 10,
-        })));
-
-        let scheduler = setup_auto_reset(quota_engine).await;
+        let quotas = Arc::new(QuotaAccounts::new(
+            QuotaLimits::default(),
+            [("shop-b".to_string(), QuotaLimits { messages_per_day: 100, ..QuotaLimits::default() })].into(),
+            Default::default(),
+        ));
+
+        let scheduler = setup_auto_reset(quotas).await;
         assert!(scheduler.is_ok());
         
         // Shutdown to clean up
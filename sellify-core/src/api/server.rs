@@ -15,6 +15,7 @@ pub fn create_app() -> Router {
 /// Create app with custom rate limiter config
 pub fn create_app_with_config(
     api_key: Option<String>,
+    #[allow(unused_variables)] // Not applied to the router yet
     rate_limiter: Option<RateLimiter>,
 ) -> Router {
     // Set API key in environment if provided
@@ -22,26 +23,81 @@ pub fn create_app_with_config(
         std::env::set_var("SELLIFY_API_KEY", key);
     }
     
+    create_app_with_state(create_app_state())
+}
+
+/// Initialize the engines shared by the handlers and the scheduled jobs
+/// The configuration is read from SELLIFY_CONFIG when set
+pub fn create_app_state() -> AppState {
+    create_app_state_with_config(load_config())
+}
+
+/// Initialize the engines from an already loaded configuration
+pub fn create_app_state_with_config(config: ConfigEngine) -> AppState {
     // Initialize Prometheus metrics
     let _ = crate::api::metrics::init_metrics();
     
     // Initialize engines
-    let decision_engine = Arc::new(DecisionEngine::new());
     let anti_hallucination = Arc::new(AntiHallucinationEngine::new());
-    let conversation_engine = Arc::new(ConversationEngine::new());
-    let quota_engine = Arc::new(Mutex::new(QuotaEngine::default()));
-    let knowledge_base = Arc::new(Mutex::new(KnowledgeBaseEngine::new()));
+    let conversation_engine = Arc::new(ConversationEngine::with_table(load_state_machine()));
+    let decision_engine = Arc::new(
+        DecisionEngine::with_policy(load_decision_policy(conversation_engine.table()))
+            .with_media_policy(config.get_config().media_policy.clone())
+            .with_max_misunderstandings(config.get_config().escalation_threshold.max_misunderstandings),
+    );
     let audit_engine = Arc::new(AuditEngine::new());
+    let language_detector = Arc::new(LanguageDetector::default());
+    let storage = Arc::new(open_storage());
+    // Catalog versions are restored so audits keep pointing at the catalog the bot actually used
+    let knowledge_base = Arc::new(Mutex::new(
+        KnowledgeBaseEngine::with_storage(Arc::clone(&storage))
+            .expect("Failed to restore catalog versions"),
+    ));
+    // Quota usage of every account is restored from storage so a restart does not reset the counters
+    let quotas = Arc::new(
+        QuotaAccounts::from_config(config.get_config(), Arc::clone(&storage))
+            .expect("Failed to restore quota usage"),
+    );
+    // One sentiment engine detects threats for the classifier, the decisions and the alerts
+    let sentiment_engine = Arc::new(SentimentEngine::new(config.get_config().sentiment.clone()));
+    let event_classifier = Arc::new(
+        EventClassifier::new(config.get_config().classifier.clone())
+            .with_sentiment(Arc::clone(&sentiment_engine)),
+    );
+    let alert_engine = Arc::new(
+        AlertEngine::new(config.get_config().alert_numbers.clone())
+            .with_sentiment(Arc::clone(&sentiment_engine)),
+    );
+    let lifecycle = Arc::new(LifecycleEngine::new(config.get_config().lifecycle.clone()));
+    let analytics = Arc::new(AnalyticsEngine::new());
+    let send_scheduler = Arc::new(SendScheduler::from_config(config.get_config()));
+    let outbound_queue = Arc::new(OutboundQueue::new(
+        Arc::clone(&storage),
+        config.get_config().outbound.clone(),
+        config.get_config().active_hours.clone(),
+    ));
     
-    let state = AppState {
+    AppState {
         decision_engine,
         anti_hallucination,
         conversation_engine,
-        quota_engine,
+        quotas,
         knowledge_base,
         audit_engine,
-    };
-    
+        language_detector,
+        storage,
+        event_classifier,
+        sentiment_engine,
+        alert_engine,
+        lifecycle,
+        analytics,
+        send_scheduler,
+        outbound_queue,
+    }
+}
+
+/// Create the router around an existing state
+pub fn create_app_with_state(state: AppState) -> Router {
     // Create router with middleware
     routes::create_router()
         // Authentication middleware (checks API key)
@@ -58,6 +114,49 @@ pub fn create_app_with_config(
         .with_state(state)
 }
 
+/// Load the configuration from SELLIFY_CONFIG (.toml or .json)
+/// Defaults to the built-in values; missing sections keep their defaults
+fn load_config() -> ConfigEngine {
+    match std::env::var("SELLIFY_CONFIG") {
+        Ok(path) => ConfigEngine::load(std::path::Path::new(&path))
+            .expect("Failed to load configuration"),
+        Err(_) => ConfigEngine::new(),
+    }
+}
+
+/// Load the conversation state machine from SELLIFY_STATE_MACHINE (.toml or .json)
+/// Defaults to the built-in funnel
+fn load_state_machine() -> state_machine::TransitionTable {
+    match std::env::var("SELLIFY_STATE_MACHINE") {
+        Ok(path) => state_machine::TransitionTable::load(std::path::Path::new(&path))
+            .expect("Failed to load state machine"),
+        Err(_) => state_machine::TransitionTable::builtin(),
+    }
+}
+
+/// Load the decision policy from SELLIFY_DECISION_POLICY (.toml or .json)
+/// Defaults to the built-in policy; rules may only use states of `table`
+fn load_decision_policy(table: &state_machine::TransitionTable) -> policy::DecisionPolicy {
+    match std::env::var("SELLIFY_DECISION_POLICY") {
+        Ok(path) => policy::DecisionPolicy::load(std::path::Path::new(&path), table)
+            .expect("Failed to load decision policy"),
+        Err(_) => policy::DecisionPolicy::builtin(),
+    }
+}
+
+/// Open the storage engine from SELLIFY_DB_PATH / SELLIFY_MASTER_KEY
+/// Defaults to an in-memory database (nothing survives a restart)
+fn open_storage() -> StorageEngine {
+    let db_path = std::env::var("SELLIFY_DB_PATH").unwrap_or_else(|_| ":memory:".to_string());
+    let master_key = std::env::var("SELLIFY_MASTER_KEY")
+        .unwrap_or_else(|_| "dev-master-key-change-in-production".to_string());
+    
+    let mut storage = StorageEngine::new_with_key(db_path.into(), master_key.as_bytes())
+        .expect("Failed to create storage engine");
+    storage.initialize().expect("Failed to initialize storage");
+    storage
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -67,6 +166,32 @@ mod tests {
     };
     use tower::ServiceExt;
     
+    /// Sends an authenticated JSON request and returns status + parsed body
+    async fn send_json(
+        app: &Router,
+        method: &str,
+        uri: &str,
+        body: Option<serde_json::Value>,
+    ) -> (StatusCode, serde_json::Value) {
+        let mut builder = Request::builder()
+            .uri(uri)
+            .method(method)
+            .header("X-API-Key", "test-api-key");
+        let body = match body {
+            Some(json) => {
+                builder = builder.header("content-type", "application/json");
+                Body::from(serde_json::to_string(&json).unwrap())
+            }
+            None => Body::empty(),
+        };
+        
+        let response = app.clone().oneshot(builder.body(body).unwrap()).await.unwrap();
+        let status = response.status();
+        let bytes = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        let json = serde_json::from_slice(&bytes).unwrap_or(serde_json::Value::Null);
+        (status, json)
+    }
+    
     #[tokio::test]
     async fn test_health_check() {
         let app = create_app();
@@ -136,6 +261,225 @@ mod tests {
         assert_eq!(response.status(), StatusCode::OK);
     }
     
+    #[tokio::test]
+    async fn test_decision_endpoint_detects_locale() {
+        let app = create_app_with_config(Some("test-api-key".to_string()), None);
+        
+        let request_body = serde_json::json!({
+            "incoming_message": "Hello, how much is this product?",
+            "conversation_state": "Discovery",
+            "quotas_available": true,
+            "is_active_hours": true,
+            "sentiment_detected": null
+        });
+        
+        let response = app
+            .oneshot(
+                Request::builder()
+                    .uri("/api/v1/decision")
+                    .method("POST")
+                    .header("content-type", "application/json")
+                    .header("X-API-Key", "test-api-key")
+                    .body(Body::from(serde_json::to_string(&request_body).unwrap()))
+                    .unwrap()
+            )
+            .await
+            .unwrap();
+        
+        assert_eq!(response.status(), StatusCode::OK);
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        let json: serde_json::Value = serde_json::from_slice(&body).unwrap();
+        assert_eq!(json["locale"], "en");
+        assert_eq!(json["action"], "RespondText");
+        assert_eq!(json["rule"], "respond");
+    }
+    
+    #[tokio::test]
+    async fn test_decision_policy_endpoint() {
+        let app = create_app_with_config(Some("test-api-key".to_string()), None);
+        
+        let (status, policy) = send_json(&app, "GET", "/api/v1/decision/policy", None).await;
+        assert_eq!(status, StatusCode::OK);
+        let rules = policy["rules"].as_array().unwrap();
+        assert_eq!(rules[0]["name"], "human_takeover");
+        assert_eq!(rules.last().unwrap()["when"], serde_json::json!({}));
+    }
+    
+    #[tokio::test]
+    async fn test_decision_uses_media_and_terminal_states() {
+        let app = create_app_with_config(Some("test-api-key".to_string()), None);
+        let (status, _) = send_json(&app, "PUT", "/api/v1/products", Some(serde_json::json!([{
+            "id": "prod-001",
+            "name": "Montre",
+            "short_description": "Montre connectée",
+            "long_description": "Écran AMOLED",
+            "price": { "amount": "49.90", "currency": "EUR" },
+            "keywords": ["montre"],
+            "objections": [],
+            "media": [{ "id": "img-001", "media_type": "Image", "url": "https://cdn.example.com/img-001.jpg" }]
+        }]))).await;
+        assert_eq!(status, StatusCode::OK);
+        let decide = |message: &str, state: &str| serde_json::json!({
+            "incoming_message": message,
+            "conversation_state": state,
+            "quotas_available": true,
+            "is_active_hours": true,
+            "sentiment_detected": null
+        });
+        
+        let (_, decision) = send_json(
+            &app, "POST", "/api/v1/decision",
+            Some(decide("Je peux voir une photo de la montre ?", "Interest")),
+        ).await;
+        assert_eq!(decision["action"], "RespondWithMedia");
+        assert_eq!(decision["rule"], "show_product_media");
+        assert_eq!(decision["details"], " (media: img-001)");
+        
+        // The product has no English content: it is not offered to an English-speaking customer
+        let (_, decision) = send_json(
+            &app, "POST", "/api/v1/decision",
+            Some(decide("Can I see a picture of the montre?", "Interest")),
+        ).await;
+        assert_eq!(decision["locale"], "en");
+        assert_eq!(decision["action"], "RespondText");
+        
+        // Not in a state where the media policy allows media
+        let (_, decision) = send_json(
+            &app, "POST", "/api/v1/decision",
+            Some(decide("Je peux voir une photo de la montre ?", "Negative")),
+        ).await;
+        assert_eq!(decision["action"], "RespondText");
+        
+        for terminal in ["Escalated", "Frozen"] {
+            let (_, decision) = send_json(&app, "POST", "/api/v1/decision", Some(decide("Bonjour", terminal))).await;
+            assert_eq!(decision["action"], "StopAutomation");
+        }
+    }
+    
+    #[tokio::test]
+    async fn test_decision_v2_returns_typed_action() {
+        let app = create_app_with_config(Some("test-api-key".to_string()), None);
+        
+        let (_, conversation) = send_json(
+            &app, "POST", "/api/v1/conversations",
+            Some(serde_json::json!({ "phone_number": "+33677777777" })),
+        ).await;
+        let request = serde_json::json!({
+            "incoming_message": "Bonjour",
+            "conversation_state": "Interest",
+            "quotas_available": false,
+            "is_active_hours": true,
+            "sentiment_detected": null,
+            "conversation_id": conversation["id"],
+        });
+        
+        let (status, decision) = send_json(&app, "POST", "/api/v2/decision", Some(request.clone())).await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(decision["action"], serde_json::json!({ "type": "Delay", "seconds": 3600 }));
+        assert_eq!(decision["rule"], "quota_exhausted");
+        assert_eq!(decision["trace"].as_array().unwrap().last().unwrap()["rule"], "quota_exhausted");
+        assert_eq!(decision["conversation_state"], "Discovery");
+        assert!(decision.get("details").is_none());
+        
+        // v1 keeps its format
+        let (_, decision) = send_json(&app, "POST", "/api/v1/decision", Some(request)).await;
+        assert_eq!(decision["action"], "Delay");
+        assert_eq!(decision["details"], "3600 seconds");
+    }
+    
+    #[tokio::test]
+    async fn test_decision_uses_the_stored_conversation_state() {
+        let app = create_app_with_config(Some("test-api-key".to_string()), None);
+        
+        send_json(
+            &app, "POST", "/api/v1/conversations",
+            Some(serde_json::json!({ "phone_number": "+33677777778" })),
+        ).await;
+        
+        // The caller's state is stale: the stored conversation is still in Discovery
+        let (_, decision) = send_json(
+            &app, "POST", "/api/v2/decision",
+            Some(serde_json::json!({
+                "incoming_message": "Bonjour",
+                "conversation_state": "Frozen",
+                "quotas_available": true,
+                "is_active_hours": true,
+                "sentiment_detected": null,
+                "phone_number": "+33677777778",
+            })),
+        ).await;
+        assert_eq!(decision["rule"], "respond");
+        assert_eq!(decision["conversation_state"], "Discovery");
+        
+        // Without a stored conversation the given state is used
+        let (_, decision) = send_json(
+            &app, "POST", "/api/v2/decision",
+            Some(serde_json::json!({
+                "incoming_message": "Bonjour",
+                "conversation_state": "Frozen",
+                "quotas_available": true,
+                "is_active_hours": true,
+                "sentiment_detected": null,
+                "phone_number": "+33677777779",
+            })),
+        ).await;
+        assert_eq!(decision["rule"], "terminal_state");
+    }
+    
+    #[tokio::test]
+    async fn test_detected_sentiment_feeds_decisions() {
+        let app = create_app_with_config(Some("test-api-key".to_string()), None);
+        
+        let (status, analysis) = send_json(
+            &app, "POST", "/api/v1/conversation/sentiment",
+            Some(serde_json::json!({ "message": "Je vais vous tuer 🔪" })),
+        ).await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(analysis["sentiment"], "threat");
+        assert_eq!(analysis["score"], 1.0);
+        
+        let decide = |sentiment: serde_json::Value| serde_json::json!({
+            "incoming_message": "Vous êtes des VOLEURS 😡",
+            "conversation_state": "Intent",
+            "quotas_available": true,
+            "is_active_hours": true,
+            "sentiment_detected": sentiment,
+        });
+        let (_, decision) = send_json(&app, "POST", "/api/v2/decision", Some(decide(serde_json::Value::Null))).await;
+        assert_eq!(decision["rule"], "anger_detected");
+        assert_eq!(decision["action"]["type"], "AlertHuman");
+        
+        // The caller's value wins
+        let (_, decision) = send_json(&app, "POST", "/api/v2/decision", Some(decide(serde_json::json!("positive")))).await;
+        assert_eq!(decision["rule"], "respond");
+    }
+    
+    #[tokio::test]
+    async fn test_inbound_threats_and_alerts_use_the_sentiment_engine() {
+        let app = create_app_with_config(Some("test-api-key".to_string()), None);
+        
+        let (_, conversation) = send_json(
+            &app, "POST", "/api/v1/conversations",
+            Some(serde_json::json!({ "phone_number": "+33612121212" })),
+        ).await;
+        let messages = format!("/api/v1/conversations/{}/messages", conversation["id"].as_str().unwrap());
+        
+        // A negated threat neither escalates nor alerts
+        let (status, result) = send_json(&app, "POST", &messages, Some(serde_json::json!({ "message": "Je ne vais pas te frapper" }))).await;
+        assert_eq!(status, StatusCode::OK);
+        assert!(result["classifications"].as_array().unwrap().is_empty());
+        assert_eq!(result["conversation"]["state"], "Discovery");
+        assert!(result["alert"].is_null());
+        
+        let (_, result) = send_json(&app, "POST", &messages, Some(serde_json::json!({ "message": "C'est INADMISSIBLE 😡" }))).await;
+        assert_eq!(result["alert"], "Anger");
+        
+        let (_, result) = send_json(&app, "POST", &messages, Some(serde_json::json!({ "message": "Je vais te frapper" }))).await;
+        assert_eq!(result["classifications"][0]["rule"], "sentiment:threat");
+        assert_eq!(result["conversation"]["state"], "Escalated");
+        assert_eq!(result["alert"], "Threat");
+    }
+    
     #[tokio::test]
     async fn test_metrics_endpoint_public() {
         let app = create_app();
@@ -148,4 +492,783 @@ mod tests {
         // Metrics endpoint should be public (no auth required)
         assert_eq!(response.status(), StatusCode::OK);
     }
+    
+    #[tokio::test]
+    async fn test_catalog_versioning_endpoints() {
+        let app = create_app_with_config(Some("test-api-key".to_string()), None);
+        let product = |amount: &str| serde_json::json!({
+            "id": "prod-001",
+            "name": "Montre",
+            "short_description": "Montre connectée",
+            "long_description": "Écran AMOLED",
+            "price": { "amount": amount, "currency": "EUR" },
+            "keywords": ["montre"],
+            "objections": [],
+            "media": []
+        });
+        
+        let (status, v1) = send_json(&app, "PUT", "/api/v1/products", Some(serde_json::json!([product("49.90")]))).await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(v1["version"], 1);
+        
+        let (_, v2) = send_json(&app, "PUT", "/api/v1/products", Some(serde_json::json!([product("39.90")]))).await;
+        assert_eq!(v2["version"], 2);
+        
+        let (status, diff) = send_json(&app, "GET", "/api/v1/catalog/diff?from=1&to=2", None).await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(diff["modified"], serde_json::json!(["prod-001"]));
+        
+        let (status, rolled_back) = send_json(&app, "POST", "/api/v1/catalog/rollback/1", None).await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(rolled_back["version"], 3);
+        assert_eq!(rolled_back["hash"], v1["hash"]);
+        
+        let (_, versions) = send_json(&app, "GET", "/api/v1/catalog/versions", None).await;
+        assert_eq!(versions.as_array().unwrap().len(), 3);
+    }
+    
+    #[tokio::test]
+    async fn test_audit_records_the_decision_catalog_version() {
+        let app = create_app_with_config(Some("test-api-key".to_string()), None);
+        let catalog = |amount: &str| serde_json::json!([{
+            "id": "prod-001",
+            "name": "Montre",
+            "short_description": "Montre connectée",
+            "long_description": "Écran AMOLED",
+            "price": { "amount": amount, "currency": "EUR" },
+            "keywords": ["montre"],
+            "objections": [],
+            "media": []
+        }]);
+        send_json(&app, "PUT", "/api/v1/products", Some(catalog("49.90"))).await;
+        
+        let (status, decision) = send_json(
+            &app, "POST", "/api/v1/decision",
+            Some(serde_json::json!({
+                "incoming_message": "La montre est disponible ?",
+                "conversation_state": "Interest",
+                "quotas_available": true,
+                "is_active_hours": true,
+                "sentiment_detected": null
+            })),
+        ).await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(decision["catalog_version"], 1);
+        
+        // The catalog changes before the audit entry is written
+        send_json(&app, "PUT", "/api/v1/products", Some(catalog("39.90"))).await;
+        
+        let audit = |version: serde_json::Value| serde_json::json!({
+            "id": "audit-001",
+            "timestamp": "2026-01-15T10:00:00Z",
+            "conversation_id": "conv-001",
+            "incoming_message": "La montre est disponible ?",
+            "state": "Interest",
+            "chosen_action": "RespondText",
+            "ai_prompt": null,
+            "ai_response": null,
+            "sent_message": null,
+            "quotas_before": { "messages_today": 0, "messages_this_week": 0 },
+            "quotas_after": { "messages_today": 1, "messages_this_week": 1 },
+            "catalog_version": version,
+            "catalog_hash": null
+        });
+        let (status, _) = send_json(&app, "POST", "/api/v1/audit/log", Some(audit(decision["catalog_version"].clone()))).await;
+        assert_eq!(status, StatusCode::CREATED);
+        let (status, _) = send_json(&app, "POST", "/api/v1/audit/log", Some(audit(serde_json::json!(42)))).await;
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+    }
+    
+    #[tokio::test]
+    async fn test_conversation_entity_endpoints() {
+        let app = create_app_with_config(Some("test-api-key".to_string()), None);
+        
+        let (status, conversation) = send_json(
+            &app, "POST", "/api/v1/conversations",
+            Some(serde_json::json!({ "phone_number": "+33622222222" })),
+        ).await;
+        assert_eq!(status, StatusCode::CREATED);
+        assert_eq!(conversation["state"], "Discovery");
+        let id = conversation["id"].as_str().unwrap().to_string();
+        
+        let (status, applied) = send_json(
+            &app, "POST", &format!("/api/v1/conversations/{}/events", id),
+            Some(serde_json::json!({ "event": "ProductQuestion", "product_id": "prod-001" })),
+        ).await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(applied["conversation"]["state"], "Interest");
+        assert_eq!(applied["transition"]["from_state"], "Discovery");
+        
+        let (_, history) = send_json(&app, "GET", &format!("/api/v1/conversations/{}/history", id), None).await;
+        assert_eq!(history.as_array().unwrap().len(), 1);
+        assert_eq!(history[0]["product_id"], "prod-001");
+        
+        let (status, _) = send_json(&app, "GET", "/api/v1/conversations/unknown", None).await;
+        assert_eq!(status, StatusCode::NOT_FOUND);
+        
+        let (status, machine) = send_json(&app, "GET", "/api/v1/conversation/state-machine", None).await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(machine["initial"], "Discovery");
+        
+        let (status, _) = send_json(
+            &app, "POST", &format!("/api/v1/conversations/{}/events", id),
+            Some(serde_json::json!({ "event": "PaymentReceived" })),
+        ).await;
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+    }
+    
+    #[tokio::test]
+    async fn test_inbound_message_classified_and_applied() {
+        let app = create_app_with_config(Some("test-api-key".to_string()), None);
+        
+        let (status, classified) = send_json(
+            &app, "POST", "/api/v1/conversation/classify",
+            Some(serde_json::json!({ "message": "C'est quoi le prix ?" })),
+        ).await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(classified["classifications"][0]["event"], "PriceInterest");
+        assert_eq!(classified["classifications"][0]["rule"], "lexicon:price");
+        
+        let (_, conversation) = send_json(
+            &app, "POST", "/api/v1/conversations",
+            Some(serde_json::json!({ "phone_number": "+33633333333" })),
+        ).await;
+        let id = conversation["id"].as_str().unwrap().to_string();
+        
+        let (status, result) = send_json(
+            &app, "POST", &format!("/api/v1/conversations/{}/messages", id),
+            Some(serde_json::json!({ "message": "Non merci" })),
+        ).await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(result["conversation"]["state"], "Negative");
+        assert_eq!(result["transitions"][0]["event"], "NegativeResponse");
+    }
+    
+    #[tokio::test]
+    async fn test_inbound_message_applies_one_event() {
+        let app = create_app_with_config(Some("test-api-key".to_string()), None);
+        send_json(&app, "PUT", "/api/v1/products", Some(serde_json::json!([{
+            "id": "prod-001",
+            "name": "Montre",
+            "short_description": "Montre connectée",
+            "long_description": "Écran AMOLED",
+            "price": { "amount": "49.90", "currency": "EUR" },
+            "keywords": ["montre"],
+            "objections": [],
+            "media": []
+        }]))).await;
+        let new_conversation = |phone: &'static str| {
+            let app = app.clone();
+            async move {
+                let (_, conversation) = send_json(
+                    &app, "POST", "/api/v1/conversations",
+                    Some(serde_json::json!({ "phone_number": phone })),
+                ).await;
+                conversation["id"].as_str().unwrap().to_string()
+            }
+        };
+        
+        // The product keyword no longer takes the conversation back out of Objection
+        let id = new_conversation("+33634343434").await;
+        send_json(&app, "POST", &format!("/api/v1/conversations/{}/events", id), Some(serde_json::json!({ "event": "ProductQuestion" }))).await;
+        let (status, result) = send_json(
+            &app, "POST", &format!("/api/v1/conversations/{}/messages", id),
+            Some(serde_json::json!({ "message": "La montre est trop chère" })),
+        ).await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(result["classifications"].as_array().unwrap().len(), 2);
+        assert_eq!(result["transitions"].as_array().unwrap().len(), 1);
+        assert_eq!(result["conversation"]["state"], "Objection");
+        assert_eq!(result["conversation"]["focus_product_id"], "prod-001");
+        
+        // The price question wins over the product keyword; the built-in funnel
+        // only moves to Intent from Interest
+        let id = new_conversation("+33635353535").await;
+        let messages = format!("/api/v1/conversations/{}/messages", id);
+        let (_, result) = send_json(&app, "POST", &messages, Some(serde_json::json!({ "message": "Combien la montre ?" }))).await;
+        assert_eq!(result["transitions"].as_array().unwrap().len(), 1);
+        assert_eq!(result["transitions"][0]["event"], "PriceInterest");
+        assert_eq!(result["conversation"]["state"], "Discovery");
+        send_json(&app, "POST", &messages, Some(serde_json::json!({ "message": "La montre est disponible ?" }))).await;
+        let (_, result) = send_json(&app, "POST", &messages, Some(serde_json::json!({ "message": "Combien la montre ?" }))).await;
+        assert_eq!(result["conversation"]["state"], "Intent");
+    }
+    
+    #[tokio::test]
+    async fn test_simulation_has_no_side_effects() {
+        let app = create_app_with_config(Some("test-api-key".to_string()), None);
+        
+        let (_, conversation) = send_json(
+            &app, "POST", "/api/v1/conversations",
+            Some(serde_json::json!({ "phone_number": "+33644444444" })),
+        ).await;
+        let id = conversation["id"].as_str().unwrap().to_string();
+        
+        let (status, simulation) = send_json(
+            &app, "POST", "/api/v1/decision/simulate",
+            Some(serde_json::json!({
+                "message": "Non merci",
+                "conversation_id": id,
+                "is_active_hours": true,
+                "reply": "Je suis un robot",
+            })),
+        ).await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(simulation["classifications"][0]["event"], "NegativeResponse");
+        assert_eq!(simulation["transitions"][0]["to_state"], "Negative");
+        assert_eq!(simulation["conversation_state"], "Negative");
+        assert_eq!(simulation["decision"]["rule"], "respond");
+        assert_eq!(simulation["decision"]["trace"][3]["rule"], "outside_active_hours");
+        assert_eq!(simulation["decision"]["trace"][3]["conditions"][0]["actual"], true);
+        assert!(simulation["prompt"].as_str().unwrap().starts_with("Objectif:"));
+        assert_eq!(simulation["validation"]["valid"], false);
+        assert!(simulation["validation"]["fallback_text"].as_str().unwrap().starts_with("Désolé"));
+        
+        // Nothing was stored
+        let (_, stored) = send_json(&app, "GET", &format!("/api/v1/conversations/{}", id), None).await;
+        assert_eq!(stored["state"], "Discovery");
+        let (_, history) = send_json(&app, "GET", &format!("/api/v1/conversations/{}/history", id), None).await;
+        assert!(history.as_array().unwrap().is_empty());
+        
+        let (status, _) = send_json(
+            &app, "POST", "/api/v1/decision/simulate",
+            Some(serde_json::json!({ "message": "Bonjour", "conversation_state": "Shipped" })),
+        ).await;
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+        
+        let (status, _) = send_json(
+            &app, "POST", "/api/v1/decision/simulate",
+            Some(serde_json::json!({ "message": "Bonjour", "conversation_id": id, "conversation_state": "Intent" })),
+        ).await;
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+    }
+    
+    #[tokio::test]
+    async fn test_simulation_applies_the_contact_reply() {
+        // No spacing between sends, so only the per-contact block is left
+        let config = ConfigEngine::from_toml(r#"
+            [anti_ban]
+            max_messages_per_day = 200
+            max_messages_per_hour = 30
+            typing_simulation = true
+            min_seconds_between_messages = 0
+        "#).unwrap();
+        std::env::set_var("SELLIFY_API_KEY", "test-api-key");
+        let app = create_app_with_state(create_app_state_with_config(config));
+        
+        let (_, conversation) = send_json(
+            &app, "POST", "/api/v1/conversations",
+            Some(serde_json::json!({ "phone_number": "+33655555555" })),
+        ).await;
+        let id = conversation["id"].as_str().unwrap().to_string();
+        for _ in 0..3 {
+            send_json(
+                &app, "POST", "/api/v1/quota/record",
+                Some(serde_json::json!({ "message_type": "text", "recipient": "+33655555555" })),
+            ).await;
+        }
+        
+        // The contact is awaiting a reply until they write: the simulated message lifts the block
+        let (status, simulation) = send_json(
+            &app, "POST", "/api/v1/decision/simulate",
+            Some(serde_json::json!({ "message": "Bonjour", "conversation_id": id, "is_active_hours": true })),
+        ).await;
+        assert_eq!(status, StatusCode::OK);
+        let quota_rule = simulation["decision"]["trace"].as_array().unwrap().iter()
+            .find(|rule| rule["rule"] == "quota_exhausted")
+            .unwrap();
+        assert_eq!(quota_rule["conditions"][0]["actual"], true);
+        
+        // ... without touching the real counters
+        let check = serde_json::json!({ "message_type": "text", "recipient": "+33655555555" });
+        let (_, result) = send_json(&app, "POST", "/api/v1/quota/check", Some(check)).await;
+        assert_eq!(result["blocked_by"], "awaiting_reply");
+    }
+    
+    #[tokio::test]
+    async fn test_per_contact_quota_lifted_by_reply() {
+        let app = create_app_with_config(Some("test-api-key".to_string()), None);
+        
+        let (_, conversation) = send_json(
+            &app, "POST", "/api/v1/conversations",
+            Some(serde_json::json!({ "phone_number": "+33644444444" })),
+        ).await;
+        let id = conversation["id"].as_str().unwrap().to_string();
+        
+        for _ in 0..3 {
+            let (status, _) = send_json(
+                &app, "POST", "/api/v1/quota/record",
+                Some(serde_json::json!({ "message_type": "text", "recipient": "+33644444444" })),
+            ).await;
+            assert_eq!(status, StatusCode::OK);
+        }
+        
+        let check = serde_json::json!({ "message_type": "text", "recipient": "+33644444444" });
+        let (_, result) = send_json(&app, "POST", "/api/v1/quota/check", Some(check.clone())).await;
+        assert_eq!(result["can_send"], false);
+        assert_eq!(result["blocked_by"], "awaiting_reply");
+        assert!(result["allowed_at"].is_null());
+        
+        send_json(
+            &app, "POST", &format!("/api/v1/conversations/{}/messages", id),
+            Some(serde_json::json!({ "message": "Bonjour" })),
+        ).await;
+        let (_, result) = send_json(&app, "POST", "/api/v1/quota/check", Some(check)).await;
+        assert_ne!(result["blocked_by"], "awaiting_reply");
+        assert!(result["allowed_at"].is_string());
+    }
+    
+    #[tokio::test]
+    async fn test_quota_feedback_tightens_effective_limits() {
+        let app = create_app_with_config(Some("test-api-key".to_string()), None);
+        
+        let (_, status) = send_json(&app, "GET", "/api/v1/quota/status", None).await;
+        assert_eq!(status["throttle_factor"], 1.0);
+        let configured = status["effective_limits"]["messages_per_day"].as_u64().unwrap();
+        
+        let (code, throttle) = send_json(
+            &app, "POST", "/api/v1/quota/feedback",
+            Some(serde_json::json!({ "kind": "rate_limited" })),
+        ).await;
+        assert_eq!(code, StatusCode::OK);
+        assert_eq!(throttle["throttle_factor"], 0.5);
+        
+        let (_, status) = send_json(&app, "GET", "/api/v1/quota/status", None).await;
+        assert_eq!(status["effective_limits"]["messages_per_day"].as_u64().unwrap(), configured / 2);
+        assert_eq!(status["effective_limits"]["min_gap_seconds"], 10);
+        
+        let (code, _) = send_json(
+            &app, "POST", "/api/v1/quota/feedback",
+            Some(serde_json::json!({ "kind": "unknown" })),
+        ).await;
+        assert_eq!(code, StatusCode::UNPROCESSABLE_ENTITY);
+    }
+    
+    #[tokio::test]
+    async fn test_quota_warmup_stage_in_status() {
+        let app = create_app_with_config(Some("test-api-key".to_string()), None);
+        
+        let connected_at = (chrono::Utc::now() - chrono::Duration::days(7)).to_rfc3339();
+        let (code, warmup) = send_json(
+            &app, "POST", "/api/v1/quota/warmup",
+            Some(serde_json::json!({ "connected_at": connected_at })),
+        ).await;
+        assert_eq!(code, StatusCode::OK);
+        assert_eq!(warmup["day"], 7);
+        assert_eq!(warmup["completed"], false);
+        
+        let (_, status) = send_json(&app, "GET", "/api/v1/quota/status", None).await;
+        assert_eq!(status["warmup"]["day"], 7);
+        assert!(status["effective_limits"]["messages_per_day"].as_u64().unwrap() < 200);
+        
+        let future = (chrono::Utc::now() + chrono::Duration::days(1)).to_rfc3339();
+        let (code, _) = send_json(
+            &app, "POST", "/api/v1/quota/warmup",
+            Some(serde_json::json!({ "connected_at": future })),
+        ).await;
+        assert_eq!(code, StatusCode::BAD_REQUEST);
+    }
+    
+    #[tokio::test]
+    async fn test_quotas_are_isolated_per_account() {
+        let config = ConfigEngine::from_toml(r#"
+            [accounts.shop-a]
+            max_messages_per_day = 200
+            max_messages_per_hour = 30
+            typing_simulation = true
+            
+            [accounts.shop-b]
+            max_messages_per_day = 200
+            max_messages_per_hour = 30
+            typing_simulation = true
+        "#).unwrap();
+        std::env::set_var("SELLIFY_API_KEY", "test-api-key");
+        let app = create_app_with_state(create_app_state_with_config(config));
+        
+        for _ in 0..3 {
+            let (code, _) = send_json(&app, "POST", "/api/v1/quota/record?account=shop-a", None).await;
+            assert_eq!(code, StatusCode::OK);
+        }
+        let (code, _) = send_json(&app, "POST", "/api/v1/quota/record?account=shop-b", None).await;
+        assert_eq!(code, StatusCode::OK);
+        
+        let (_, status) = send_json(&app, "GET", "/api/v1/quota/status?account=shop-a", None).await;
+        assert_eq!(status["account"], "shop-a");
+        assert_eq!(status["messages_today"], 3);
+        let (_, status) = send_json(&app, "GET", "/api/v1/quota/status?account=shop-b", None).await;
+        assert_eq!(status["messages_today"], 1);
+        let (_, status) = send_json(&app, "GET", "/api/v1/quota/status", None).await;
+        assert_eq!(status["account"], "default");
+        assert_eq!(status["messages_today"], 0);
+        
+        let (_, accounts) = send_json(&app, "GET", "/api/v1/quota/accounts", None).await;
+        let accounts: Vec<_> = accounts.as_array().unwrap().iter().map(|a| a["account"].clone()).collect();
+        assert!(accounts.contains(&serde_json::json!("shop-a")));
+        assert!(accounts.contains(&serde_json::json!("shop-b")));
+        
+        let (code, _) = send_json(&app, "GET", "/api/v1/quota/status?account=bad%20id", None).await;
+        assert_eq!(code, StatusCode::BAD_REQUEST);
+        
+        // Accounts neither configured nor known are not created by any route
+        let (code, _) = send_json(&app, "GET", "/api/v1/quota/status?account=shop-z", None).await;
+        assert_eq!(code, StatusCode::NOT_FOUND);
+        let (code, _) = send_json(&app, "POST", "/api/v1/quota/record?account=shop-z", None).await;
+        assert_eq!(code, StatusCode::NOT_FOUND);
+        let (_, accounts) = send_json(&app, "GET", "/api/v1/quota/accounts", None).await;
+        assert!(accounts.as_array().unwrap().iter().all(|a| a["account"] != "shop-z"));
+    }
+    
+    #[tokio::test]
+    async fn test_state_from_configuration_file() {
+        let path = std::env::temp_dir().join(format!("sellify_config_{}.toml", uuid::Uuid::new_v4()));
+        std::fs::write(&path, r#"
+            [active_hours]
+            start = "08:00"
+            end = "20:00"
+            timezone = "Europe/Paris"
+            
+            [accounts.shop-a]
+            max_messages_per_day = 50
+            max_messages_per_hour = 7
+            typing_simulation = true
+            warmup = { days = 14, start_ratio = 0.1, connected_at = "2026-01-11T09:00:00Z" }
+        "#).unwrap();
+        let config = ConfigEngine::load(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+        
+        std::env::set_var("SELLIFY_API_KEY", "test-api-key");
+        let app = create_app_with_state(create_app_state_with_config(config));
+        
+        let (_, status) = send_json(&app, "GET", "/api/v1/quota/status?account=shop-a", None).await;
+        assert_eq!(status["timezone"], "Europe/Paris");
+        assert_eq!(status["effective_limits"]["messages_per_hour"], 7);
+        assert_eq!(status["warmup"]["connected_at"], "2026-01-11T09:00:00Z");
+        
+        // The default account keeps the default anti-ban section, without warm-up
+        let (_, status) = send_json(&app, "GET", "/api/v1/quota/status", None).await;
+        assert_eq!(status["effective_limits"]["messages_per_hour"], 30);
+        assert_eq!(status["warmup"]["connected_at"], serde_json::Value::Null);
+    }
+    
+    #[tokio::test]
+    async fn test_send_plan_endpoint() {
+        let app = create_app_with_config(Some("test-api-key".to_string()), None);
+        
+        let (status, plan) = send_json(
+            &app, "POST", "/api/v1/send/plan",
+            Some(serde_json::json!({ "text": "Oui, elle est disponible.", "start_at": "2026-01-18T10:00:00Z" })),
+        ).await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(plan["parts"], 1);
+        let types: Vec<&str> = plan["actions"].as_array().unwrap().iter()
+            .map(|a| a["type"].as_str().unwrap())
+            .collect();
+        assert_eq!(types, vec!["mark_read", "composing", "send"]);
+        assert_eq!(plan["actions"][2]["text"], "Oui, elle est disponible.");
+        
+        let (status, _) = send_json(
+            &app, "POST", "/api/v1/send/plan",
+            Some(serde_json::json!({ "text": "  " })),
+        ).await;
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+    }
+    
+    #[tokio::test]
+    async fn test_outbound_queue_endpoints() {
+        let app = create_app_with_config(Some("test-api-key".to_string()), None);
+        
+        let (_, conversation) = send_json(
+            &app, "POST", "/api/v1/conversations",
+            Some(serde_json::json!({ "phone_number": "+33655555555" })),
+        ).await;
+        let id = conversation["id"].as_str().unwrap().to_string();
+        
+        let request = serde_json::json!({ "idempotency_key": "msg-1", "text": "Bonjour", "delay_seconds": 30 });
+        let uri = format!("/api/v1/conversations/{}/outbound", id);
+        let (status, queued) = send_json(&app, "POST", &uri, Some(request.clone())).await;
+        assert_eq!(status, StatusCode::CREATED);
+        assert_eq!(queued["status"], "pending");
+        assert_eq!(queued["payload"]["type"], "text");
+        
+        // Same idempotency key: the first entry comes back
+        let (status, again) = send_json(&app, "POST", &uri, Some(request)).await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(again["id"], queued["id"]);
+        
+        let (_, pending) = send_json(&app, "GET", "/api/v1/outbound?status=pending", None).await;
+        assert!(pending.as_array().unwrap().iter().any(|m| m["id"] == queued["id"]));
+        
+        let cancel_uri = format!("/api/v1/outbound/{}", queued["id"].as_str().unwrap());
+        let (status, cancelled) = send_json(&app, "DELETE", &cancel_uri, None).await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(cancelled["status"], "cancelled");
+        let (status, _) = send_json(&app, "DELETE", &cancel_uri, None).await;
+        assert_eq!(status, StatusCode::CONFLICT);
+        
+        // An out-of-range delay is refused instead of crashing the handler
+        for delay_seconds in [30 * 24 * 3600 + 1, u64::MAX] {
+            let (status, _) = send_json(
+                &app, "POST", &uri,
+                Some(serde_json::json!({ "idempotency_key": "msg-2", "text": "Bonjour", "delay_seconds": delay_seconds })),
+            ).await;
+            assert_eq!(status, StatusCode::BAD_REQUEST);
+        }
+    }
+    
+    #[tokio::test]
+    async fn test_release_escalated_conversation() {
+        let app = create_app_with_config(Some("test-api-key".to_string()), None);
+        
+        let (_, conversation) = send_json(
+            &app, "POST", "/api/v1/conversations",
+            Some(serde_json::json!({ "phone_number": "+33644444444" })),
+        ).await;
+        let id = conversation["id"].as_str().unwrap().to_string();
+        let release = serde_json::json!({ "state": "Interest", "operator": "alice", "reason": "Customer calmed down" });
+        
+        let (status, _) = send_json(&app, "POST", &format!("/api/v1/conversations/{}/release", id), Some(release.clone())).await;
+        assert_eq!(status, StatusCode::CONFLICT);
+        
+        send_json(
+            &app, "POST", &format!("/api/v1/conversations/{}/events", id),
+            Some(serde_json::json!({ "event": "ThreatDetected" })),
+        ).await;
+        let (status, _) = send_json(
+            &app, "POST", &format!("/api/v1/conversations/{}/release", id),
+            Some(serde_json::json!({ "state": "Frozen", "operator": "alice" })),
+        ).await;
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+        let (status, released) = send_json(&app, "POST", &format!("/api/v1/conversations/{}/release", id), Some(release.clone())).await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(released["conversation"]["state"], "Interest");
+        assert_eq!(released["transition"]["event"], "Release");
+        assert_eq!(released["transition"]["actor"], "operator:alice");
+        
+        // Already released: rejected without recording a second release
+        let (status, _) = send_json(&app, "POST", &format!("/api/v1/conversations/{}/release", id), Some(release)).await;
+        assert_eq!(status, StatusCode::CONFLICT);
+        let (_, history) = send_json(&app, "GET", &format!("/api/v1/conversations/{}/history", id), None).await;
+        assert_eq!(history.as_array().unwrap().len(), 2);
+        assert_eq!(history[1]["reason"], "Customer calmed down");
+        
+        let (status, run) = send_json(&app, "POST", "/api/v1/conversation/lifecycle/run", None).await;
+        assert_eq!(status, StatusCode::OK);
+        assert!(run["transitions"].as_array().unwrap().is_empty());
+    }
+    
+    #[tokio::test]
+    async fn test_only_the_misunderstanding_alert_resets_the_count() {
+        // A policy where an anger alert is checked before the misunderstanding one
+        let mut state = create_app_state_with_config(ConfigEngine::new());
+        let policy = crate::engines::policy::DecisionPolicy::from_toml(r#"
+            [[rules]]
+            name = "anger_detected"
+            when = { sentiments = ["anger"] }
+            action = { AlertHuman = { reason = "Sentiment detected: anger" } }
+            
+            [[rules]]
+            name = "repeated_misunderstanding"
+            when = { misunderstanding_limit = true }
+            action = { AlertHuman = { reason = "Repeated misunderstanding" } }
+            
+            [[rules]]
+            name = "respond"
+            action = { RespondText = { text = "" } }
+        "#, state.conversation_engine.table()).unwrap();
+        state.decision_engine = Arc::new(DecisionEngine::with_policy(policy));
+        std::env::set_var("SELLIFY_API_KEY", "test-api-key");
+        let app = create_app_with_state(state);
+        
+        let (_, conversation) = send_json(
+            &app, "POST", "/api/v1/conversations",
+            Some(serde_json::json!({ "phone_number": "+33677777777" })),
+        ).await;
+        let id = conversation["id"].as_str().unwrap().to_string();
+        for _ in 0..3 {
+            send_json(&app, "POST", &format!("/api/v1/conversations/{}/misunderstandings", id), None).await;
+        }
+        let decide = |sentiment: Option<&str>| serde_json::json!({
+            "incoming_message": "Bonjour",
+            "conversation_state": "Escalated",
+            "quotas_available": true,
+            "is_active_hours": true,
+            "sentiment_detected": sentiment,
+            "conversation_id": id,
+        });
+        
+        let (_, response) = send_json(&app, "POST", "/api/v1/decision", Some(decide(Some("anger")))).await;
+        assert_eq!(response["rule"], "anger_detected");
+        let (_, stored) = send_json(&app, "GET", &format!("/api/v1/conversations/{}", id), None).await;
+        assert_eq!(stored["misunderstanding_count"], 3);
+        
+        let (_, response) = send_json(&app, "POST", "/api/v1/decision", Some(decide(None))).await;
+        assert_eq!(response["rule"], "repeated_misunderstanding");
+        let (_, stored) = send_json(&app, "GET", &format!("/api/v1/conversations/{}", id), None).await;
+        assert_eq!(stored["misunderstanding_count"], 0);
+    }
+    
+    #[tokio::test]
+    async fn test_repeated_misunderstandings_escalate() {
+        let app = create_app_with_config(Some("test-api-key".to_string()), None);
+        
+        let (_, conversation) = send_json(
+            &app, "POST", "/api/v1/conversations",
+            Some(serde_json::json!({ "phone_number": "+33666666666" })),
+        ).await;
+        let id = conversation["id"].as_str().unwrap().to_string();
+        let messages = format!("/api/v1/conversations/{}/messages", id);
+        
+        let fallback = format!("/api/v1/conversations/{}/misunderstandings", id);
+        
+        // Only fallback replies are counted, not unclassified messages ("Bonjour", "Merci")
+        let (_, result) = send_json(&app, "POST", &messages, Some(serde_json::json!({ "message": "Bonjour" }))).await;
+        assert_eq!(result["conversation"]["misunderstanding_count"], 0);
+        send_json(&app, "POST", &fallback, None).await;
+        let (_, result) = send_json(&app, "POST", &messages, Some(serde_json::json!({ "message": "Merci" }))).await;
+        assert_eq!(result["conversation"]["misunderstanding_count"], 1);
+        
+        // A classified message resets the count
+        let (_, result) = send_json(&app, "POST", &messages, Some(serde_json::json!({ "message": "Non merci" }))).await;
+        assert_eq!(result["conversation"]["misunderstanding_count"], 0);
+        
+        for _ in 0..2 {
+            let (status, result) = send_json(&app, "POST", &fallback, None).await;
+            assert_eq!(status, StatusCode::OK);
+            assert!(result["escalation"].is_null());
+        }
+        
+        // Third one reaches the default threshold
+        let (_, result) = send_json(&app, "POST", &fallback, None).await;
+        assert_eq!(result["conversation"]["state"], "Escalated");
+        assert_eq!(result["escalation"]["event"], "Escalate");
+        assert_eq!(result["escalation"]["from_state"], "Negative");
+        
+        let (_, response) = send_json(
+            &app, "POST", "/api/v1/decision",
+            Some(serde_json::json!({
+                "incoming_message": "Bonjour",
+                "conversation_state": "Escalated",
+                "quotas_available": true,
+                "is_active_hours": true,
+                "sentiment_detected": null,
+                "conversation_id": id,
+            })),
+        ).await;
+        assert_eq!(response["action"], "AlertHuman");
+        assert_eq!(response["rule"], "repeated_misunderstanding");
+        
+        // Alerted once: the next messages only stop the automation
+        let (_, stored) = send_json(&app, "GET", &format!("/api/v1/conversations/{}", id), None).await;
+        assert_eq!(stored["misunderstanding_count"], 0);
+        let (_, response) = send_json(
+            &app, "POST", "/api/v1/decision",
+            Some(serde_json::json!({
+                "incoming_message": "Allô ?",
+                "conversation_state": "Escalated",
+                "quotas_available": true,
+                "is_active_hours": true,
+                "sentiment_detected": null,
+                "conversation_id": id,
+            })),
+        ).await;
+        assert_eq!(response["action"], "StopAutomation");
+        assert_eq!(response["rule"], "terminal_state");
+        
+        let (_, history) = send_json(&app, "GET", &format!("/api/v1/conversations/{}/history", id), None).await;
+        assert_eq!(history.as_array().unwrap().last().unwrap()["event"], "Escalate");
+        
+        let (status, released) = send_json(
+            &app, "POST", &format!("/api/v1/conversations/{}/release", id),
+            Some(serde_json::json!({ "state": "Interest", "operator": "alice" })),
+        ).await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(released["conversation"]["misunderstanding_count"], 0);
+    }
+    
+    #[tokio::test]
+    async fn test_human_takeover_silences_the_bot() {
+        let app = create_app_with_config(Some("test-api-key".to_string()), None);
+        
+        let (_, conversation) = send_json(
+            &app, "POST", "/api/v1/conversations",
+            Some(serde_json::json!({ "phone_number": "+33655555555" })),
+        ).await;
+        let id = conversation["id"].as_str().unwrap().to_string();
+        let decision = serde_json::json!({
+            "incoming_message": "Bonjour",
+            "conversation_state": "Discovery",
+            "quotas_available": true,
+            "is_active_hours": true,
+            "sentiment_detected": null,
+            "conversation_id": id,
+        });
+        
+        let (status, claimed) = send_json(
+            &app, "POST", &format!("/api/v1/conversations/{}/takeover", id),
+            Some(serde_json::json!({ "operator": "alice", "lease_minutes": 15 })),
+        ).await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(claimed["takeover"]["operator"], "alice");
+        
+        for lease_minutes in [24 * 60 + 1, i64::MAX] {
+            let (status, _) = send_json(
+                &app, "POST", &format!("/api/v1/conversations/{}/takeover", id),
+                Some(serde_json::json!({ "operator": "alice", "lease_minutes": lease_minutes })),
+            ).await;
+            assert_eq!(status, StatusCode::BAD_REQUEST);
+        }
+        
+        let (status, _) = send_json(
+            &app, "POST", &format!("/api/v1/conversations/{}/takeover", id),
+            Some(serde_json::json!({ "operator": "bob" })),
+        ).await;
+        assert_eq!(status, StatusCode::CONFLICT);
+        
+        let (_, response) = send_json(&app, "POST", "/api/v1/decision", Some(decision.clone())).await;
+        assert_eq!(response["action"], "Ignore");
+        
+        let (_, takeovers) = send_json(&app, "GET", "/api/v1/takeovers", None).await;
+        assert!(takeovers.as_array().unwrap().iter().any(|c| c["id"] == id.as_str()));
+        
+        // The customer's number is enough to find the conversation under takeover
+        let mut by_phone = decision.clone();
+        by_phone["conversation_id"] = serde_json::Value::Null;
+        by_phone["phone_number"] = serde_json::json!("+33655555555");
+        let (_, response) = send_json(&app, "POST", "/api/v1/decision", Some(by_phone)).await;
+        assert_eq!(response["action"], "Ignore");
+        
+        let (status, _) = send_json(&app, "DELETE", &format!("/api/v1/conversations/{}/takeover?operator=bob", id), None).await;
+        assert_eq!(status, StatusCode::CONFLICT);
+        let (status, released) = send_json(&app, "DELETE", &format!("/api/v1/conversations/{}/takeover?operator=alice", id), None).await;
+        assert_eq!(status, StatusCode::OK);
+        assert!(released["takeover"].is_null());
+        
+        let (_, response) = send_json(&app, "POST", "/api/v1/decision", Some(decision)).await;
+        assert_eq!(response["action"], "RespondText");
+    }
+    
+    #[tokio::test]
+    async fn test_funnel_analytics_endpoint() {
+        let app = create_app_with_config(Some("test-api-key".to_string()), None);
+        
+        let (_, conversation) = send_json(
+            &app, "POST", "/api/v1/conversations",
+            Some(serde_json::json!({ "phone_number": "+33666666666" })),
+        ).await;
+        let id = conversation["id"].as_str().unwrap().to_string();
+        for event in ["ProductQuestion", "ObjectionRaised"] {
+            send_json(
+                &app, "POST", &format!("/api/v1/conversations/{}/events", id),
+                Some(serde_json::json!({ "event": event, "product_id": "prod-001" })),
+            ).await;
+        }
+        
+        let (status, report) = send_json(&app, "GET", "/api/v1/analytics/funnel", None).await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(report["total_conversations"], 1);
+        assert_eq!(report["state_distribution"]["Objection"], 1);
+        assert_eq!(report["objections_per_product"]["prod-001"], 1);
+        assert_eq!(report["conversions"][0]["from"], "Discovery");
+        assert_eq!(report["conversions"][0]["rate"], 1.0);
+    }
 }
@@ -1,29 +1,48 @@
-use sellify_core::api::{create_app, setup_auto_reset};
+use sellify_core::api::{create_app_state, create_app_with_state, setup_auto_reset, setup_funnel_job, setup_lifecycle_job, setup_outbound_worker};
+use sellify_core::engines::outbound::WebhookSender;
 use std::sync::Arc;
-use tokio::sync::Mutex;
-use sellify_core::engines::quota::{QuotaEngine, QuotaLimits};
 
 #[tokio::main]
 async fn main() {
     // Initialize logging
     env_logger::init();
     
-    // Create quota engine that will be shared with the scheduler
-    let quota_engine = Arc::new(Mutex::new(QuotaEngine::new(QuotaLimits {
-        messages_per_day: 200,
-        messages_per_week: 1000,
-        images_per_day: 50,
-        videos_per_week: 20,
-    })));
+    // Create application state (quota usage restored from storage)
+    let state = create_app_state();
+    let timezone = state.quotas.calendar().timezone();
     
-    // Setup automatic quota resets (daily at 00:00, weekly on Monday 00:00)
+    // Setup automatic quota resets for every known account
+    // (daily at 00:00, weekly on Monday 00:00, in the business timezone)
     log::info!("🕐 Setting up automatic quota reset scheduler...");
-    let _scheduler = setup_auto_reset(Arc::clone(&quota_engine))
+    let _scheduler = setup_auto_reset(Arc::clone(&state.quotas))
         .await
         .expect("Failed to setup quota scheduler");
     
+    // Setup conversation lifecycle rules (Negative for 48h → Frozen...)
+    let _lifecycle = setup_lifecycle_job(state.clone())
+        .await
+        .expect("Failed to setup lifecycle job");
+    
+    // Funnel gauges served by /metrics
+    let _funnel = setup_funnel_job(state.clone())
+        .await
+        .expect("Failed to setup funnel job");
+    
+    // Deliver queued replies through the WhatsApp gateway, if one is configured
+    let _outbound = match std::env::var("SELLIFY_GATEWAY_URL") {
+        Ok(url) => Some(
+            setup_outbound_worker(state.clone(), Arc::new(WebhookSender::new(url).expect("Failed to build gateway client")))
+                .await
+                .expect("Failed to setup outbound worker"),
+        ),
+        Err(_) => {
+            log::warn!("SELLIFY_GATEWAY_URL not set: queued replies are kept but not sent");
+            None
+        }
+    };
+    
     // Create application
-    let app = create_app();
+    let app = create_app_with_state(state);
     
     // Start server
     let listener = tokio::net::TcpListener::bind("0.0.0.0:3000")
@@ -33,7 +52,7 @@ async fn main() {
     println!("🚀 Sellify Core API Server starting on http://0.0.0.0:3000");
     println!("📖 Health check: http://localhost:3000/health");
     println!("📡 API endpoints: http://localhost:3000/api/v1/");
-    println!("🕐 Quota resets: Daily 00:00 UTC, Weekly Monday 00:00 UTC");
+    println!("🕐 Quota resets: Daily 00:00, Weekly Monday 00:00 ({}, checked every minute)", timezone);
     
     axum::serve(listener, app)
         .await
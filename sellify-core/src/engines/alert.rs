@@ -1,7 +1,11 @@
 use anyhow::Result;
+use serde::Serialize;
+use std::sync::Arc;
+
+use crate::engines::sentiment::{Sentiment, SentimentEngine};
 
 /// Alert triggers
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize)]
 pub enum AlertTrigger {
     Anger,
     Threat,
@@ -12,12 +16,23 @@ pub enum AlertTrigger {
 
 /// Alert Engine - Notifies humans without revealing to client
 pub struct AlertEngine {
+    #[allow(dead_code)] // Used once alerts are sent through the gateway
     alert_numbers: Vec<String>,
+    sentiment: Arc<SentimentEngine>,
 }
 
 impl AlertEngine {
     pub fn new(alert_numbers: Vec<String>) -> Self {
-        Self { alert_numbers }
+        Self {
+            alert_numbers,
+            sentiment: Arc::new(SentimentEngine::default()),
+        }
+    }
+
+    /// Detects threats and anger with the sentiment engine shared with the classifier
+    pub fn with_sentiment(mut self, sentiment: Arc<SentimentEngine>) -> Self {
+        self.sentiment = sentiment;
+        self
     }
 
     /// Send alert to internal WhatsApp number
@@ -45,15 +60,12 @@ impl AlertEngine {
             }
         }
 
-        // Check for threats
-        let threat_keywords = ["tuer", "frapper", "détruire"];
-        for keyword in threat_keywords {
-            if message_lower.contains(keyword) {
-                return Some(AlertTrigger::Threat);
-            }
+        // Threats and anger come from the sentiment engine (lexicons, negation, emoji, capitals)
+        match self.sentiment.analyze(message).sentiment {
+            Sentiment::Threat => Some(AlertTrigger::Threat),
+            Sentiment::Anger => Some(AlertTrigger::Anger),
+            _ => None,
         }
-
-        None
     }
 }
 
@@ -83,6 +95,13 @@ mod tests {
         assert!(matches!(trigger, Some(AlertTrigger::Threat)));
     }
 
+    #[test]
+    fn test_detect_anger_but_not_negated_threat() {
+        let engine = AlertEngine::default();
+        assert!(matches!(engine.detect_trigger("C'est INADMISSIBLE 😡"), Some(AlertTrigger::Anger)));
+        assert!(engine.detect_trigger("Je ne vais pas vous frapper, promis").is_none());
+    }
+
     #[test]
     fn test_no_trigger_on_normal_message() {
         let engine = AlertEngine::default();
@@ -0,0 +1,206 @@
+use serde::{Deserialize, Serialize};
+use chrono::{DateTime, Utc};
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+
+use crate::engines::conversation::{Conversation, ConversationEvent, TransitionRecord};
+
+/// Key used for objections recorded without a product in focus
+pub const UNKNOWN_PRODUCT: &str = "unknown";
+
+/// Share of the conversations that entered `from` and then moved to `to`
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct StateConversion {
+    pub from: String,
+    pub to: String,
+    pub conversations: usize,
+    /// conversations / conversations that were ever in `from` (0.0 - 1.0)
+    pub rate: f64,
+}
+
+/// Funnel report computed from the persisted transition history
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct FunnelReport {
+    pub total_conversations: usize,
+    /// Current state -> number of conversations
+    pub state_distribution: BTreeMap<String, usize>,
+    pub conversions: Vec<StateConversion>,
+    /// Median duration of completed stays in each state, in seconds
+    pub median_seconds_in_state: BTreeMap<String, i64>,
+    /// Product -> number of ObjectionRaised events
+    pub objections_per_product: BTreeMap<String, usize>,
+    pub generated_at: DateTime<Utc>,
+}
+
+/// Analytics Engine - Read-only statistics, never influences decisions
+pub struct AnalyticsEngine;
+
+impl AnalyticsEngine {
+    pub fn new() -> Self {
+        Self
+    }
+
+    /// Builds the funnel report
+    /// `transitions` must be in the order they happened (as returned by the storage)
+    pub fn funnel(&self, conversations: &[Conversation], transitions: &[TransitionRecord]) -> FunnelReport {
+        let mut history: HashMap<&str, Vec<&TransitionRecord>> = HashMap::new();
+        for record in transitions {
+            history.entry(record.conversation_id.as_str()).or_default().push(record);
+        }
+
+        let mut state_distribution = BTreeMap::new();
+        // State -> conversations that were ever in it
+        let mut entered: BTreeMap<String, BTreeSet<&str>> = BTreeMap::new();
+        // (from, to) -> conversations that made this move
+        let mut moves: BTreeMap<(String, String), BTreeSet<&str>> = BTreeMap::new();
+        let mut stays: BTreeMap<String, Vec<i64>> = BTreeMap::new();
+
+        for conversation in conversations {
+            *state_distribution.entry(conversation.state.to_string()).or_insert(0) += 1;
+
+            let records = history.get(conversation.id.as_str()).map(Vec::as_slice).unwrap_or_default();
+            let id = conversation.id.as_str();
+            let initial = records.first()
+                .map(|r| r.from_state.to_string())
+                .unwrap_or_else(|| conversation.state.to_string());
+            entered.entry(initial).or_default().insert(id);
+
+            let mut stay_started = conversation.created_at;
+            for record in records.iter().filter(|r| r.from_state != r.to_state) {
+                let (from, to) = (record.from_state.to_string(), record.to_state.to_string());
+                stays.entry(from.clone()).or_default().push((record.timestamp - stay_started).num_seconds());
+                stay_started = record.timestamp;
+
+                entered.entry(to.clone()).or_default().insert(id);
+                moves.entry((from, to)).or_default().insert(id);
+            }
+        }
+
+        let conversions = moves.into_iter()
+            .map(|((from, to), ids)| {
+                let base = entered.get(&from).map(BTreeSet::len).unwrap_or(0).max(1);
+                StateConversion {
+                    rate: ids.len() as f64 / base as f64,
+                    conversations: ids.len(),
+                    from,
+                    to,
+                }
+            })
+            .collect();
+
+        let median_seconds_in_state = stays.into_iter()
+            .map(|(state, mut durations)| (state, Self::median(&mut durations)))
+            .collect();
+
+        let mut objections_per_product = BTreeMap::new();
+        for record in transitions.iter().filter(|r| r.event == ConversationEvent::ObjectionRaised) {
+            let product = record.product_id.clone().unwrap_or_else(|| UNKNOWN_PRODUCT.to_string());
+            *objections_per_product.entry(product).or_insert(0) += 1;
+        }
+
+        FunnelReport {
+            total_conversations: conversations.len(),
+            state_distribution,
+            conversions,
+            median_seconds_in_state,
+            objections_per_product,
+            generated_at: Utc::now(),
+        }
+    }
+
+    /// Median of a non-empty list (mean of the two middle values for even lengths)
+    fn median(values: &mut [i64]) -> i64 {
+        values.sort_unstable();
+        let middle = values.len() / 2;
+        if values.len().is_multiple_of(2) {
+            (values[middle - 1] + values[middle]) / 2
+        } else {
+            values[middle]
+        }
+    }
+}
+
+impl Default for AnalyticsEngine {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::engines::conversation::{ConversationEngine, ConversationState};
+    use chrono::Duration;
+
+    /// Applies each event `minutes` after the conversation was created
+    fn play(
+        engine: &ConversationEngine,
+        phone: &str,
+        events: &[(ConversationEvent, i64)],
+    ) -> (Conversation, Vec<TransitionRecord>) {
+        let mut conversation = Conversation::new(phone, engine.initial_state());
+        let created_at = conversation.created_at;
+        let records = events.iter()
+            .map(|(event, minutes)| {
+                let mut record = engine.apply(&mut conversation, event.clone());
+                record.timestamp = created_at + Duration::minutes(*minutes);
+                record
+            })
+            .collect();
+        (conversation, records)
+    }
+
+    #[test]
+    fn test_funnel_report() {
+        let engine = ConversationEngine::new();
+
+        let (a, mut history) = play(&engine, "+1", &[
+            (ConversationEvent::ProductQuestion, 10),
+            (ConversationEvent::PriceInterest, 30),
+        ]);
+        let (b, records) = play(&engine, "+2", &[
+            (ConversationEvent::ProductQuestion, 20),
+            (ConversationEvent::ObjectionRaised, 25),
+            (ConversationEvent::NegativeResponse, 40),
+        ]);
+        history.extend(records);
+        let (c, records) = play(&engine, "+3", &[(ConversationEvent::NegativeResponse, 5)]);
+        history.extend(records);
+        let (d, _) = play(&engine, "+4", &[]);
+
+        let report = AnalyticsEngine::new().funnel(&[a, b, c, d], &history);
+
+        assert_eq!(report.total_conversations, 4);
+        assert_eq!(report.state_distribution["Intent"], 1);
+        assert_eq!(report.state_distribution["Negative"], 2);
+        assert_eq!(report.state_distribution["Discovery"], 1);
+
+        let rate = |from: &str, to: &str| {
+            report.conversions.iter().find(|c| c.from == from && c.to == to).unwrap().rate
+        };
+        assert_eq!(rate("Discovery", "Interest"), 0.5);
+        assert_eq!(rate("Interest", "Intent"), 0.5);
+        assert_eq!(rate("Objection", "Negative"), 1.0);
+
+        // Discovery stays: 10, 20 and 5 minutes
+        assert_eq!(report.median_seconds_in_state["Discovery"], 600);
+        // Interest stays: 20 and 5 minutes
+        assert_eq!(report.median_seconds_in_state["Interest"], 750);
+        assert_eq!(report.objections_per_product[UNKNOWN_PRODUCT], 1);
+    }
+
+    #[test]
+    fn test_objections_per_product() {
+        let engine = ConversationEngine::new();
+        let mut conversation = Conversation::new("+1", engine.initial_state());
+        conversation.focus_product_id = Some("prod-001".to_string());
+        let history = vec![
+            engine.apply(&mut conversation, ConversationEvent::ProductQuestion),
+            engine.apply(&mut conversation, ConversationEvent::ObjectionRaised),
+        ];
+
+        let report = AnalyticsEngine::new().funnel(&[conversation], &history);
+
+        assert_eq!(report.objections_per_product["prod-001"], 1);
+        assert_eq!(report.state_distribution[&ConversationState::Objection.to_string()], 1);
+    }
+}
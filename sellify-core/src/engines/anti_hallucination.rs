@@ -1,27 +1,62 @@
 use anyhow::{Result, anyhow};
 use regex;
+use rust_decimal::Decimal;
+use std::collections::BTreeMap;
+use std::sync::OnceLock;
+use crate::engines::knowledge_base::Price;
+use crate::engines::language::{Locale, LocalizedText};
 
 /// Anti-Hallucination Engine - Double lock (before + after AI)
 pub struct AntiHallucinationEngine {
-    forbidden_words: Vec<String>,
+    forbidden_words: BTreeMap<Locale, Vec<String>>,
+    fallback_messages: LocalizedText,
     max_length: usize,
 }
 
 impl AntiHallucinationEngine {
     pub fn new() -> Self {
+        let words = |list: &[&str]| list.iter().map(|w| w.to_string()).collect::<Vec<_>>();
         Self {
-            forbidden_words: vec![
-                "ai".to_string(),
-                "intelligence artificielle".to_string(),
-                "robot".to_string(),
-                "humain".to_string(),
-                "transférer".to_string(),
-                "escalade".to_string(),
-            ],
+            forbidden_words: BTreeMap::from([
+                (Locale::Fr, words(&[
+                    "ai",
+                    "intelligence artificielle",
+                    "robot",
+                    "humain",
+                    "transférer",
+                    "escalade",
+                ])),
+                (Locale::En, words(&[
+                    "artificial intelligence",
+                    "bot",
+                    "chatbot",
+                    "human",
+                    "transfer",
+                    "escalate",
+                ])),
+                (Locale::Ar, words(&[
+                    "ذكاء اصطناعي",
+                    "الذكاء الاصطناعي",
+                    "روبوت",
+                    "إنسان",
+                    "تحويل",
+                ])),
+            ]),
+            fallback_messages: LocalizedText::new(
+                Locale::Fr,
+                "Désolé, je n'ai pas bien compris votre demande. Pouvez-vous préciser ?",
+            )
+            .with(Locale::En, "Sorry, I didn't quite understand your request. Could you clarify?")
+            .with(Locale::Ar, "عذرًا، لم أفهم طلبك جيدًا. هل يمكنك التوضيح؟"),
             max_length: 500,
         }
     }
 
+    /// Forbidden words for one locale
+    pub fn forbidden_words_for(&self, locale: Locale) -> &[String] {
+        self.forbidden_words.get(&locale).map(Vec::as_slice).unwrap_or_default()
+    }
+
     /// Validates BEFORE AI generation
     pub fn validate_before_ai(&self, product_id: &str, action: &str) -> Result<()> {
         // TODO: Validate product exists
@@ -31,8 +66,13 @@ impl AntiHallucinationEngine {
         Ok(())
     }
 
-    /// Validates AFTER AI generation
-    pub fn validate_after_ai(&self, generated_text: &str) -> Result<String> {
+    /// Longest reply accepted after generation, in bytes
+    pub fn max_length(&self) -> usize {
+        self.max_length
+    }
+
+    /// Validates AFTER AI generation, against the forbidden words of the reply's locale
+    pub fn validate_after_ai(&self, generated_text: &str, locale: Locale) -> Result<String> {
         // Length check
         if generated_text.len() > self.max_length {
             return Err(anyhow!("Generated text exceeds max length"));
@@ -40,7 +80,7 @@ impl AntiHallucinationEngine {
 
         // Forbidden words check (whole words only)
         let text_lower = generated_text.to_lowercase();
-        for forbidden in &self.forbidden_words {
+        for forbidden in self.forbidden_words_for(locale) {
             // Check for whole word matches with word boundaries
             let pattern = format!(r"\b{}\b", regex::escape(forbidden));
             if let Ok(re) = regex::Regex::new(&pattern) {
@@ -61,9 +101,59 @@ impl AntiHallucinationEngine {
         Ok(generated_text.to_string())
     }
 
-    /// Returns neutral fallback if validation fails
-    pub fn get_fallback_message(&self) -> String {
-        "Désolé, je n'ai pas bien compris votre demande. Pouvez-vous préciser ?".to_string()
+    /// Rejects any price quoted by the AI that does not come from the knowledge base
+    /// `allowed` is the list of quotable prices (see `KnowledgeBaseEngine::quotable_prices`)
+    pub fn validate_prices(&self, generated_text: &str, allowed: &[Price]) -> Result<()> {
+        for quoted in Self::extract_prices(generated_text) {
+            if !allowed.iter().any(|p| p.same_amount(&quoted)) {
+                return Err(anyhow!("Generated text quotes a price not in the knowledge base: {}", quoted));
+            }
+        }
+        Ok(())
+    }
+
+    /// Finds amounts written next to a currency marker ("19,90 €", "$25", "150 dhs")
+    fn extract_prices(text: &str) -> Vec<Price> {
+        static SUFFIXED: OnceLock<regex::Regex> = OnceLock::new();
+        static PREFIXED: OnceLock<regex::Regex> = OnceLock::new();
+        let suffixed = SUFFIXED.get_or_init(|| regex::Regex::new(
+            r"(?i)(\d+(?:[.,]\d+)?)\s*(€|\$|£|(?:euros?|eur|usd|dollars?|gbp|mad|dhs?|dirhams?|xof|fcfa)\b)"
+        ).expect("valid price regex"));
+        let prefixed = PREFIXED.get_or_init(|| {
+            regex::Regex::new(r"(€|\$|£)\s*(\d+(?:[.,]\d+)?)").expect("valid price regex")
+        });
+
+        let suffixed_matches = suffixed.captures_iter(text).map(|c| (c[1].to_string(), c[2].to_string()));
+        let prefixed_matches = prefixed.captures_iter(text).map(|c| (c[2].to_string(), c[1].to_string()));
+
+        suffixed_matches
+            .chain(prefixed_matches)
+            .filter_map(|(amount, marker)| {
+                let amount = amount.replace(',', ".").parse::<Decimal>().ok()?;
+                let currency = Self::currency_from_marker(&marker)?;
+                Some(Price { amount, currency: currency.to_string() })
+            })
+            .collect()
+    }
+
+    fn currency_from_marker(marker: &str) -> Option<&'static str> {
+        match marker.to_lowercase().as_str() {
+            "€" | "eur" | "euro" | "euros" => Some("EUR"),
+            "$" | "usd" | "dollar" | "dollars" => Some("USD"),
+            "£" | "gbp" => Some("GBP"),
+            "mad" | "dh" | "dhs" | "dirham" | "dirhams" => Some("MAD"),
+            "xof" | "fcfa" => Some("XOF"),
+            _ => None,
+        }
+    }
+
+    /// Returns neutral fallback if validation fails, in the customer's language
+    pub fn get_fallback_message(&self, locale: Locale) -> String {
+        self.fallback_messages
+            .get(locale)
+            .or_else(|| self.fallback_messages.get(Locale::default()))
+            .unwrap_or_default()
+            .to_string()
     }
 }
 
@@ -81,13 +171,50 @@ mod tests {
     fn test_reject_forbidden_words() {
         let engine = AntiHallucinationEngine::new();
         let text = "Je suis une AI qui peut vous aider";
-        assert!(engine.validate_after_ai(text).is_err());
+        assert!(engine.validate_after_ai(text, Locale::Fr).is_err());
     }
 
     #[test]
     fn test_accept_valid_text() {
         let engine = AntiHallucinationEngine::new();
         let text = "Bonjour, comment puis-je vous aider ?";
-        assert!(engine.validate_after_ai(text).is_ok());
+        assert!(engine.validate_after_ai(text, Locale::Fr).is_ok());
+    }
+
+    #[test]
+    fn test_accept_price_from_knowledge_base() {
+        let engine = AntiHallucinationEngine::new();
+        let allowed = vec![Price::parse("19.90", "EUR").unwrap()];
+        assert!(engine.validate_prices("Il est à 19,90 € aujourd'hui", &allowed).is_ok());
+        assert!(engine.validate_prices("Prix : €19.9", &allowed).is_ok());
+        assert!(engine.validate_prices("Bonjour !", &[]).is_ok());
+    }
+
+    #[test]
+    fn test_reject_invented_price() {
+        let engine = AntiHallucinationEngine::new();
+        let allowed = vec![Price::parse("19.90", "EUR").unwrap()];
+        assert!(engine.validate_prices("Seulement 15 euros", &allowed).is_err());
+        assert!(engine.validate_prices("Only $19.90", &allowed).is_err());
+    }
+
+    #[test]
+    fn test_forbidden_words_follow_the_locale() {
+        let engine = AntiHallucinationEngine::new();
+        assert!(engine.validate_after_ai("I am a bot", Locale::En).is_err());
+        assert!(engine.validate_after_ai("أنا روبوت", Locale::Ar).is_err());
+
+        // "transfer" is only forbidden as an escalation word in English replies
+        assert!(engine.validate_after_ai("Le paiement par bank transfer est accepté", Locale::Fr).is_ok());
+        assert!(engine.validate_after_ai("We accept bank transfer", Locale::En).is_err());
+        assert!(engine.validate_after_ai("Nous acceptons le virement, pas de تحويل", Locale::Fr).is_ok());
+    }
+
+    #[test]
+    fn test_fallback_message_per_locale() {
+        let engine = AntiHallucinationEngine::new();
+        assert!(engine.get_fallback_message(Locale::Fr).starts_with("Désolé"));
+        assert!(engine.get_fallback_message(Locale::En).starts_with("Sorry"));
+        assert!(engine.get_fallback_message(Locale::Ar).starts_with("عذرًا"));
     }
 }
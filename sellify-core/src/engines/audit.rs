@@ -2,6 +2,8 @@ use serde::{Deserialize, Serialize};
 use anyhow::Result;
 use chrono::{DateTime, Utc};
 
+use crate::engines::conversation::TransitionRecord;
+
 /// Audit log entry - complete traceability
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct AuditLog {
@@ -16,6 +18,14 @@ pub struct AuditLog {
     pub sent_message: Option<String>,
     pub quotas_before: QuotaSnapshot,
     pub quotas_after: QuotaSnapshot,
+    /// Products whose data was exposed to the AI in the prompt
+    #[serde(default)]
+    pub exposed_product_ids: Vec<String>,
+    /// Catalog version (and its hash) the answer was based on
+    #[serde(default)]
+    pub catalog_version: Option<u32>,
+    #[serde(default)]
+    pub catalog_hash: Option<String>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -39,6 +49,17 @@ impl AuditEngine {
         Ok(())
     }
 
+    /// Logs a scheduler or operator transition
+    /// (the record itself is persisted with the conversation history)
+    pub fn log_transition(&self, record: &TransitionRecord) -> Result<()> {
+        log::info!(
+            "Audit transition: conversation={} {} -> {} event={} actor={} reason={:?}",
+            record.conversation_id, record.from_state, record.to_state,
+            record.event, record.actor, record.reason
+        );
+        Ok(())
+    }
+
     /// Retrieves audit logs for a conversation
     pub fn get_logs(&self, _conversation_id: &str) -> Result<Vec<AuditLog>> {
         // TODO: Retrieve from storage
@@ -81,6 +102,9 @@ mod tests {
                 messages_today: 1,
                 messages_this_week: 1,
             },
+            exposed_product_ids: vec!["prod-001".to_string()],
+            catalog_version: Some(1),
+            catalog_hash: Some("e3b0c442".to_string()),
         }
     }
 
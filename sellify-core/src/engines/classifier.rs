@@ -0,0 +1,327 @@
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+
+use crate::engines::conversation::ConversationEvent;
+use crate::engines::knowledge_base::KnowledgeBaseEngine;
+use crate::engines::sentiment::{Sentiment, SentimentAnalysis, SentimentEngine};
+
+/// Word lists used by the classifier (French, English and Arabic by default)
+/// Entries are matched as whole words or phrases, case-insensitively
+/// Threats are not listed here: they come from the sentiment engine
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ClassifierLexicons {
+    pub negative: Vec<String>,
+    pub objection: Vec<String>,
+    pub purchase: Vec<String>,
+    pub price: Vec<String>,
+    pub product_question: Vec<String>,
+}
+
+impl Default for ClassifierLexicons {
+    fn default() -> Self {
+        let words = |list: &[&str]| list.iter().map(|w| w.to_string()).collect::<Vec<_>>();
+        Self {
+            negative: words(&[
+                "non merci", "pas intéressé", "pas intéressée", "ça ne m'intéresse pas", "laissez moi tranquille",
+                "arrêtez", "stop", "no thanks", "not interested", "leave me alone", "لا شكرا", "غير مهتم",
+            ]),
+            objection: words(&[
+                "trop cher", "trop chère", "c'est cher", "pas confiance", "arnaque", "je vais réfléchir",
+                "too expensive", "scam", "i'll think about it", "غالي", "غالي بزاف",
+            ]),
+            purchase: words(&[
+                "je prends", "je le prends", "je la prends", "commander", "je commande", "j'achète",
+                "acheter", "i'll take it", "i'll take", "buy", "i want to order", "place an order", "أريد أن أطلب", "نطلب", "بغيت نشري",
+            ]),
+            price: words(&[
+                "prix", "combien", "coûte", "tarif", "promo", "price", "how much", "cost",
+                "بكم", "الثمن", "السعر", "شحال",
+            ]),
+            product_question: words(&[
+                "disponible", "taille", "couleur", "photo", "livraison", "available", "size",
+                "colour", "color", "delivery", "متوفر", "المقاس", "اللون", "التوصيل",
+            ]),
+        }
+    }
+}
+
+/// One event produced from a message, with the rule that produced it (explainability)
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Classification {
+    pub event: ConversationEvent,
+    /// Rule identifier, e.g. "lexicon:price" or "kb:product_keyword"
+    pub rule: String,
+    /// Word or phrase that matched
+    pub matched: String,
+    /// Product involved, for knowledge base rules
+    pub product_id: Option<String>,
+}
+
+/// Event Classifier - Turns a raw customer message into ConversationEvents
+/// Purely rule-based: same message + same lexicons + same KB = same events
+pub struct EventClassifier {
+    lexicons: ClassifierLexicons,
+    sentiment: Arc<SentimentEngine>,
+}
+
+impl EventClassifier {
+    pub fn new(lexicons: ClassifierLexicons) -> Self {
+        Self {
+            lexicons,
+            sentiment: Arc::new(SentimentEngine::default()),
+        }
+    }
+
+    /// Detects threats with the given sentiment engine (the one used by decisions and alerts)
+    pub fn with_sentiment(mut self, sentiment: Arc<SentimentEngine>) -> Self {
+        self.sentiment = sentiment;
+        self
+    }
+
+    /// Classifies a message into zero or more events, at most one per event type
+    /// Events are returned in a fixed priority order (threat first)
+    pub fn classify(&self, message: &str, kb: &KnowledgeBaseEngine) -> Vec<Classification> {
+        let text = Self::normalize(message);
+        let mut results = vec![];
+
+        // Negations apply: "je ne vais pas te frapper" is not a threat
+        let analysis = self.sentiment.analyze(message);
+        if analysis.sentiment == Sentiment::Threat {
+            results.push(Classification {
+                event: ConversationEvent::ThreatDetected,
+                rule: "sentiment:threat".to_string(),
+                matched: Self::threat_signal(&analysis),
+                product_id: None,
+            });
+        }
+
+        let lexicon_rules: [(&[String], ConversationEvent, &str); 4] = [
+            (&self.lexicons.negative, ConversationEvent::NegativeResponse, "lexicon:negative"),
+            (&self.lexicons.objection, ConversationEvent::ObjectionRaised, "lexicon:objection"),
+            (&self.lexicons.purchase, ConversationEvent::PurchaseIntent, "lexicon:purchase"),
+            (&self.lexicons.price, ConversationEvent::PriceInterest, "lexicon:price"),
+        ];
+
+        for (lexicon, event, rule) in lexicon_rules {
+            // Objection triggers defined in the KB take precedence over the generic lexicon
+            if event == ConversationEvent::ObjectionRaised {
+                if let Some(found) = self.match_kb_objection(&text, kb) {
+                    results.push(found);
+                    continue;
+                }
+            }
+            if let Some(matched) = Self::find_phrase(&text, lexicon) {
+                results.push(Classification {
+                    event,
+                    rule: rule.to_string(),
+                    matched,
+                    product_id: None,
+                });
+            }
+        }
+
+        if let Some(found) = self.match_product_question(&text, kb) {
+            results.push(found);
+        }
+
+        results
+    }
+
+    /// The event a message applies to the conversation: its highest-priority classification
+    /// The others are kept for explainability; a product they mention still becomes the focus
+    pub fn primary(classifications: &[Classification]) -> Option<&Classification> {
+        classifications.first()
+    }
+
+    /// Term or emoji that made the message a threat
+    fn threat_signal(analysis: &SentimentAnalysis) -> String {
+        let signals = &analysis.signals;
+        signals.iter().find_map(|s| s.strip_prefix("threat:"))
+            .or_else(|| signals.iter().find_map(|s| s.strip_prefix("emoji:")))
+            .unwrap_or_default()
+            .to_string()
+    }
+
+    fn match_kb_objection(&self, text: &str, kb: &KnowledgeBaseEngine) -> Option<Classification> {
+        kb.get_available_products().into_iter().find_map(|product| {
+            product.objections.iter().find_map(|objection| {
+                Self::contains_phrase(text, &objection.trigger).then(|| Classification {
+                    event: ConversationEvent::ObjectionRaised,
+                    rule: "kb:objection_trigger".to_string(),
+                    matched: objection.trigger.clone(),
+                    product_id: Some(product.id.clone()),
+                })
+            })
+        })
+    }
+
+    fn match_product_question(&self, text: &str, kb: &KnowledgeBaseEngine) -> Option<Classification> {
+        let from_kb = kb.get_available_products().into_iter().find_map(|product| {
+            product.keywords.iter().find_map(|keyword| {
+                Self::contains_phrase(text, keyword).then(|| Classification {
+                    event: ConversationEvent::ProductQuestion,
+                    rule: "kb:product_keyword".to_string(),
+                    matched: keyword.clone(),
+                    product_id: Some(product.id.clone()),
+                })
+            })
+        });
+
+        from_kb.or_else(|| {
+            Self::find_phrase(text, &self.lexicons.product_question).map(|matched| Classification {
+                event: ConversationEvent::ProductQuestion,
+                rule: "lexicon:product_question".to_string(),
+                matched,
+                product_id: None,
+            })
+        })
+    }
+
+    fn find_phrase(text: &str, lexicon: &[String]) -> Option<String> {
+        lexicon.iter().find(|phrase| Self::contains_phrase(text, phrase)).cloned()
+    }
+
+    /// Whole-word / whole-phrase match on normalized text
+    pub(crate) fn contains_phrase(normalized_text: &str, phrase: &str) -> bool {
+        let phrase = Self::normalize(phrase);
+        !phrase.trim().is_empty() && normalized_text.contains(&phrase)
+    }
+
+    /// Lowercase, punctuation as spaces, padded so that matches are on word boundaries
+    pub(crate) fn normalize(text: &str) -> String {
+        let words: Vec<String> = text
+            .to_lowercase()
+            .split(|c: char| !c.is_alphanumeric())
+            .filter(|w| !w.is_empty())
+            .map(str::to_string)
+            .collect();
+        format!(" {} ", words.join(" "))
+    }
+}
+
+impl Default for EventClassifier {
+    fn default() -> Self {
+        Self::new(ClassifierLexicons::default())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::engines::knowledge_base::{Objection, Price, Product};
+
+    fn create_kb() -> KnowledgeBaseEngine {
+        let mut kb = KnowledgeBaseEngine::new();
+        kb.load_products(vec![Product {
+            id: "prod-001".to_string(),
+            name: "Montre".into(),
+            short_description: "Montre connectée".into(),
+            long_description: "".into(),
+            price: Price::parse("49.90", "EUR").unwrap(),
+            keywords: vec!["montre".to_string()],
+            objections: vec![Objection {
+                trigger: "batterie".to_string(),
+                answer: "Autonomie de 7 jours".into(),
+            }],
+            media: vec![],
+            variants: vec![],
+            promotions: vec![],
+            stock: None,
+        }]).unwrap();
+        kb
+    }
+
+    fn events(classifications: &[Classification]) -> Vec<ConversationEvent> {
+        classifications.iter().map(|c| c.event.clone()).collect()
+    }
+
+    #[test]
+    fn test_price_words_map_to_price_interest() {
+        let classifier = EventClassifier::default();
+        let result = classifier.classify("Combien ça coûte ?", &KnowledgeBaseEngine::new());
+        assert_eq!(events(&result), vec![ConversationEvent::PriceInterest]);
+        assert_eq!(result[0].rule, "lexicon:price");
+        assert_eq!(result[0].matched, "combien");
+    }
+
+    #[test]
+    fn test_purchase_phrases_map_to_purchase_intent() {
+        let classifier = EventClassifier::default();
+        let kb = KnowledgeBaseEngine::new();
+        assert_eq!(events(&classifier.classify("Ok je prends !", &kb)), vec![ConversationEvent::PurchaseIntent]);
+        assert_eq!(events(&classifier.classify("Je veux commander", &kb)), vec![ConversationEvent::PurchaseIntent]);
+    }
+
+    #[test]
+    fn test_refusal_maps_to_negative_response() {
+        let classifier = EventClassifier::default();
+        let result = classifier.classify("Non merci, pas intéressé.", &KnowledgeBaseEngine::new());
+        assert_eq!(events(&result), vec![ConversationEvent::NegativeResponse]);
+    }
+
+    #[test]
+    fn test_kb_keywords_and_objections() {
+        let classifier = EventClassifier::default();
+        let result = classifier.classify("La montre, elle tient combien la batterie ?", &create_kb());
+
+        assert_eq!(events(&result), vec![
+            ConversationEvent::ObjectionRaised,
+            ConversationEvent::PriceInterest,
+            ConversationEvent::ProductQuestion,
+        ]);
+        assert_eq!(result[0].rule, "kb:objection_trigger");
+        assert_eq!(result[2].product_id.as_deref(), Some("prod-001"));
+        assert_eq!(EventClassifier::primary(&result).unwrap().event, ConversationEvent::ObjectionRaised);
+    }
+
+    #[test]
+    fn test_objection_on_a_product_is_the_primary_event() {
+        let classifier = EventClassifier::default();
+        let result = classifier.classify("La montre est trop chère", &create_kb());
+
+        assert_eq!(events(&result), vec![ConversationEvent::ObjectionRaised, ConversationEvent::ProductQuestion]);
+        let primary = EventClassifier::primary(&result).unwrap();
+        assert_eq!(primary.event, ConversationEvent::ObjectionRaised);
+        assert_eq!(result.iter().find_map(|c| c.product_id.as_deref()), Some("prod-001"));
+    }
+
+    #[test]
+    fn test_order_tracking_is_not_a_purchase() {
+        let classifier = EventClassifier::default();
+        let kb = KnowledgeBaseEngine::new();
+        assert!(classifier.classify("Where is my order?", &kb).is_empty());
+        assert_eq!(events(&classifier.classify("I want to order two", &kb)), vec![ConversationEvent::PurchaseIntent]);
+    }
+
+    #[test]
+    fn test_whole_words_only_and_empty_result() {
+        let classifier = EventClassifier::default();
+        let kb = KnowledgeBaseEngine::new();
+        // "prixe" / "stopper" must not match "prix" / "stop"
+        assert!(classifier.classify("prixe stopper", &kb).is_empty());
+        assert!(classifier.classify("Bonjour", &kb).is_empty());
+    }
+
+    #[test]
+    fn test_threats_come_from_the_sentiment_engine() {
+        let classifier = EventClassifier::default();
+        let kb = KnowledgeBaseEngine::new();
+        let result = classifier.classify("Je vais te frapper", &kb);
+        assert_eq!(events(&result), vec![ConversationEvent::ThreatDetected]);
+        assert_eq!(result[0].rule, "sentiment:threat");
+        assert_eq!(result[0].matched, "frapper");
+        assert!(classifier.classify("Je ne vais pas te frapper", &kb).is_empty());
+        assert!(classifier.classify("Will it hurt my skin?", &kb).is_empty());
+    }
+
+    #[test]
+    fn test_custom_lexicons() {
+        let lexicons = ClassifierLexicons {
+            price: vec!["kaç para".to_string()],
+            ..Default::default()
+        };
+        let classifier = EventClassifier::new(lexicons);
+        let result = classifier.classify("Bu saat kaç para?", &KnowledgeBaseEngine::new());
+        assert_eq!(events(&result), vec![ConversationEvent::PriceInterest]);
+    }
+}
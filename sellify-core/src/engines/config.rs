@@ -1,8 +1,21 @@
 use serde::{Deserialize, Serialize};
-use anyhow::Result;
+use anyhow::{Result, anyhow};
+use chrono::{DateTime, Duration, NaiveDateTime, NaiveTime, TimeZone, Utc};
+use chrono_tz::Tz;
+use std::collections::HashMap;
+use std::path::Path;
+use crate::engines::classifier::ClassifierLexicons;
+use crate::engines::lifecycle::LifecycleConfig;
+use crate::engines::media_policy::MediaPolicy;
+use crate::engines::outbound::OutboundConfig;
+use crate::engines::quota::{ThrottleConfig, WarmupConfig};
+use crate::engines::send_scheduler::SendScheduleConfig;
+use crate::engines::sentiment::SentimentConfig;
 
 /// Global configuration parameters
+/// Sections missing from a configuration file keep their default values
 #[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
 pub struct GlobalConfig {
     /// Active hours for automated responses
     pub active_hours: ActiveHours,
@@ -19,8 +32,36 @@ pub struct GlobalConfig {
     /// Anti-ban strategy settings
     pub anti_ban: AntiBanConfig,
     
+    /// Anti-ban settings per WhatsApp account (instance ID); other accounts use `anti_ban`
+    #[serde(default)]
+    pub accounts: HashMap<String, AntiBanConfig>,
+    
     /// Escalation thresholds
     pub escalation_threshold: EscalationThreshold,
+    
+    /// Lexicons used to classify incoming messages into conversation events
+    #[serde(default)]
+    pub classifier: ClassifierLexicons,
+    
+    /// Weighted lexicons and rules used to detect the sentiment of incoming messages
+    #[serde(default)]
+    pub sentiment: SentimentConfig,
+    
+    /// Time-based conversation rules (freeze / reopen)
+    #[serde(default)]
+    pub lifecycle: LifecycleConfig,
+    
+    /// Typing simulation, jitter and message splitting for outbound replies
+    #[serde(default)]
+    pub send_schedule: SendScheduleConfig,
+    
+    /// Retries and polling of the outbound queue worker
+    #[serde(default)]
+    pub outbound: OutboundConfig,
+    
+    /// When replies may include a product photo or video
+    #[serde(default)]
+    pub media_policy: MediaPolicy,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -30,6 +71,61 @@ pub struct ActiveHours {
     pub timezone: String,
 }
 
+impl ActiveHours {
+    /// Business timezone (IANA name, e.g. "Europe/Paris")
+    pub fn tz(&self) -> Result<Tz> {
+        self.timezone.parse().map_err(|_| anyhow!("Unknown timezone: {}", self.timezone))
+    }
+
+    fn bounds(&self) -> Result<(NaiveTime, NaiveTime)> {
+        let parse = |value: &str| NaiveTime::parse_from_str(value, "%H:%M")
+            .map_err(|_| anyhow!("Invalid time (expected HH:MM): {}", value));
+        Ok((parse(&self.start)?, parse(&self.end)?))
+    }
+
+    /// True if `now` is within [start, end) in the business timezone
+    /// An end before the start spans midnight, start == end means all day;
+    /// an invalid configuration never blocks
+    pub fn contains(&self, now: DateTime<Utc>) -> bool {
+        let (Ok(tz), Ok((start, end))) = (self.tz(), self.bounds()) else {
+            log::error!("Invalid active hours {:?}, treated as always active", self);
+            return true;
+        };
+        let time = now.with_timezone(&tz).time();
+        if start == end {
+            true
+        } else if start < end {
+            start <= time && time < end
+        } else {
+            time >= start || time < end
+        }
+    }
+
+    /// `now` when active, otherwise the next start of the active period
+    pub fn next_start(&self, now: DateTime<Utc>) -> DateTime<Utc> {
+        if self.contains(now) {
+            return now;
+        }
+        let (Ok(tz), Ok((start, _))) = (self.tz(), self.bounds()) else {
+            return now;
+        };
+        let local = now.with_timezone(&tz);
+        let mut day = local.date_naive();
+        if local.time() >= start {
+            day += Duration::days(1);
+        }
+        Self::resolve_local(tz, day.and_time(start))
+    }
+
+    /// Local time to UTC; a time skipped by a DST change moves to the end of the gap
+    pub(crate) fn resolve_local(tz: Tz, local: NaiveDateTime) -> DateTime<Utc> {
+        (0..=2)
+            .find_map(|hours| tz.from_local_datetime(&(local + Duration::hours(hours))).earliest())
+            .map(|time| time.with_timezone(&Utc))
+            .unwrap_or_else(|| local.and_utc())
+    }
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ResponseDelay {
     pub min_seconds: u64,
@@ -39,8 +135,86 @@ pub struct ResponseDelay {
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct AntiBanConfig {
     pub max_messages_per_day: u32,
+    /// Sliding window: any 60 minutes
     pub max_messages_per_hour: u32,
+    #[serde(default = "default_max_messages_per_week")]
+    pub max_messages_per_week: u32,
+    #[serde(default = "default_max_images_per_day")]
+    pub max_images_per_day: u32,
+    #[serde(default = "default_max_videos_per_week")]
+    pub max_videos_per_week: u32,
+    /// Sends to one contact in any sliding 60 minutes
+    #[serde(default = "default_max_messages_per_contact_per_hour")]
+    pub max_messages_per_contact_per_hour: u32,
+    /// Consecutive messages to a contact without a reply
+    #[serde(default = "default_max_unanswered")]
+    pub max_unanswered: u32,
+    /// Contacts messaged for the first time per day
+    #[serde(default = "default_max_new_contacts_per_day")]
+    pub max_new_contacts_per_day: u32,
     pub typing_simulation: bool,
+    /// Minimum delay between two sends, whatever their type
+    #[serde(default = "default_min_seconds_between_messages")]
+    pub min_seconds_between_messages: u64,
+    /// Longer delay after an image or a video
+    #[serde(default = "default_media_cooldown_seconds")]
+    pub media_cooldown_seconds: u64,
+    /// How gateway feedback tightens the limits
+    #[serde(default)]
+    pub throttle: ThrottleConfig,
+    /// Ramp of the daily and weekly limits for a new number
+    #[serde(default)]
+    pub warmup: WarmupConfig,
+}
+
+/// Longest configurable delay between sends (one day)
+const MAX_SEND_DELAY_SECONDS: u64 = 24 * 3600;
+
+impl AntiBanConfig {
+    /// Rejects delays too long to be meant (and to be added to a date)
+    fn validate(&self) -> Result<()> {
+        for (name, seconds) in [
+            ("min_seconds_between_messages", self.min_seconds_between_messages),
+            ("media_cooldown_seconds", self.media_cooldown_seconds),
+        ] {
+            if seconds > MAX_SEND_DELAY_SECONDS {
+                return Err(anyhow!("{} must be at most {} (got {})", name, MAX_SEND_DELAY_SECONDS, seconds));
+            }
+        }
+        Ok(())
+    }
+}
+
+fn default_max_messages_per_week() -> u32 {
+    1000
+}
+
+fn default_max_images_per_day() -> u32 {
+    50
+}
+
+fn default_max_videos_per_week() -> u32 {
+    20
+}
+
+fn default_max_messages_per_contact_per_hour() -> u32 {
+    10
+}
+
+fn default_max_unanswered() -> u32 {
+    3
+}
+
+fn default_max_new_contacts_per_day() -> u32 {
+    20
+}
+
+fn default_min_seconds_between_messages() -> u64 {
+    5
+}
+
+fn default_media_cooldown_seconds() -> u64 {
+    60
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -79,8 +253,19 @@ impl ConfigEngine {
             anti_ban: AntiBanConfig {
                 max_messages_per_day: 200,
                 max_messages_per_hour: 30,
+                max_messages_per_week: default_max_messages_per_week(),
+                max_images_per_day: default_max_images_per_day(),
+                max_videos_per_week: default_max_videos_per_week(),
+                max_messages_per_contact_per_hour: default_max_messages_per_contact_per_hour(),
+                max_unanswered: default_max_unanswered(),
+                max_new_contacts_per_day: default_max_new_contacts_per_day(),
                 typing_simulation: true,
+                min_seconds_between_messages: default_min_seconds_between_messages(),
+                media_cooldown_seconds: default_media_cooldown_seconds(),
+                throttle: ThrottleConfig::default(),
+                warmup: WarmupConfig::default(),
             },
+            accounts: HashMap::new(),
             escalation_threshold: EscalationThreshold {
                 max_misunderstandings: 3,
                 sensitive_keywords: vec![
@@ -89,13 +274,47 @@ impl ConfigEngine {
                     "police".to_string(),
                 ],
             },
+            classifier: ClassifierLexicons::default(),
+            sentiment: SentimentConfig::default(),
+            lifecycle: LifecycleConfig::default(),
+            send_schedule: SendScheduleConfig::default(),
+            outbound: OutboundConfig::default(),
+            media_policy: MediaPolicy::default(),
         }
     }
 
-    /// Loads configuration from storage
-    pub fn load(&mut self, _config_data: &str) -> Result<()> {
-        // TODO: Load from encrypted storage
-        Ok(())
+    pub fn from_toml(data: &str) -> Result<Self> {
+        let config: GlobalConfig = toml::from_str(data)
+            .map_err(|e| anyhow!("Invalid configuration: {}", e))?;
+        Self::from_config(config)
+    }
+
+    pub fn from_json(data: &str) -> Result<Self> {
+        let config: GlobalConfig = serde_json::from_str(data)
+            .map_err(|e| anyhow!("Invalid configuration: {}", e))?;
+        Self::from_config(config)
+    }
+
+    /// Loads a `.toml` or `.json` file
+    pub fn load(path: &Path) -> Result<Self> {
+        let data = std::fs::read_to_string(path)
+            .map_err(|e| anyhow!("Cannot read {}: {}", path.display(), e))?;
+        match path.extension().and_then(|e| e.to_str()) {
+            Some("json") => Self::from_json(&data),
+            Some("toml") => Self::from_toml(&data),
+            _ => Err(anyhow!("Unsupported configuration format: {}", path.display())),
+        }
+    }
+
+    /// Rejects active hours that would otherwise be ignored at runtime
+    fn from_config(config: GlobalConfig) -> Result<Self> {
+        config.active_hours.tz()?;
+        config.active_hours.bounds()?;
+        config.anti_ban.validate()?;
+        for (account, anti_ban) in &config.accounts {
+            anti_ban.validate().map_err(|e| anyhow!("Account {}: {}", account, e))?;
+        }
+        Ok(Self { config })
     }
 
     /// Gets the current configuration
@@ -112,8 +331,7 @@ impl ConfigEngine {
 
     /// Checks if current time is within active hours
     pub fn is_active_now(&self) -> bool {
-        // TODO: Implement time check with timezone
-        true
+        self.config.active_hours.contains(Utc::now())
     }
 }
 
@@ -123,6 +341,12 @@ impl Default for ConfigEngine {
     }
 }
 
+impl Default for GlobalConfig {
+    fn default() -> Self {
+        ConfigEngine::default_config()
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -133,6 +357,60 @@ mod tests {
         assert!(engine.get_config().ai_enabled);
     }
 
+    #[test]
+    fn test_active_hours_in_timezone() {
+        let hours = ActiveHours {
+            start: "09:00".to_string(),
+            end: "18:00".to_string(),
+            timezone: "Europe/Paris".to_string(),
+        };
+        // 08:30 UTC = 10:30 in Paris (summer time)
+        let now = Utc.with_ymd_and_hms(2026, 7, 1, 8, 30, 0).unwrap();
+        assert!(hours.contains(now));
+        // 17:00 UTC = 19:00 in Paris: next start is 07:00 UTC the next day
+        let evening = Utc.with_ymd_and_hms(2026, 7, 1, 17, 0, 0).unwrap();
+        assert!(!hours.contains(evening));
+        assert_eq!(hours.next_start(evening), Utc.with_ymd_and_hms(2026, 7, 2, 7, 0, 0).unwrap());
+    }
+
+    #[test]
+    fn test_active_hours_across_midnight() {
+        let hours = ActiveHours {
+            start: "22:00".to_string(),
+            end: "02:00".to_string(),
+            timezone: "UTC".to_string(),
+        };
+        assert!(hours.contains(Utc.with_ymd_and_hms(2026, 1, 1, 23, 0, 0).unwrap()));
+        assert!(hours.contains(Utc.with_ymd_and_hms(2026, 1, 2, 1, 0, 0).unwrap()));
+        let noon = Utc.with_ymd_and_hms(2026, 1, 2, 12, 0, 0).unwrap();
+        assert!(!hours.contains(noon));
+        assert_eq!(hours.next_start(noon), Utc.with_ymd_and_hms(2026, 1, 2, 22, 0, 0).unwrap());
+    }
+
+    #[test]
+    fn test_partial_configuration_keeps_defaults() {
+        let engine = ConfigEngine::from_json(r#"{ "ai_enabled": false, "accounts": { "shop-a": {
+            "max_messages_per_day": 50, "max_messages_per_hour": 7, "typing_simulation": true
+        } } }"#).unwrap();
+        let config = engine.get_config();
+        assert!(!config.ai_enabled);
+        assert_eq!(config.accounts["shop-a"].max_messages_per_day, 50);
+        assert_eq!(config.accounts["shop-a"].min_seconds_between_messages, default_min_seconds_between_messages());
+        assert_eq!(config.active_hours.start, "09:00");
+        assert_eq!(config.escalation_threshold.max_misunderstandings, 3);
+    }
+
+    #[test]
+    fn test_invalid_configuration_rejected() {
+        assert!(ConfigEngine::from_toml("[active_hours]\nstart = \"09:00\"\nend = \"18:00\"\ntimezone = \"Mars/Olympus\"").is_err());
+        assert!(ConfigEngine::from_toml("[active_hours]\nstart = \"9h\"\nend = \"18:00\"\ntimezone = \"UTC\"").is_err());
+        assert!(ConfigEngine::from_toml("ai_enabled = \"yes\"").is_err());
+        let anti_ban = "max_messages_per_day = 200\nmax_messages_per_hour = 30\ntyping_simulation = true";
+        assert!(ConfigEngine::from_toml(&format!("[anti_ban]\n{}\nmin_seconds_between_messages = 86401", anti_ban)).is_err());
+        assert!(ConfigEngine::from_toml(&format!("[accounts.shop-a]\n{}\nmedia_cooldown_seconds = {}", anti_ban, u64::MAX)).is_err());
+        assert!(ConfigEngine::load(Path::new("config.yaml")).is_err());
+    }
+
     #[test]
     fn test_default_config() {
         let engine = ConfigEngine::new();
@@ -1,7 +1,15 @@
 use serde::{Deserialize, Serialize};
+use anyhow::{Result, anyhow};
+use chrono::{DateTime, Utc};
+use std::fmt;
+use std::str::FromStr;
+
+use crate::engines::state_machine::TransitionTable;
 
 /// Conversation states - deterministic, rule-based transitions
-#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+/// `Custom` holds states added by a configured state machine (e.g. "Negotiation")
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq, Hash)]
+#[serde(into = "String", try_from = "String")]
 pub enum ConversationState {
     Discovery,
     Interest,
@@ -10,10 +18,61 @@ pub enum ConversationState {
     Negative,
     Escalated,
     Frozen,
+    Custom(String),
+}
+
+impl fmt::Display for ConversationState {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ConversationState::Custom(name) => f.write_str(name),
+            _ => write!(f, "{:?}", self),
+        }
+    }
+}
+
+impl From<ConversationState> for String {
+    fn from(state: ConversationState) -> Self {
+        state.to_string()
+    }
+}
+
+impl TryFrom<String> for ConversationState {
+    type Error = anyhow::Error;
+
+    fn try_from(s: String) -> Result<Self> {
+        s.parse()
+    }
+}
+
+/// State and event names are identifiers: a letter, then letters, digits or '_'
+fn is_valid_name(s: &str) -> bool {
+    s.starts_with(|c: char| c.is_ascii_alphabetic())
+        && s.chars().all(|c| c.is_ascii_alphanumeric() || c == '_')
+}
+
+impl FromStr for ConversationState {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        use ConversationState::*;
+        match s {
+            "Discovery" => Ok(Discovery),
+            "Interest" => Ok(Interest),
+            "Intent" => Ok(Intent),
+            "Objection" => Ok(Objection),
+            "Negative" => Ok(Negative),
+            "Escalated" => Ok(Escalated),
+            "Frozen" => Ok(Frozen),
+            _ if is_valid_name(s) => Ok(Custom(s.to_string())),
+            _ => Err(anyhow!("Invalid state: {}", s)),
+        }
+    }
 }
 
 /// Event signals that trigger state transitions
-#[derive(Debug, Clone, PartialEq)]
+/// `Custom` holds events added by a configured state machine (e.g. "PaymentReceived")
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq, Hash)]
+#[serde(into = "String", try_from = "String")]
 pub enum ConversationEvent {
     ProductQuestion,
     PriceInterest,
@@ -22,63 +81,371 @@ pub enum ConversationEvent {
     NegativeResponse,
     ThreatDetected,
     Freeze,
+    /// Lifecycle: a frozen conversation re-enters the funnel (not part of the table)
+    Reopen,
+    /// Operator hands an escalated conversation back to automation (not part of the table)
+    Release,
+    /// Automation hands the conversation to a human after repeated misunderstandings (not part of the table)
+    Escalate,
+    Custom(String),
+}
+
+impl fmt::Display for ConversationEvent {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ConversationEvent::Custom(name) => f.write_str(name),
+            _ => write!(f, "{:?}", self),
+        }
+    }
+}
+
+impl From<ConversationEvent> for String {
+    fn from(event: ConversationEvent) -> Self {
+        event.to_string()
+    }
+}
+
+impl TryFrom<String> for ConversationEvent {
+    type Error = anyhow::Error;
+
+    fn try_from(s: String) -> Result<Self> {
+        s.parse()
+    }
+}
+
+impl FromStr for ConversationEvent {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        use ConversationEvent::*;
+        match s {
+            "ProductQuestion" => Ok(ProductQuestion),
+            "PriceInterest" => Ok(PriceInterest),
+            "PurchaseIntent" => Ok(PurchaseIntent),
+            "ObjectionRaised" => Ok(ObjectionRaised),
+            "NegativeResponse" => Ok(NegativeResponse),
+            "ThreatDetected" => Ok(ThreatDetected),
+            "Freeze" => Ok(Freeze),
+            "Reopen" => Ok(Reopen),
+            "Release" => Ok(Release),
+            "Escalate" => Ok(Escalate),
+            _ if is_valid_name(s) => Ok(Custom(s.to_string())),
+            _ => Err(anyhow!("Invalid event: {}", s)),
+        }
+    }
+}
+
+impl ConversationEvent {
+    /// Overrides move to a state chosen outside the transition table
+    pub fn is_override(&self) -> bool {
+        matches!(self, ConversationEvent::Reopen | ConversationEvent::Release | ConversationEvent::Escalate)
+    }
+}
+
+/// Who caused a transition (serialized as "automation", "scheduler" or "operator:<name>")
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(into = "String", try_from = "String")]
+pub enum TransitionActor {
+    #[default]
+    Automation,
+    Scheduler,
+    Operator(String),
+}
+
+impl fmt::Display for TransitionActor {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            TransitionActor::Automation => f.write_str("automation"),
+            TransitionActor::Scheduler => f.write_str("scheduler"),
+            TransitionActor::Operator(name) => write!(f, "operator:{}", name),
+        }
+    }
+}
+
+impl FromStr for TransitionActor {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s {
+            "automation" => Ok(TransitionActor::Automation),
+            "scheduler" => Ok(TransitionActor::Scheduler),
+            _ => match s.strip_prefix("operator:") {
+                Some(name) if !name.is_empty() => Ok(TransitionActor::Operator(name.to_string())),
+                _ => Err(anyhow!("Invalid actor: {}", s)),
+            },
+        }
+    }
+}
+
+impl From<TransitionActor> for String {
+    fn from(actor: TransitionActor) -> Self {
+        actor.to_string()
+    }
+}
+
+impl TryFrom<String> for TransitionActor {
+    type Error = anyhow::Error;
+
+    fn try_from(s: String) -> Result<Self> {
+        s.parse()
+    }
+}
+
+/// Lease used when an operator claims a conversation without giving one
+pub const DEFAULT_TAKEOVER_LEASE_MINUTES: i64 = 60;
+
+/// Longest lease an operator can claim in one go
+pub const MAX_TAKEOVER_LEASE_MINUTES: i64 = 24 * 60;
+
+/// A human operator has taken the conversation over from the bot
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct HumanTakeover {
+    pub operator: String,
+    pub claimed_at: DateTime<Utc>,
+    /// The bot may answer again after this time, unless the lease is renewed
+    pub expires_at: DateTime<Utc>,
+}
+
+/// Persistent conversation entity - the state is owned by the backend, not the caller
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Conversation {
+    pub id: String,
+    pub phone_number: String,
+    pub state: ConversationState,
+    /// Product currently discussed
+    pub focus_product_id: Option<String>,
+    /// Consecutive messages the bot did not understand (reset by a successful exchange)
+    pub misunderstanding_count: u32,
+    /// When the current state was entered (drives the lifecycle timeouts)
+    pub state_since: DateTime<Utc>,
+    /// Set while a human operator handles the conversation
+    pub takeover: Option<HumanTakeover>,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+impl Conversation {
+    /// Starts a new conversation (see `ConversationEngine::initial_state`)
+    pub fn new(phone_number: &str, initial_state: ConversationState) -> Self {
+        let now = Utc::now();
+        Self {
+            id: uuid::Uuid::new_v4().to_string(),
+            phone_number: phone_number.to_string(),
+            state: initial_state,
+            focus_product_id: None,
+            misunderstanding_count: 0,
+            state_since: now,
+            takeover: None,
+            created_at: now,
+            updated_at: now,
+        }
+    }
+
+    /// True while an operator's lease is running
+    pub fn is_under_takeover(&self, now: DateTime<Utc>) -> bool {
+        self.takeover.as_ref().is_some_and(|t| t.expires_at > now)
+    }
+
+    /// Counts a fallback reply of the bot; returns the new count
+    pub fn record_misunderstanding(&mut self) -> u32 {
+        self.misunderstanding_count += 1;
+        self.updated_at = Utc::now();
+        self.misunderstanding_count
+    }
+
+    /// A successful exchange clears the count; returns true if it was set
+    pub fn reset_misunderstandings(&mut self) -> bool {
+        let was_set = self.misunderstanding_count > 0;
+        if was_set {
+            self.misunderstanding_count = 0;
+            self.updated_at = Utc::now();
+        }
+        was_set
+    }
+}
+
+/// One applied event - the history lets us replay how a state was reached
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct TransitionRecord {
+    pub conversation_id: String,
+    pub event: ConversationEvent,
+    pub from_state: ConversationState,
+    pub to_state: ConversationState,
+    /// Product in focus when the event occurred
+    pub product_id: Option<String>,
+    pub timestamp: DateTime<Utc>,
+    #[serde(default)]
+    pub actor: TransitionActor,
+    /// Why a scheduler or operator transition happened
+    #[serde(default)]
+    pub reason: Option<String>,
 }
 
 /// Conversation Engine - Maintains objective state of discussions
-pub struct ConversationEngine;
+pub struct ConversationEngine {
+    table: TransitionTable,
+}
 
 impl ConversationEngine {
+    /// Engine with the built-in funnel
     pub fn new() -> Self {
-        Self
+        Self::with_table(TransitionTable::builtin())
+    }
+
+    /// Engine with a configured (already validated) state machine
+    pub fn with_table(table: TransitionTable) -> Self {
+        Self { table }
+    }
+
+    pub fn table(&self) -> &TransitionTable {
+        &self.table
     }
 
     /// Transitions are deterministic and rule-based, NEVER AI-decided
     pub fn transition(&self, current: &ConversationState, event: ConversationEvent) -> ConversationState {
-        use ConversationState::*;
-        use ConversationEvent::*;
-        
-        match (current, event) {
-            // From Discovery
-            (Discovery, ProductQuestion) => Interest,
-            (Discovery, NegativeResponse) => Negative,
-            (Discovery, ThreatDetected) => Escalated,
-            
-            // From Interest
-            (Interest, PriceInterest) => Intent,
-            (Interest, ObjectionRaised) => Objection,
-            (Interest, NegativeResponse) => Negative,
-            (Interest, ThreatDetected) => Escalated,
-            
-            // From Intent
-            (Intent, PurchaseIntent) => Intent, // Stay in Intent
-            (Intent, ObjectionRaised) => Objection,
-            (Intent, NegativeResponse) => Negative,
-            (Intent, ThreatDetected) => Escalated,
-            
-            // From Objection
-            (Objection, ProductQuestion) => Interest,
-            (Objection, NegativeResponse) => Negative,
-            (Objection, ThreatDetected) => Escalated,
-            
-            // From Negative/Escalated - frozen states
-            (Negative, Freeze) => Frozen,
-            (Escalated, Freeze) => Frozen,
-            (Escalated, _) => Escalated, // Stay escalated
-            (Negative, _) => Negative,   // Stay negative
-            (Frozen, _) => Frozen,       // Stay frozen
-            
-            // Default: stay in current state
-            _ => current.clone(),
-        }
-    }
-
-    pub fn get_initial_state() -> ConversationState {
-        ConversationState::Discovery
+        self.table.next_state(current, &event)
+    }
+
+    /// Parses a state name, rejecting states unknown to the table
+    pub fn parse_state(&self, name: &str) -> Result<ConversationState> {
+        let state: ConversationState = name.parse()?;
+        if !self.table.has_state(&state) {
+            return Err(anyhow!("Invalid state: {}", name));
+        }
+        Ok(state)
+    }
+
+    /// Parses an event name, rejecting events unknown to the table
+    pub fn parse_event(&self, name: &str) -> Result<ConversationEvent> {
+        let event: ConversationEvent = name.parse()?;
+        if !self.table.has_event(&event) {
+            return Err(anyhow!("Invalid event: {}", name));
+        }
+        Ok(event)
+    }
+
+    /// Applies an event to a conversation and returns the record to persist
+    /// Every event is recorded, including those that keep the current state
+    pub fn apply(&self, conversation: &mut Conversation, event: ConversationEvent) -> TransitionRecord {
+        self.apply_as(conversation, event, TransitionActor::Automation, None)
+    }
+
+    /// Same as `apply`, on behalf of the scheduler or an operator
+    pub fn apply_as(
+        &self,
+        conversation: &mut Conversation,
+        event: ConversationEvent,
+        actor: TransitionActor,
+        reason: Option<String>,
+    ) -> TransitionRecord {
+        let to_state = self.transition(&conversation.state, event.clone());
+        Self::record(conversation, event, to_state, actor, reason)
+    }
+
+    /// Operator "release to automation": moves an Escalated conversation to a chosen state
+    pub fn release(
+        &self,
+        conversation: &mut Conversation,
+        target: ConversationState,
+        operator: &str,
+        reason: Option<String>,
+    ) -> Result<TransitionRecord> {
+        if conversation.state != ConversationState::Escalated {
+            return Err(anyhow!("Only Escalated conversations can be released (current: {})", conversation.state));
+        }
+        if !self.table.has_state(&target) || self.is_terminal_state(&target) {
+            return Err(anyhow!("Cannot release to {}: not an automated state", target));
+        }
+        if operator.trim().is_empty() {
+            return Err(anyhow!("Operator is required"));
+        }
+        let actor = TransitionActor::Operator(operator.to_string());
+        conversation.reset_misunderstandings();
+        Ok(Self::record(conversation, ConversationEvent::Release, target, actor, reason))
+    }
+
+    /// Automation hands the conversation to a human: moves it to Escalated
+    /// Fails if the table has no Escalated state or the conversation is already terminal
+    pub fn escalate(&self, conversation: &mut Conversation, reason: String) -> Result<TransitionRecord> {
+        let target = ConversationState::Escalated;
+        if !self.table.has_state(&target) {
+            return Err(anyhow!("The state machine has no {} state", target));
+        }
+        if self.is_terminal_state(&conversation.state) {
+            return Err(anyhow!("Cannot escalate from {}: terminal state", conversation.state));
+        }
+        Ok(Self::record(conversation, ConversationEvent::Escalate, target, TransitionActor::Automation, Some(reason)))
+    }
+
+    /// Lifecycle: a frozen conversation re-enters the initial state
+    pub fn reopen(&self, conversation: &mut Conversation, reason: String) -> TransitionRecord {
+        let initial = self.initial_state();
+        Self::record(conversation, ConversationEvent::Reopen, initial, TransitionActor::Automation, Some(reason))
+    }
+
+    fn record(
+        conversation: &mut Conversation,
+        event: ConversationEvent,
+        to_state: ConversationState,
+        actor: TransitionActor,
+        reason: Option<String>,
+    ) -> TransitionRecord {
+        let now = Utc::now();
+        let from_state = conversation.state.clone();
+
+        if to_state != from_state {
+            conversation.state_since = now;
+        }
+        conversation.state = to_state.clone();
+        conversation.updated_at = now;
+
+        TransitionRecord {
+            conversation_id: conversation.id.clone(),
+            event,
+            from_state,
+            to_state,
+            product_id: conversation.focus_product_id.clone(),
+            timestamp: now,
+            actor,
+            reason,
+        }
+    }
+
+    /// Re-runs a recorded history from the initial state
+    /// Fails if a record does not match what the rules produce (tampered or inconsistent history)
+    pub fn replay(&self, history: &[TransitionRecord]) -> Result<ConversationState> {
+        let mut state = self.initial_state();
+        for (index, record) in history.iter().enumerate() {
+            if record.from_state != state {
+                return Err(anyhow!(
+                    "History diverges at step {}: expected {} but record starts from {}",
+                    index, state, record.from_state
+                ));
+            }
+            // Overrides (reopen, operator release) are not derived from the table
+            let next = if record.event.is_override() {
+                record.to_state.clone()
+            } else {
+                self.transition(&state, record.event.clone())
+            };
+            if next != record.to_state {
+                return Err(anyhow!(
+                    "History diverges at step {}: {} + {} gives {}, record says {}",
+                    index, state, record.event, next, record.to_state
+                ));
+            }
+            state = next;
+        }
+        Ok(state)
+    }
+
+    pub fn initial_state(&self) -> ConversationState {
+        self.table.initial_state()
     }
     
     /// Check if state is terminal (no automation)
-    pub fn is_terminal_state(state: &ConversationState) -> bool {
-        matches!(state, ConversationState::Escalated | ConversationState::Frozen)
+    pub fn is_terminal_state(&self, state: &ConversationState) -> bool {
+        self.table.is_terminal(state)
     }
 }
 
@@ -94,7 +461,7 @@ mod tests {
 
     #[test]
     fn test_initial_state() {
-        let state = ConversationEngine::get_initial_state();
+        let state = ConversationEngine::new().initial_state();
         assert_eq!(state, ConversationState::Discovery);
     }
 
@@ -124,9 +491,10 @@ mod tests {
 
     #[test]
     fn test_escalated_is_terminal() {
-        assert!(ConversationEngine::is_terminal_state(&ConversationState::Escalated));
-        assert!(ConversationEngine::is_terminal_state(&ConversationState::Frozen));
-        assert!(!ConversationEngine::is_terminal_state(&ConversationState::Discovery));
+        let engine = ConversationEngine::new();
+        assert!(engine.is_terminal_state(&ConversationState::Escalated));
+        assert!(engine.is_terminal_state(&ConversationState::Frozen));
+        assert!(!engine.is_terminal_state(&ConversationState::Discovery));
     }
 
     #[test]
@@ -136,4 +504,104 @@ mod tests {
         let next = engine.transition(&current, ConversationEvent::ProductQuestion);
         assert_eq!(next, ConversationState::Interest);
     }
+
+    #[test]
+    fn test_parse_checks_the_table() {
+        let engine = ConversationEngine::new();
+        assert_eq!(engine.parse_state("Intent").unwrap(), ConversationState::Intent);
+        assert!(engine.parse_state("Negotiation").is_err());
+        assert!(engine.parse_event("PaymentReceived").is_err());
+    }
+
+    #[test]
+    fn test_apply_records_transition() {
+        let engine = ConversationEngine::new();
+        let mut conversation = Conversation::new("+33600000000", engine.initial_state());
+        conversation.focus_product_id = Some("prod-001".to_string());
+
+        let record = engine.apply(&mut conversation, ConversationEvent::ProductQuestion);
+
+        assert_eq!(conversation.state, ConversationState::Interest);
+        assert_eq!(record.conversation_id, conversation.id);
+        assert_eq!(record.from_state, ConversationState::Discovery);
+        assert_eq!(record.to_state, ConversationState::Interest);
+        assert_eq!(record.product_id.as_deref(), Some("prod-001"));
+    }
+
+    #[test]
+    fn test_replay_history_to_escalated() {
+        let engine = ConversationEngine::new();
+        let mut conversation = Conversation::new("+33600000000", engine.initial_state());
+        let history: Vec<TransitionRecord> = [
+            ConversationEvent::ProductQuestion,
+            ConversationEvent::ObjectionRaised,
+            ConversationEvent::ThreatDetected,
+        ]
+        .into_iter()
+        .map(|event| engine.apply(&mut conversation, event))
+        .collect();
+
+        assert_eq!(engine.replay(&history).unwrap(), ConversationState::Escalated);
+
+        let mut tampered = history.clone();
+        tampered[1].to_state = ConversationState::Intent;
+        assert!(engine.replay(&tampered).is_err());
+    }
+
+    #[test]
+    fn test_release_and_reopen_are_replayable() {
+        let engine = ConversationEngine::new();
+        let mut conversation = Conversation::new("+33600000000", engine.initial_state());
+        let mut history = vec![engine.apply(&mut conversation, ConversationEvent::ThreatDetected)];
+
+        assert!(engine.release(&mut conversation, ConversationState::Frozen, "alice", None).is_err());
+        let released = engine.release(&mut conversation, ConversationState::Interest, "alice", None).unwrap();
+        assert_eq!(released.actor, TransitionActor::Operator("alice".to_string()));
+        assert_eq!(conversation.state, ConversationState::Interest);
+        assert!(engine.release(&mut conversation, ConversationState::Intent, "alice", None).is_err());
+        history.push(released);
+
+        history.push(engine.apply_as(&mut conversation, ConversationEvent::NegativeResponse, TransitionActor::Automation, None));
+        history.push(engine.apply_as(&mut conversation, ConversationEvent::Freeze, TransitionActor::Scheduler, None));
+        history.push(engine.reopen(&mut conversation, "new inbound".to_string()));
+
+        assert_eq!(conversation.state, ConversationState::Discovery);
+        assert_eq!(engine.replay(&history).unwrap(), ConversationState::Discovery);
+        assert_eq!("operator:alice".parse::<TransitionActor>().unwrap(), TransitionActor::Operator("alice".to_string()));
+    }
+
+    #[test]
+    fn test_escalate_after_misunderstandings_is_replayable() {
+        let engine = ConversationEngine::new();
+        let mut conversation = Conversation::new("+33600000000", engine.initial_state());
+        let mut history = vec![engine.apply(&mut conversation, ConversationEvent::ProductQuestion)];
+
+        assert_eq!(conversation.record_misunderstanding(), 1);
+        assert_eq!(conversation.record_misunderstanding(), 2);
+        let escalated = engine.escalate(&mut conversation, "2 misunderstandings".to_string()).unwrap();
+        assert_eq!(escalated.from_state, ConversationState::Interest);
+        assert_eq!(conversation.state, ConversationState::Escalated);
+        assert!(engine.escalate(&mut conversation, "again".to_string()).is_err());
+        history.push(escalated);
+
+        // The operator hands it back with a clean count
+        history.push(engine.release(&mut conversation, ConversationState::Interest, "alice", None).unwrap());
+        assert_eq!(conversation.misunderstanding_count, 0);
+        assert!(!conversation.reset_misunderstandings());
+        assert_eq!(engine.replay(&history).unwrap(), ConversationState::Interest);
+    }
+
+    #[test]
+    fn test_state_and_event_round_trip_strings() {
+        for state in ["Discovery", "Interest", "Intent", "Objection", "Negative", "Escalated", "Frozen"] {
+            assert_eq!(state.parse::<ConversationState>().unwrap().to_string(), state);
+        }
+        assert_eq!("Freeze".parse::<ConversationEvent>().unwrap(), ConversationEvent::Freeze);
+        assert_eq!(
+            "PaymentReceived".parse::<ConversationEvent>().unwrap(),
+            ConversationEvent::Custom("PaymentReceived".to_string())
+        );
+        assert!("not an event".parse::<ConversationEvent>().is_err());
+        assert_eq!(serde_json::to_string(&ConversationState::Custom("Negotiation".to_string())).unwrap(), "\"Negotiation\"");
+    }
 }
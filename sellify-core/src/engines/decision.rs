@@ -1,5 +1,8 @@
 use serde::{Deserialize, Serialize};
-use anyhow::Result;
+use anyhow::{Result, anyhow};
+
+use crate::engines::media_policy::MediaPolicy;
+use crate::engines::policy::{DecisionPolicy, RuleTrace};
 
 /// Closed set of actions - Decision Engine can ONLY choose from these
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
@@ -12,53 +15,115 @@ pub enum Action {
     StopAutomation,
 }
 
+/// Misunderstandings before escalating to a human, unless configured
+pub const DEFAULT_MAX_MISUNDERSTANDINGS: u32 = 3;
+
 /// Decision context - all inputs for decision making
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Default)]
 pub struct DecisionContext {
     pub incoming_message: String,
     pub conversation_state: String,
     pub quotas_available: bool,
     pub is_active_hours: bool,
     pub sentiment_detected: Option<String>,
+    /// A human operator currently handles the conversation
+    pub human_takeover: bool,
+    /// Knowledge base products mentioned in the message
+    pub kb_matches: Vec<String>,
+    /// Hour of the day (0-23) in the business timezone
+    pub local_hour: u32,
+    /// Consecutive messages the bot did not understand
+    pub misunderstandings: u32,
+    /// `misunderstandings` reached the escalation threshold
+    pub misunderstanding_limit_reached: bool,
+    /// The conversation is in a terminal state of the state machine
+    pub terminal_state: bool,
+    /// The customer asks to see a product
+    pub media_requested: bool,
+    /// Media the media policy and the quotas allow to send, if any
+    pub media_id: Option<String>,
+}
+
+/// Policy rule alerting a human on repeated misunderstandings
+pub const REPEATED_MISUNDERSTANDING_RULE: &str = "repeated_misunderstanding";
+
+/// Chosen action, the policy rule that produced it and how it was reached
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Decision {
+    pub action: Action,
+    pub rule: String,
+    /// Rules evaluated in order, the last one is `rule`
+    pub trace: Vec<RuleTrace>,
 }
 
 /// Decision Engine - The CORE system that decides "what to do"
 /// IMPORTANT: This engine decides actions, NEVER how AI speaks
-pub struct DecisionEngine;
+pub struct DecisionEngine {
+    policy: DecisionPolicy,
+    media_policy: MediaPolicy,
+    max_misunderstandings: u32,
+}
 
 impl DecisionEngine {
-    /// Creates a new Decision Engine
+    /// Creates a new Decision Engine with the built-in policy
     pub fn new() -> Self {
-        Self
+        Self::with_policy(DecisionPolicy::builtin())
     }
 
-    /// Main decision function - returns ONE action from the closed set
-    /// Inputs: message, state, quotas, rules, schedule, knowledge base
-    /// Output: ONE deterministic action
-    pub fn decide(&self, context: DecisionContext) -> Result<Action> {
-        // Rule 1: If outside active hours -> Ignore
-        if !context.is_active_hours {
-            return Ok(Action::Ignore);
+    /// Engine with a configured (already validated) policy
+    pub fn with_policy(policy: DecisionPolicy) -> Self {
+        Self {
+            policy,
+            media_policy: MediaPolicy::default(),
+            max_misunderstandings: DEFAULT_MAX_MISUNDERSTANDINGS,
         }
+    }
 
-        // Rule 2: If quotas exceeded -> Stop or Delay
-        if !context.quotas_available {
-            return Ok(Action::Delay { seconds: 3600 }); // Wait 1 hour
-        }
+    pub fn with_media_policy(mut self, media_policy: MediaPolicy) -> Self {
+        self.media_policy = media_policy;
+        self
+    }
+
+    /// Escalation threshold (`escalation_threshold.max_misunderstandings`); 0 disables it
+    pub fn with_max_misunderstandings(mut self, max_misunderstandings: u32) -> Self {
+        self.max_misunderstandings = max_misunderstandings;
+        self
+    }
+
+    pub fn policy(&self) -> &DecisionPolicy {
+        &self.policy
+    }
 
-        // Rule 3: If anger/threat detected -> Alert human + Stop
-        if let Some(sentiment) = &context.sentiment_detected {
-            if sentiment == "anger" || sentiment == "threat" {
-                return Ok(Action::AlertHuman {
-                    reason: format!("Sentiment detected: {}", sentiment),
-                });
+    /// Used by the callers to fill `media_requested` and `media_id`
+    pub fn media_policy(&self) -> &MediaPolicy {
+        &self.media_policy
+    }
+
+    /// Used by the callers to fill `misunderstanding_limit_reached`
+    pub fn misunderstanding_limit_reached(&self, misunderstandings: u32) -> bool {
+        self.max_misunderstandings > 0 && misunderstandings >= self.max_misunderstandings
+    }
+
+    /// Main decision function - returns ONE action from the closed set
+    /// Inputs: message, state, quotas, rules, schedule, knowledge base
+    /// Output: ONE deterministic action, from the first policy rule that matches
+    pub fn decide(&self, context: DecisionContext) -> Result<Decision> {
+        let (rule, trace) = self.policy.evaluate(&context);
+        let rule = rule.ok_or_else(|| anyhow!("No decision rule matches the context"))?;
+
+        // A media rule without a fixed media sends the one allowed for the context
+        let mut action = rule.action.clone();
+        if let Action::RespondWithMedia { media_id, .. } = &mut action {
+            if media_id.is_empty() {
+                *media_id = context.media_id.clone()
+                    .ok_or_else(|| anyhow!("Rule {} sends media but none is allowed", rule.name))?;
             }
         }
 
-        // Rule 4: Normal flow - Respond with text
-        // Note: The actual text generation is delegated to IA Gateway
-        Ok(Action::RespondText {
-            text: String::new(), // Will be filled by IA Gateway
+        Ok(Decision {
+            action,
+            rule: rule.name.clone(),
+            trace,
         })
     }
 
@@ -94,10 +159,12 @@ mod tests {
             quotas_available: true,
             is_active_hours: false,
             sentiment_detected: None,
+            human_takeover: false,
+            ..Default::default()
         };
 
-        let action = engine.decide(context).unwrap();
-        assert_eq!(action, Action::Ignore);
+        let decision = engine.decide(context).unwrap();
+        assert_eq!(decision.action, Action::Ignore);
     }
 
     #[test]
@@ -109,9 +176,105 @@ mod tests {
             quotas_available: true,
             is_active_hours: true,
             sentiment_detected: Some("threat".to_string()),
+            human_takeover: false,
+            ..Default::default()
         };
 
-        let action = engine.decide(context).unwrap();
-        assert!(matches!(action, Action::AlertHuman { .. }));
+        let decision = engine.decide(context).unwrap();
+        assert!(matches!(decision.action, Action::AlertHuman { .. }));
+        assert_eq!(decision.rule, "threat_detected");
+        assert_eq!(decision.trace.len(), 7);
+        assert!(decision.trace[..6].iter().all(|rule| !rule.matched));
+    }
+
+    #[test]
+    fn test_decision_engine_ignore_during_takeover() {
+        let engine = DecisionEngine::new();
+        let context = DecisionContext {
+            incoming_message: "Vous êtes là ?".to_string(),
+            conversation_state: "Escalated".to_string(),
+            quotas_available: true,
+            is_active_hours: true,
+            sentiment_detected: Some("anger".to_string()),
+            human_takeover: true,
+            ..Default::default()
+        };
+
+        let decision = engine.decide(context).unwrap();
+        assert_eq!(decision.action, Action::Ignore);
+        assert_eq!(decision.rule, "human_takeover");
+    }
+
+    #[test]
+    fn test_decision_engine_stops_terminal_conversations() {
+        let engine = DecisionEngine::new();
+        let context = DecisionContext {
+            incoming_message: "Allô ?".to_string(),
+            conversation_state: "Frozen".to_string(),
+            quotas_available: true,
+            is_active_hours: true,
+            terminal_state: true,
+            ..Default::default()
+        };
+
+        let decision = engine.decide(context).unwrap();
+        assert_eq!(decision.action, Action::StopAutomation);
+        assert_eq!(decision.rule, "terminal_state");
+    }
+
+    #[test]
+    fn test_decision_engine_alerts_on_repeated_misunderstanding() {
+        let engine = DecisionEngine::new().with_max_misunderstandings(2);
+        let mut context = DecisionContext {
+            incoming_message: "???".to_string(),
+            conversation_state: "Interest".to_string(),
+            quotas_available: true,
+            is_active_hours: true,
+            misunderstandings: 1,
+            ..Default::default()
+        };
+        context.misunderstanding_limit_reached = engine.misunderstanding_limit_reached(context.misunderstandings);
+        assert_eq!(engine.decide(context.clone()).unwrap().rule, "respond");
+
+        // Escalated by the same message: the alert wins over StopAutomation
+        context.misunderstandings = 2;
+        context.misunderstanding_limit_reached = engine.misunderstanding_limit_reached(context.misunderstandings);
+        context.conversation_state = "Escalated".to_string();
+        context.terminal_state = true;
+        let decision = engine.decide(context.clone()).unwrap();
+        assert_eq!(decision.action, Action::AlertHuman { reason: "Repeated misunderstanding".to_string() });
+
+        // The count is cleared once the alert is raised: later messages are not alerted again
+        context.misunderstandings = 0;
+        context.misunderstanding_limit_reached = engine.misunderstanding_limit_reached(context.misunderstandings);
+        let decision = engine.decide(context).unwrap();
+        assert_eq!(decision.action, Action::StopAutomation);
+        assert_eq!(decision.rule, "terminal_state");
+        assert!(!DecisionEngine::new().with_max_misunderstandings(0).misunderstanding_limit_reached(10));
+    }
+
+    #[test]
+    fn test_decision_engine_responds_with_allowed_media() {
+        let engine = DecisionEngine::new();
+        let mut context = DecisionContext {
+            incoming_message: "Je peux voir une photo de la montre ?".to_string(),
+            conversation_state: "Interest".to_string(),
+            quotas_available: true,
+            is_active_hours: true,
+            kb_matches: vec!["prod-001".to_string()],
+            ..Default::default()
+        };
+        context.media_requested = engine.media_policy().is_request(&context.incoming_message);
+
+        // No media allowed (none in the catalog, or media quota reached): text only
+        let decision = engine.decide(context.clone()).unwrap();
+        assert_eq!(decision.rule, "respond");
+
+        context.media_id = Some("img-001".to_string());
+        let decision = engine.decide(context).unwrap();
+        assert_eq!(decision.action, Action::RespondWithMedia {
+            text: String::new(),
+            media_id: "img-001".to_string(),
+        });
     }
 }
@@ -0,0 +1,190 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+use crate::engines::knowledge_base::Product;
+use crate::engines::language::Locale;
+
+/// Knowledge base block injected in the AI prompt ("l'IA ne voit que ce contenu")
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct GroundingContext {
+    pub block: String,
+    /// Exactly the products whose data appears in `block` (for the audit)
+    pub exposed_product_ids: Vec<String>,
+    pub estimated_tokens: usize,
+    /// True if some selected products were left out to respect the budget
+    pub truncated: bool,
+}
+
+impl GroundingContext {
+    pub fn is_empty(&self) -> bool {
+        self.block.is_empty()
+    }
+}
+
+/// Context Builder - Serializes ONLY the allowed product fields
+/// (name, descriptions, price, objection answers) within a token budget
+pub struct ContextBuilder {
+    token_budget: usize,
+    locale: Locale,
+}
+
+impl ContextBuilder {
+    pub fn new(token_budget: usize, locale: Locale) -> Self {
+        Self { token_budget, locale }
+    }
+
+    /// Rough token estimate (~4 characters per token), deterministic
+    pub fn estimate_tokens(text: &str) -> usize {
+        text.chars().count().div_ceil(4)
+    }
+
+    /// Builds the prompt block from the products selected by the KB search
+    /// Products are kept in order; when one does not fit, a compact entry
+    /// (without long description and objection answers) is tried before stopping
+    pub fn build(&self, products: &[&Product], now: DateTime<Utc>) -> GroundingContext {
+        let mut context = GroundingContext::default();
+
+        for product in products {
+            // Never expose unavailable products or content in another language
+            if !product.is_in_stock() || !product.has_locale(self.locale) {
+                continue;
+            }
+
+            let candidates = [
+                self.serialize_product(product, now, true),
+                self.serialize_product(product, now, false),
+            ];
+            let remaining = self.token_budget - context.estimated_tokens;
+            match candidates.into_iter().find(|entry| Self::estimate_tokens(entry) <= remaining) {
+                Some(entry) => {
+                    context.estimated_tokens += Self::estimate_tokens(&entry);
+                    context.block.push_str(&entry);
+                    context.exposed_product_ids.push(product.id.clone());
+                }
+                None => {
+                    context.truncated = true;
+                    break;
+                }
+            }
+        }
+
+        context
+    }
+
+    fn serialize_product(&self, product: &Product, now: DateTime<Utc>, detailed: bool) -> String {
+        let locale = self.locale;
+        let mut entry = format!("[{}]\n", product.id);
+        entry.push_str(&format!("name: {}\n", product.name.get(locale).unwrap_or_default()));
+        entry.push_str(&format!(
+            "description: {}\n",
+            product.short_description.get(locale).unwrap_or_default()
+        ));
+
+        if detailed {
+            if let Some(long_description) = product.long_description.get(locale) {
+                entry.push_str(&format!("details: {}\n", long_description));
+            }
+        }
+
+        if product.variants.is_empty() {
+            if let Some(price) = product.effective_price(None, now) {
+                entry.push_str(&format!("price: {}\n", price));
+            }
+        }
+        for variant in product.available_variants() {
+            if let Some(price) = product.effective_price(Some(&variant.sku), now) {
+                let label: Vec<&str> = [variant.size.as_deref(), variant.colour.as_deref()]
+                    .into_iter()
+                    .flatten()
+                    .collect();
+                entry.push_str(&format!("price ({}): {}\n", label.join(", "), price));
+            }
+        }
+
+        if detailed {
+            for answer in product.objections.iter().filter_map(|o| o.answer_for(locale)) {
+                entry.push_str(&format!("answer: {}\n", answer));
+            }
+        }
+
+        entry.push('\n');
+        entry
+    }
+}
+
+impl Default for ContextBuilder {
+    fn default() -> Self {
+        Self::new(400, Locale::default())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::engines::knowledge_base::{Objection, Price};
+
+    fn create_product(id: &str, long_description: &str) -> Product {
+        Product {
+            id: id.to_string(),
+            name: "Montre".into(),
+            short_description: "Montre connectée".into(),
+            long_description: long_description.into(),
+            price: Price::parse("49.90", "EUR").unwrap(),
+            keywords: vec!["montre".to_string()],
+            objections: vec![Objection {
+                trigger: "cher".to_string(),
+                answer: "Garantie 2 ans incluse".into(),
+            }],
+            media: vec![],
+            variants: vec![],
+            promotions: vec![],
+            stock: None,
+        }
+    }
+
+    #[test]
+    fn test_build_exposes_allowed_fields_only() {
+        let product = create_product("prod-001", "Écran AMOLED");
+        let context = ContextBuilder::default().build(&[&product], Utc::now());
+
+        assert!(context.block.contains("name: Montre"));
+        assert!(context.block.contains("price: 49.90 EUR"));
+        assert!(context.block.contains("answer: Garantie 2 ans incluse"));
+        assert!(!context.block.contains("cher"));
+        assert!(!context.block.contains("montre\n"));
+        assert_eq!(context.exposed_product_ids, vec!["prod-001"]);
+        assert!(!context.truncated);
+    }
+
+    #[test]
+    fn test_build_respects_token_budget() {
+        let products: Vec<Product> = (0..10)
+            .map(|i| create_product(&format!("prod-{:03}", i), &"x".repeat(200)))
+            .collect();
+        let selected: Vec<&Product> = products.iter().collect();
+
+        let context = ContextBuilder::new(100, Locale::Fr).build(&selected, Utc::now());
+
+        assert!(context.estimated_tokens <= 100);
+        assert!(context.truncated);
+        assert!(!context.exposed_product_ids.is_empty());
+        for id in &context.exposed_product_ids {
+            assert!(context.block.contains(id.as_str()));
+        }
+        assert!(!context.block.contains(&format!("[prod-{:03}]", context.exposed_product_ids.len())));
+    }
+
+    #[test]
+    fn test_build_skips_other_locales_and_out_of_stock() {
+        let mut out_of_stock = create_product("prod-001", "");
+        out_of_stock.stock = Some(0);
+        let french_only = create_product("prod-002", "");
+
+        let context = ContextBuilder::default().build(&[&out_of_stock], Utc::now());
+        assert!(context.is_empty());
+
+        let context = ContextBuilder::new(400, Locale::En).build(&[&french_only], Utc::now());
+        assert!(context.is_empty());
+        assert!(context.exposed_product_ids.is_empty());
+    }
+}
@@ -1,5 +1,7 @@
 use anyhow::Result;
 use serde::{Deserialize, Serialize};
+use crate::engines::grounding::GroundingContext;
+use crate::engines::language::Locale;
 
 /// AI generation constraints
 #[derive(Debug, Clone)]
@@ -8,6 +10,10 @@ pub struct AIConstraints {
     pub tone: String,
     pub objective: String,
     pub prohibitions: Vec<String>,
+    /// Language the reply must be written in
+    pub locale: Locale,
+    /// Knowledge base content the AI is allowed to use (and nothing else)
+    pub grounding: GroundingContext,
 }
 
 /// IA Gateway - Generates text ONLY, under strict constraints
@@ -15,9 +21,11 @@ pub struct AIConstraints {
 /// invent information, send media
 pub struct IAGateway {
     api_key: Option<String>,
+    #[allow(dead_code)] // Used once the provider call is implemented
     client: reqwest::Client,
 }
 
+#[allow(dead_code)]
 #[derive(Debug, Serialize)]
 struct AIRequest {
     prompt: String,
@@ -25,6 +33,7 @@ struct AIRequest {
     temperature: f32,
 }
 
+#[allow(dead_code)]
 #[derive(Debug, Deserialize)]
 struct AIResponse {
     text: String,
@@ -41,14 +50,7 @@ impl IAGateway {
     /// Generate text under constraints
     /// Prompts are ALWAYS generated by backend, never by AI
     pub async fn generate_text(&self, constraints: AIConstraints) -> Result<String> {
-        // Build deterministic prompt
-        let prompt = format!(
-            "Objectif: {}\nTone: {}\nLongueur max: {} caractères\nInterdictions: {}\n\nGénère une réponse:",
-            constraints.objective,
-            constraints.tone,
-            constraints.max_length,
-            constraints.prohibitions.join(", ")
-        );
+        let prompt = self.build_prompt(&constraints);
 
         // TODO: Call actual AI API (OpenAI, Anthropic, etc.)
         log::info!("AI prompt: {}", prompt);
@@ -57,6 +59,26 @@ impl IAGateway {
         Ok("Réponse générée par IA (TODO: implémenter appel API)".to_string())
     }
 
+    /// Build deterministic prompt, in the customer's language
+    pub fn build_prompt(&self, constraints: &AIConstraints) -> String {
+        let prohibitions = constraints.prohibitions.join(", ");
+        let knowledge = &constraints.grounding.block;
+        match constraints.locale {
+            Locale::Fr => format!(
+                "Objectif: {}\nTone: {}\nLongueur max: {} caractères\nInterdictions: {}\n\nInformations autorisées (n'utilise rien d'autre):\n{}\nGénère une réponse:",
+                constraints.objective, constraints.tone, constraints.max_length, prohibitions, knowledge
+            ),
+            Locale::En => format!(
+                "Objective: {}\nTone: {}\nMax length: {} characters\nProhibitions: {}\n\nAllowed information (use nothing else):\n{}\nWrite a reply in English:",
+                constraints.objective, constraints.tone, constraints.max_length, prohibitions, knowledge
+            ),
+            Locale::Ar => format!(
+                "الهدف: {}\nالأسلوب: {}\nالطول الأقصى: {} حرفًا\nالمحظورات: {}\n\nالمعلومات المسموح بها (لا تستخدم غيرها):\n{}\nاكتب ردًا باللغة العربية:",
+                constraints.objective, constraints.tone, constraints.max_length, prohibitions, knowledge
+            ),
+        }
+    }
+
     /// Check if AI is available
     pub fn is_available(&self) -> bool {
         self.api_key.is_some()
@@ -93,9 +115,49 @@ mod tests {
             tone: "professionnel".to_string(),
             objective: "Répondre à une question produit".to_string(),
             prohibitions: vec!["AI".to_string(), "robot".to_string()],
+            locale: Locale::Fr,
+            grounding: GroundingContext::default(),
         };
         
         let result = gateway.generate_text(constraints).await;
         assert!(result.is_ok());
     }
+
+    #[test]
+    fn test_prompt_follows_locale() {
+        let gateway = IAGateway::default();
+        let mut constraints = AIConstraints {
+            max_length: 200,
+            tone: "friendly".to_string(),
+            objective: "Answer a product question".to_string(),
+            prohibitions: vec![],
+            locale: Locale::En,
+            grounding: GroundingContext::default(),
+        };
+        assert!(gateway.build_prompt(&constraints).starts_with("Objective:"));
+
+        constraints.locale = Locale::Ar;
+        assert!(gateway.build_prompt(&constraints).contains("باللغة العربية"));
+    }
+
+    #[test]
+    fn test_prompt_includes_grounding_block() {
+        let gateway = IAGateway::default();
+        let constraints = AIConstraints {
+            max_length: 200,
+            tone: "professionnel".to_string(),
+            objective: "Répondre à une question produit".to_string(),
+            prohibitions: vec![],
+            locale: Locale::Fr,
+            grounding: GroundingContext {
+                block: "[prod-001]\nname: Montre\nprice: 49.90 EUR\n\n".to_string(),
+                exposed_product_ids: vec!["prod-001".to_string()],
+                estimated_tokens: 12,
+                truncated: false,
+            },
+        };
+        let prompt = gateway.build_prompt(&constraints);
+        assert!(prompt.contains("Informations autorisées"));
+        assert!(prompt.contains("price: 49.90 EUR"));
+    }
 }
@@ -1,23 +1,201 @@
 use serde::{Deserialize, Serialize};
-use anyhow::Result;
+use anyhow::{Result, anyhow};
+use chrono::{DateTime, Utc};
+use rust_decimal::Decimal;
+use sha2::{Sha256, Digest};
+use std::collections::{BTreeMap, HashSet};
+use std::fmt;
+use std::sync::Arc;
+use crate::engines::language::{Locale, LocalizedText};
+use crate::engines::storage::StorageEngine;
+
+/// Price with a decimal amount and an ISO 4217 currency code
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Price {
+    /// Serialized as a string ("19.90") to keep exact decimals
+    pub amount: Decimal,
+    pub currency: String,
+}
+
+impl Price {
+    /// Creates a validated price
+    pub fn new(amount: Decimal, currency: &str) -> Result<Self> {
+        let price = Self {
+            amount,
+            currency: currency.to_string(),
+        };
+        price.validate()?;
+        Ok(price)
+    }
+
+    /// Parses a price from its decimal string representation
+    pub fn parse(amount: &str, currency: &str) -> Result<Self> {
+        let amount = amount.parse::<Decimal>()
+            .map_err(|e| anyhow!("Invalid price amount '{}': {}", amount, e))?;
+        Self::new(amount, currency)
+    }
+
+    /// Checks the amount is not negative and the currency is an ISO 4217 code
+    pub fn validate(&self) -> Result<()> {
+        if self.amount.is_sign_negative() {
+            return Err(anyhow!("Price amount cannot be negative: {}", self.amount));
+        }
+        if self.currency.len() != 3 || !self.currency.chars().all(|c| c.is_ascii_uppercase()) {
+            return Err(anyhow!("Invalid ISO 4217 currency code: '{}'", self.currency));
+        }
+        Ok(())
+    }
+
+    /// Compares amounts regardless of scale ("19.9" == "19.90")
+    pub fn same_amount(&self, other: &Price) -> bool {
+        self.currency == other.currency && self.amount.normalize() == other.amount.normalize()
+    }
+}
+
+impl fmt::Display for Price {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} {}", self.amount, self.currency)
+    }
+}
+
+/// Product variant (size, colour...) with its own SKU and price
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Variant {
+    pub sku: String,
+    #[serde(default)]
+    pub size: Option<String>,
+    #[serde(default)]
+    pub colour: Option<String>,
+    pub price: Price,
+    /// None = stock not tracked (always available)
+    #[serde(default)]
+    pub stock: Option<u32>,
+}
+
+impl Variant {
+    pub fn is_in_stock(&self) -> bool {
+        self.stock != Some(0)
+    }
+}
+
+/// Time-bounded promotional price
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Promotion {
+    pub price: Price,
+    pub starts_at: DateTime<Utc>,
+    pub ends_at: DateTime<Utc>,
+    /// None = applies to the product base price
+    #[serde(default)]
+    pub variant_sku: Option<String>,
+}
+
+impl Promotion {
+    /// Promotion window is [starts_at, ends_at)
+    pub fn is_active_at(&self, now: DateTime<Utc>) -> bool {
+        self.starts_at <= now && now < self.ends_at
+    }
+}
 
 /// Product structure
+/// Text fields accept a plain string (French) or one variant per locale
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Product {
     pub id: String,
-    pub name: String,
-    pub short_description: String,
-    pub long_description: String,
-    pub price: f64,
+    pub name: LocalizedText,
+    pub short_description: LocalizedText,
+    pub long_description: LocalizedText,
+    pub price: Price,
     pub keywords: Vec<String>,
     pub objections: Vec<Objection>,
     pub media: Vec<Media>,
+    #[serde(default)]
+    pub variants: Vec<Variant>,
+    #[serde(default)]
+    pub promotions: Vec<Promotion>,
+    /// None = stock not tracked (always available)
+    #[serde(default)]
+    pub stock: Option<u32>,
+}
+
+impl Product {
+    /// Product can be presented in `locale` (name and short description translated)
+    pub fn has_locale(&self, locale: Locale) -> bool {
+        self.name.has(locale) && self.short_description.has(locale)
+    }
+
+    /// A product with variants is available if at least one variant is
+    pub fn is_in_stock(&self) -> bool {
+        if self.stock == Some(0) {
+            return false;
+        }
+        self.variants.is_empty() || self.variants.iter().any(|v| v.is_in_stock())
+    }
+
+    pub fn get_variant(&self, sku: &str) -> Option<&Variant> {
+        self.variants.iter().find(|v| v.sku == sku)
+    }
+
+    /// Variants that can be offered to a customer
+    pub fn available_variants(&self) -> Vec<&Variant> {
+        self.variants.iter().filter(|v| v.is_in_stock()).collect()
+    }
+
+    /// Price actually charged at `now` (active promotion wins over list price)
+    pub fn effective_price(&self, variant_sku: Option<&str>, now: DateTime<Utc>) -> Option<Price> {
+        let list_price = match variant_sku {
+            Some(sku) => &self.get_variant(sku)?.price,
+            None => &self.price,
+        };
+
+        let promotion = self.promotions.iter()
+            .find(|p| p.variant_sku.as_deref() == variant_sku && p.is_active_at(now));
+
+        Some(promotion.map(|p| p.price.clone()).unwrap_or_else(|| list_price.clone()))
+    }
+
+    /// Checks prices, variants and promotions are consistent
+    pub fn validate(&self) -> Result<()> {
+        self.price.validate()?;
+
+        let mut skus = HashSet::new();
+        for variant in &self.variants {
+            variant.price.validate()?;
+            if !skus.insert(variant.sku.as_str()) {
+                return Err(anyhow!("Duplicate SKU '{}' in product {}", variant.sku, self.id));
+            }
+        }
+
+        for promotion in &self.promotions {
+            promotion.price.validate()?;
+            if promotion.starts_at >= promotion.ends_at {
+                return Err(anyhow!("Promotion for product {} ends before it starts", self.id));
+            }
+            let list_price = match &promotion.variant_sku {
+                Some(sku) => &self.get_variant(sku)
+                    .ok_or_else(|| anyhow!("Promotion references unknown SKU '{}' in product {}", sku, self.id))?
+                    .price,
+                None => &self.price,
+            };
+            if promotion.price.currency != list_price.currency {
+                return Err(anyhow!("Promotion currency differs from list price in product {}", self.id));
+            }
+        }
+
+        Ok(())
+    }
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Objection {
     pub trigger: String,
-    pub answer: String,
+    pub answer: LocalizedText,
+}
+
+impl Objection {
+    /// Answer in the customer's language only (no cross-language fallback)
+    pub fn answer_for(&self, locale: Locale) -> Option<&str> {
+        self.answer.get(locale)
+    }
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -33,22 +211,153 @@ pub enum MediaType {
     Video,
 }
 
+/// Immutable snapshot of the catalog - what the bot knew at a given time
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CatalogVersion {
+    pub version: u32,
+    /// SHA-256 (hex) of the serialized products
+    pub hash: String,
+    pub created_at: DateTime<Utc>,
+    /// Set when this version was created by rolling back to an earlier one
+    pub restored_from: Option<u32>,
+    pub products: Vec<Product>,
+}
+
+/// Product-level differences between two catalog versions
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct CatalogDiff {
+    pub from_version: u32,
+    pub to_version: u32,
+    pub added: Vec<String>,
+    pub removed: Vec<String>,
+    pub modified: Vec<String>,
+}
+
 /// Knowledge Base Engine - Defines the "authorized universe" for AI
 pub struct KnowledgeBaseEngine {
     products: Vec<Product>,
+    versions: Vec<CatalogVersion>,
+    /// Persists catalog versions so they survive a restart
+    storage: Option<Arc<StorageEngine>>,
 }
 
 impl KnowledgeBaseEngine {
     pub fn new() -> Self {
         Self {
             products: vec![],
+            versions: vec![],
+            storage: None,
         }
     }
 
-    /// Load products from storage
-    pub fn load_products(&mut self, products: Vec<Product>) -> Result<()> {
-        self.products = products;
-        Ok(())
+    /// Restores the saved catalog versions; the latest one is the catalog in use
+    pub fn with_storage(storage: Arc<StorageEngine>) -> Result<Self> {
+        let versions = storage.load_catalog_versions()?;
+        let products = versions.last().map(|v| v.products.clone()).unwrap_or_default();
+        if let Some(current) = versions.last() {
+            log::info!("Catalog version {} restored ({} versions)", current.version, versions.len());
+        }
+        Ok(Self {
+            products,
+            versions,
+            storage: Some(storage),
+        })
+    }
+
+    /// Load products from storage (rejected as a whole if one product is invalid)
+    /// Returns the catalog version now in use (a new one only if content changed)
+    pub fn load_products(&mut self, products: Vec<Product>) -> Result<u32> {
+        for product in &products {
+            product.validate()?;
+        }
+        self.commit_version(products, None)
+    }
+
+    /// Current catalog version, None until a catalog is loaded
+    pub fn current_version(&self) -> Option<&CatalogVersion> {
+        self.versions.last()
+    }
+
+    /// All catalog versions, oldest first
+    pub fn get_versions(&self) -> &[CatalogVersion] {
+        &self.versions
+    }
+
+    pub fn get_version(&self, version: u32) -> Option<&CatalogVersion> {
+        self.versions.iter().find(|v| v.version == version)
+    }
+
+    /// Compares two catalog versions product by product
+    pub fn diff_versions(&self, from: u32, to: u32) -> Result<CatalogDiff> {
+        let index = |version: u32| -> Result<BTreeMap<&str, &Product>> {
+            let snapshot = self.get_version(version)
+                .ok_or_else(|| anyhow!("Unknown catalog version: {}", version))?;
+            Ok(snapshot.products.iter().map(|p| (p.id.as_str(), p)).collect())
+        };
+        let before = index(from)?;
+        let after = index(to)?;
+
+        let mut diff = CatalogDiff {
+            from_version: from,
+            to_version: to,
+            added: vec![],
+            removed: vec![],
+            modified: vec![],
+        };
+        for (id, product) in &after {
+            match before.get(id) {
+                None => diff.added.push(id.to_string()),
+                Some(previous) if Self::hash_of(previous)? != Self::hash_of(product)? => {
+                    diff.modified.push(id.to_string())
+                }
+                Some(_) => {}
+            }
+        }
+        diff.removed = before.keys().filter(|id| !after.contains_key(*id)).map(|id| id.to_string()).collect();
+        Ok(diff)
+    }
+
+    /// Restores an earlier catalog as a NEW version (history is never rewritten)
+    pub fn rollback_to(&mut self, version: u32) -> Result<u32> {
+        let snapshot = self.get_version(version)
+            .ok_or_else(|| anyhow!("Unknown catalog version: {}", version))?;
+        let products = snapshot.products.clone();
+        self.commit_version(products, Some(version))
+    }
+
+    /// Makes `products` the catalog in use, saving the version first so that
+    /// memory never holds a version the storage does not
+    fn commit_version(&mut self, products: Vec<Product>, restored_from: Option<u32>) -> Result<u32> {
+        let hash = Self::hash_of(&products)?;
+        if let Some(current) = self.versions.last() {
+            if current.hash == hash {
+                let version = current.version;
+                self.products = products;
+                return Ok(version);
+            }
+        }
+
+        let version = self.versions.last().map(|v| v.version + 1).unwrap_or(1);
+        let snapshot = CatalogVersion {
+            version,
+            hash,
+            created_at: Utc::now(),
+            restored_from,
+            products,
+        };
+        if let Some(storage) = &self.storage {
+            storage.save_catalog_version(&snapshot)?;
+        }
+        self.products = snapshot.products.clone();
+        self.versions.push(snapshot);
+        log::info!("Catalog version {} committed", version);
+        Ok(version)
+    }
+
+    fn hash_of<T: Serialize + ?Sized>(value: &T) -> Result<String> {
+        let bytes = serde_json::to_vec(value)?;
+        let digest = Sha256::digest(&bytes);
+        Ok(digest.iter().map(|b| format!("{:02x}", b)).collect())
     }
 
     /// Get product by ID
@@ -66,13 +375,52 @@ impl KnowledgeBaseEngine {
         &self.products
     }
     
-    /// Search products by keyword
+    /// Get products that can be offered (out-of-stock items are never offered)
+    pub fn get_available_products(&self) -> Vec<&Product> {
+        self.products.iter().filter(|p| p.is_in_stock()).collect()
+    }
+
+    /// Get products that can be offered AND presented in `locale`
+    pub fn get_products_for_locale(&self, locale: Locale) -> Vec<&Product> {
+        self.products.iter()
+            .filter(|p| p.is_in_stock() && p.has_locale(locale))
+            .collect()
+    }
+
+    /// Search products by keyword (out-of-stock products excluded)
     pub fn search_by_keyword(&self, keyword: &str) -> Vec<&Product> {
         let keyword_lower = keyword.to_lowercase();
         self.products.iter()
+            .filter(|p| p.is_in_stock())
             .filter(|p| p.keywords.iter().any(|k| k.to_lowercase().contains(&keyword_lower)))
             .collect()
     }
+
+    /// Price to quote to a customer - the ONLY source of prices sent out
+    /// Returns None if the product or variant is unknown or out of stock
+    pub fn quote_price(&self, product_id: &str, variant_sku: Option<&str>, now: DateTime<Utc>) -> Option<Price> {
+        let product = self.get_product(product_id).filter(|p| p.is_in_stock())?;
+        if let Some(sku) = variant_sku {
+            if !product.get_variant(sku)?.is_in_stock() {
+                return None;
+            }
+        }
+        product.effective_price(variant_sku, now)
+    }
+
+    /// All prices that may legitimately appear in a message at `now`
+    pub fn quotable_prices(&self, now: DateTime<Utc>) -> Vec<Price> {
+        let mut prices = vec![];
+        for product in self.get_available_products() {
+            if product.variants.is_empty() {
+                prices.extend(product.effective_price(None, now));
+            }
+            for variant in product.available_variants() {
+                prices.extend(product.effective_price(Some(&variant.sku), now));
+            }
+        }
+        prices
+    }
 }
 
 impl Default for KnowledgeBaseEngine {
@@ -88,13 +436,26 @@ mod tests {
     fn create_test_product() -> Product {
         Product {
             id: "prod-001".to_string(),
-            name: "Test Product".to_string(),
-            short_description: "A test product".to_string(),
-            long_description: "A longer description".to_string(),
-            price: 99.99,
+            name: "Test Product".into(),
+            short_description: "A test product".into(),
+            long_description: "A longer description".into(),
+            price: Price::parse("99.99", "EUR").unwrap(),
             keywords: vec!["test".to_string(), "product".to_string()],
             objections: vec![],
             media: vec![],
+            variants: vec![],
+            promotions: vec![],
+            stock: None,
+        }
+    }
+
+    fn create_variant(sku: &str, amount: &str, stock: Option<u32>) -> Variant {
+        Variant {
+            sku: sku.to_string(),
+            size: Some("M".to_string()),
+            colour: None,
+            price: Price::parse(amount, "EUR").unwrap(),
+            stock,
         }
     }
 
@@ -135,4 +496,202 @@ mod tests {
         let results = kb.search_by_keyword("nonexistent");
         assert_eq!(results.len(), 0);
     }
+
+    #[test]
+    fn test_price_validation() {
+        assert!(Price::parse("19.90", "EUR").is_ok());
+        assert!(Price::parse("19.90", "eur").is_err());
+        assert!(Price::parse("19.90", "EURO").is_err());
+        assert!(Price::parse("-1", "EUR").is_err());
+        assert!(Price::parse("abc", "EUR").is_err());
+    }
+
+    #[test]
+    fn test_price_serializes_amount_as_string() {
+        let price = Price::parse("19.90", "EUR").unwrap();
+        let json = serde_json::to_value(&price).unwrap();
+        assert_eq!(json["amount"], "19.90");
+        assert_eq!(price.to_string(), "19.90 EUR");
+        assert!(price.same_amount(&Price::parse("19.9", "EUR").unwrap()));
+    }
+
+    #[test]
+    fn test_out_of_stock_never_offered() {
+        let mut kb = KnowledgeBaseEngine::new();
+        let mut product = create_test_product();
+        product.stock = Some(0);
+        kb.load_products(vec![product]).unwrap();
+
+        assert!(kb.search_by_keyword("test").is_empty());
+        assert!(kb.get_available_products().is_empty());
+        assert!(kb.quote_price("prod-001", None, Utc::now()).is_none());
+    }
+
+    #[test]
+    fn test_variant_stock_and_price() {
+        let mut kb = KnowledgeBaseEngine::new();
+        let mut product = create_test_product();
+        product.variants = vec![
+            create_variant("TS-M", "25.00", Some(0)),
+            create_variant("TS-L", "27.50", None),
+        ];
+        kb.load_products(vec![product]).unwrap();
+
+        let now = Utc::now();
+        assert!(kb.quote_price("prod-001", Some("TS-M"), now).is_none());
+        assert_eq!(kb.quote_price("prod-001", Some("TS-L"), now).unwrap().amount.to_string(), "27.50");
+        assert_eq!(kb.quotable_prices(now).len(), 1);
+    }
+
+    #[test]
+    fn test_promotion_applies_only_within_window() {
+        let mut product = create_test_product();
+        let now = Utc::now();
+        product.promotions = vec![Promotion {
+            price: Price::parse("79.99", "EUR").unwrap(),
+            starts_at: now - chrono::Duration::days(1),
+            ends_at: now + chrono::Duration::days(1),
+            variant_sku: None,
+        }];
+
+        assert_eq!(product.effective_price(None, now).unwrap().amount.to_string(), "79.99");
+        let after = now + chrono::Duration::days(2);
+        assert_eq!(product.effective_price(None, after).unwrap().amount.to_string(), "99.99");
+    }
+
+    #[test]
+    fn test_load_rejects_invalid_catalog() {
+        let mut kb = KnowledgeBaseEngine::new();
+        let mut product = create_test_product();
+        product.variants = vec![
+            create_variant("DUP", "10", None),
+            create_variant("DUP", "12", None),
+        ];
+        assert!(kb.load_products(vec![product]).is_err());
+
+        let mut product = create_test_product();
+        let now = Utc::now();
+        product.promotions = vec![Promotion {
+            price: Price::parse("10", "USD").unwrap(),
+            starts_at: now,
+            ends_at: now + chrono::Duration::days(1),
+            variant_sku: None,
+        }];
+        assert!(kb.load_products(vec![product]).is_err());
+        assert!(kb.get_all_products().is_empty());
+    }
+
+    #[test]
+    fn test_product_has_locale() {
+        let mut translated = create_test_product();
+        translated.name = translated.name.with(Locale::En, "Test Product");
+        translated.short_description = translated.short_description.with(Locale::En, "A test product");
+
+        assert!(create_test_product().has_locale(Locale::Fr));
+        assert!(!create_test_product().has_locale(Locale::En));
+        assert!(translated.has_locale(Locale::En));
+        assert!(!translated.has_locale(Locale::Ar));
+
+        let mut kb = KnowledgeBaseEngine::new();
+        translated.id = "prod-002".to_string();
+        kb.load_products(vec![create_test_product(), translated]).unwrap();
+        assert_eq!(kb.get_products_for_locale(Locale::Fr).len(), 2);
+        let english = kb.get_products_for_locale(Locale::En);
+        assert_eq!(english.len(), 1);
+        assert_eq!(english[0].id, "prod-002");
+        assert!(kb.get_products_for_locale(Locale::Ar).is_empty());
+    }
+
+    #[test]
+    fn test_objection_answer_per_locale() {
+        let objection = Objection {
+            trigger: "cher".to_string(),
+            answer: LocalizedText::new(Locale::Fr, "Livraison offerte").with(Locale::Ar, "التوصيل مجاني"),
+        };
+        assert_eq!(objection.answer_for(Locale::Ar), Some("التوصيل مجاني"));
+        assert_eq!(objection.answer_for(Locale::En), None);
+    }
+
+    #[test]
+    fn test_load_creates_catalog_versions() {
+        let mut kb = KnowledgeBaseEngine::new();
+        assert!(kb.current_version().is_none());
+
+        assert_eq!(kb.load_products(vec![create_test_product()]).unwrap(), 1);
+        // Same content: no new version
+        assert_eq!(kb.load_products(vec![create_test_product()]).unwrap(), 1);
+
+        let mut edited = create_test_product();
+        edited.price = Price::parse("89.99", "EUR").unwrap();
+        assert_eq!(kb.load_products(vec![edited]).unwrap(), 2);
+
+        let versions = kb.get_versions();
+        assert_eq!(versions.len(), 2);
+        assert_ne!(versions[0].hash, versions[1].hash);
+        assert_eq!(versions[0].hash.len(), 64);
+        // Earlier snapshot is untouched
+        assert_eq!(versions[0].products[0].price.amount.to_string(), "99.99");
+    }
+
+    #[test]
+    fn test_diff_versions() {
+        let mut kb = KnowledgeBaseEngine::new();
+        let mut second = create_test_product();
+        second.id = "prod-002".to_string();
+        kb.load_products(vec![create_test_product(), second]).unwrap();
+
+        let mut edited = create_test_product();
+        edited.stock = Some(3);
+        let mut third = create_test_product();
+        third.id = "prod-003".to_string();
+        kb.load_products(vec![edited, third]).unwrap();
+
+        let diff = kb.diff_versions(1, 2).unwrap();
+        assert_eq!(diff.added, vec!["prod-003"]);
+        assert_eq!(diff.removed, vec!["prod-002"]);
+        assert_eq!(diff.modified, vec!["prod-001"]);
+        assert!(kb.diff_versions(1, 9).is_err());
+    }
+
+    #[test]
+    fn test_rollback_creates_new_version() {
+        let mut kb = KnowledgeBaseEngine::new();
+        kb.load_products(vec![create_test_product()]).unwrap();
+        kb.load_products(vec![]).unwrap();
+        assert!(kb.get_all_products().is_empty());
+
+        let version = kb.rollback_to(1).unwrap();
+        assert_eq!(version, 3);
+        assert_eq!(kb.get_all_products().len(), 1);
+
+        let current = kb.current_version().unwrap();
+        assert_eq!(current.restored_from, Some(1));
+        assert_eq!(current.hash, kb.get_version(1).unwrap().hash);
+        assert!(kb.rollback_to(42).is_err());
+    }
+
+    #[test]
+    fn test_versions_restored_from_storage() {
+        let path = std::env::temp_dir().join(format!("test_catalog_{}.db", uuid::Uuid::new_v4()));
+        let open = || {
+            let mut storage = StorageEngine::new(path.clone()).unwrap();
+            storage.initialize().unwrap();
+            Arc::new(storage)
+        };
+
+        let mut kb = KnowledgeBaseEngine::with_storage(open()).unwrap();
+        kb.load_products(vec![create_test_product()]).unwrap();
+        kb.load_products(vec![]).unwrap();
+        kb.rollback_to(1).unwrap();
+        drop(kb);
+
+        // A restart sees the same history and serves the latest version
+        let restored = KnowledgeBaseEngine::with_storage(open()).unwrap();
+        assert_eq!(restored.get_versions().len(), 3);
+        assert_eq!(restored.current_version().unwrap().restored_from, Some(1));
+        assert_eq!(restored.get_all_products().len(), 1);
+        assert_eq!(restored.get_version(2).unwrap().products.len(), 0);
+
+        std::fs::remove_file(&path).ok();
+    }
 }
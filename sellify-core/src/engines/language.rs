@@ -0,0 +1,206 @@
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fmt;
+
+/// Supported customer languages
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Locale {
+    #[default]
+    Fr,
+    En,
+    Ar,
+}
+
+impl Locale {
+    pub const ALL: [Locale; 3] = [Locale::Fr, Locale::En, Locale::Ar];
+
+    /// ISO 639-1 code
+    pub fn code(&self) -> &'static str {
+        match self {
+            Locale::Fr => "fr",
+            Locale::En => "en",
+            Locale::Ar => "ar",
+        }
+    }
+
+    /// Parses an ISO 639-1 code ("fr", "EN", "ar-MA"...)
+    pub fn parse(code: &str) -> Option<Self> {
+        let primary = code.split(['-', '_']).next().unwrap_or_default().to_lowercase();
+        Self::ALL.into_iter().find(|l| l.code() == primary)
+    }
+}
+
+impl fmt::Display for Locale {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.code())
+    }
+}
+
+/// Text with one variant per locale
+/// A plain JSON string is accepted and treated as the default locale (French)
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(from = "LocalizedTextRepr")]
+pub struct LocalizedText(BTreeMap<Locale, String>);
+
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum LocalizedTextRepr {
+    Plain(String),
+    PerLocale(BTreeMap<Locale, String>),
+}
+
+impl From<LocalizedTextRepr> for LocalizedText {
+    fn from(repr: LocalizedTextRepr) -> Self {
+        match repr {
+            LocalizedTextRepr::Plain(text) => text.into(),
+            LocalizedTextRepr::PerLocale(map) => Self(map),
+        }
+    }
+}
+
+impl From<String> for LocalizedText {
+    fn from(text: String) -> Self {
+        Self(BTreeMap::from([(Locale::default(), text)]))
+    }
+}
+
+impl From<&str> for LocalizedText {
+    fn from(text: &str) -> Self {
+        text.to_string().into()
+    }
+}
+
+impl LocalizedText {
+    pub fn new(locale: Locale, text: &str) -> Self {
+        Self(BTreeMap::from([(locale, text.to_string())]))
+    }
+
+    /// Adds or replaces the variant for `locale`
+    pub fn with(mut self, locale: Locale, text: &str) -> Self {
+        self.0.insert(locale, text.to_string());
+        self
+    }
+
+    /// Strict lookup - never falls back to another language
+    pub fn get(&self, locale: Locale) -> Option<&str> {
+        self.0.get(&locale).map(String::as_str).filter(|t| !t.is_empty())
+    }
+
+    pub fn has(&self, locale: Locale) -> bool {
+        self.get(locale).is_some()
+    }
+
+    pub fn locales(&self) -> impl Iterator<Item = Locale> + '_ {
+        self.0.keys().copied()
+    }
+}
+
+/// Lightweight, deterministic language detector for incoming messages
+/// Arabic is detected by script, French vs English by stopwords and accents
+pub struct LanguageDetector {
+    default_locale: Locale,
+}
+
+const FRENCH_MARKERS: &[&str] = &[
+    "je", "tu", "vous", "nous", "le", "la", "les", "un", "une", "des", "est", "et",
+    "pour", "avec", "bonjour", "merci", "combien", "prix", "oui", "non", "pas", "ce",
+    "que", "qui", "du", "au", "mon", "ma", "mes", "votre", "svp", "coûte", "salut",
+];
+
+const ENGLISH_MARKERS: &[&str] = &[
+    "i", "you", "we", "the", "a", "an", "is", "are", "and", "for", "with", "hello",
+    "hi", "thanks", "thank", "how", "much", "price", "yes", "no", "not", "this",
+    "that", "what", "my", "your", "please", "does", "do", "cost", "want", "can",
+];
+
+impl LanguageDetector {
+    pub fn new(default_locale: Locale) -> Self {
+        Self { default_locale }
+    }
+
+    /// Detects the message language, falling back to the default locale when unsure
+    pub fn detect(&self, message: &str) -> Locale {
+        let arabic_chars = message.chars().filter(|c| Self::is_arabic(*c)).count();
+        let latin_chars = message.chars().filter(|c| c.is_alphabetic() && !Self::is_arabic(*c)).count();
+        if arabic_chars > 0 && arabic_chars >= latin_chars {
+            return Locale::Ar;
+        }
+
+        let lower = message.to_lowercase();
+        let words: Vec<&str> = lower
+            .split(|c: char| !c.is_alphanumeric() && c != '\'')
+            .flat_map(|w| w.split('\''))
+            .filter(|w| !w.is_empty())
+            .collect();
+
+        let mut french_score = words.iter().filter(|w| FRENCH_MARKERS.contains(w)).count();
+        let english_score = words.iter().filter(|w| ENGLISH_MARKERS.contains(w)).count();
+        if lower.chars().any(|c| "éèêàâçùûôîœ".contains(c)) {
+            french_score += 1;
+        }
+
+        match french_score.cmp(&english_score) {
+            std::cmp::Ordering::Greater => Locale::Fr,
+            std::cmp::Ordering::Less => Locale::En,
+            std::cmp::Ordering::Equal => self.default_locale,
+        }
+    }
+
+    fn is_arabic(c: char) -> bool {
+        matches!(c, '\u{0600}'..='\u{06FF}' | '\u{0750}'..='\u{077F}' | '\u{08A0}'..='\u{08FF}'
+            | '\u{FB50}'..='\u{FDFF}' | '\u{FE70}'..='\u{FEFF}')
+    }
+}
+
+impl Default for LanguageDetector {
+    fn default() -> Self {
+        Self::new(Locale::default())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_detect_french() {
+        let detector = LanguageDetector::default();
+        assert_eq!(detector.detect("Bonjour, combien coûte ce produit ?"), Locale::Fr);
+    }
+
+    #[test]
+    fn test_detect_english() {
+        let detector = LanguageDetector::default();
+        assert_eq!(detector.detect("Hello, how much is this product?"), Locale::En);
+    }
+
+    #[test]
+    fn test_detect_arabic() {
+        let detector = LanguageDetector::default();
+        assert_eq!(detector.detect("السلام عليكم، بكم هذا المنتج؟"), Locale::Ar);
+    }
+
+    #[test]
+    fn test_undecided_falls_back_to_default() {
+        let detector = LanguageDetector::new(Locale::En);
+        assert_eq!(detector.detect("42"), Locale::En);
+    }
+
+    #[test]
+    fn test_localized_text_accepts_plain_string() {
+        let text: LocalizedText = serde_json::from_str("\"Bonjour\"").unwrap();
+        assert_eq!(text.get(Locale::Fr), Some("Bonjour"));
+        assert_eq!(text.get(Locale::En), None);
+
+        let text: LocalizedText = serde_json::from_str(r#"{"fr": "Bonjour", "en": "Hello"}"#).unwrap();
+        assert_eq!(text.get(Locale::En), Some("Hello"));
+    }
+
+    #[test]
+    fn test_locale_parse() {
+        assert_eq!(Locale::parse("EN"), Some(Locale::En));
+        assert_eq!(Locale::parse("ar-MA"), Some(Locale::Ar));
+        assert_eq!(Locale::parse("de"), None);
+    }
+}
@@ -46,7 +46,7 @@ impl LicenseEngine {
     }
 
     /// Validates and loads license from encrypted storage
-    pub fn load_license(&mut self, encrypted_data: &[u8]) -> Result<LicenseState> {
+    pub fn load_license(&mut self, _encrypted_data: &[u8]) -> Result<LicenseState> {
         // TODO: Decrypt license data
         // TODO: Verify signature/integrity
         // TODO: Check expiration
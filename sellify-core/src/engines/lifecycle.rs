@@ -0,0 +1,181 @@
+use serde::{Deserialize, Serialize};
+use anyhow::Result;
+use chrono::{DateTime, Duration, Utc};
+
+use crate::engines::audit::AuditEngine;
+use crate::engines::conversation::{
+    Conversation, ConversationEngine, ConversationEvent, ConversationState, TransitionActor, TransitionRecord,
+};
+use crate::engines::storage::StorageEngine;
+
+/// "State X for N hours → Freeze"
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct FreezeRule {
+    pub state: ConversationState,
+    pub after_hours: u32,
+}
+
+/// Time-based conversation rules
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct LifecycleConfig {
+    /// Evaluated by the scheduled lifecycle job
+    pub freeze_after: Vec<FreezeRule>,
+    /// Days a conversation stays Frozen before a new inbound message reopens it
+    pub reopen_after_days: u32,
+}
+
+impl Default for LifecycleConfig {
+    fn default() -> Self {
+        Self {
+            freeze_after: vec![FreezeRule {
+                state: ConversationState::Negative,
+                after_hours: 48,
+            }],
+            reopen_after_days: 30,
+        }
+    }
+}
+
+/// Lifecycle Engine - Freezes stale conversations and reopens old frozen ones
+pub struct LifecycleEngine {
+    config: LifecycleConfig,
+}
+
+impl LifecycleEngine {
+    pub fn new(config: LifecycleConfig) -> Self {
+        Self { config }
+    }
+
+    /// Freeze rule that applies to this conversation at `now`, if any
+    pub fn freeze_due(&self, conversation: &Conversation, now: DateTime<Utc>) -> Option<&FreezeRule> {
+        self.config.freeze_after.iter().find(|rule| {
+            rule.state == conversation.state
+                && now - conversation.state_since >= Duration::hours(rule.after_hours as i64)
+        })
+    }
+
+    /// True if a new inbound message may bring this frozen conversation back into the funnel
+    pub fn can_reopen(&self, conversation: &Conversation, now: DateTime<Utc>) -> bool {
+        conversation.state == ConversationState::Frozen
+            && now - conversation.state_since >= Duration::days(self.config.reopen_after_days as i64)
+    }
+
+    /// Scheduled job body: sends `Freeze` to every conversation past its timeout
+    pub fn run(
+        &self,
+        storage: &StorageEngine,
+        conversation_engine: &ConversationEngine,
+        audit_engine: &AuditEngine,
+        now: DateTime<Utc>,
+    ) -> Result<Vec<TransitionRecord>> {
+        let mut records = vec![];
+
+        for rule in &self.config.freeze_after {
+            for mut conversation in storage.find_conversations_by_state(&rule.state)? {
+                if self.freeze_due(&conversation, now).is_none() {
+                    continue;
+                }
+                // A configured table may not freeze this state
+                if conversation_engine.transition(&conversation.state, ConversationEvent::Freeze) == conversation.state {
+                    continue;
+                }
+
+                let reason = format!("{} for {}h", rule.state, rule.after_hours);
+                let record = conversation_engine.apply_as(
+                    &mut conversation,
+                    ConversationEvent::Freeze,
+                    TransitionActor::Scheduler,
+                    Some(reason),
+                );
+                storage.save_transition(&conversation, &record)?;
+                audit_engine.log_transition(&record)?;
+                records.push(record);
+            }
+        }
+
+        Ok(records)
+    }
+
+    /// Called before classifying an inbound message
+    pub fn reopen_on_inbound(
+        &self,
+        conversation_engine: &ConversationEngine,
+        conversation: &mut Conversation,
+        now: DateTime<Utc>,
+    ) -> Option<TransitionRecord> {
+        if !self.can_reopen(conversation, now) {
+            return None;
+        }
+        let reason = format!("Frozen for {} days, new inbound message", self.config.reopen_after_days);
+        Some(conversation_engine.reopen(conversation, reason))
+    }
+}
+
+impl Default for LifecycleEngine {
+    fn default() -> Self {
+        Self::new(LifecycleConfig::default())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn create_storage() -> StorageEngine {
+        let db_path = std::env::temp_dir().join(format!("test_lifecycle_{}.db", uuid::Uuid::new_v4()));
+        let mut storage = StorageEngine::new(db_path).unwrap();
+        storage.initialize().unwrap();
+        storage
+    }
+
+    #[test]
+    fn test_negative_for_48h_is_frozen() {
+        let storage = create_storage();
+        let engine = ConversationEngine::new();
+        let lifecycle = LifecycleEngine::default();
+        let audit = AuditEngine::new();
+
+        let mut stale = Conversation::new("+33600000001", engine.initial_state());
+        let record = engine.apply(&mut stale, ConversationEvent::NegativeResponse);
+        stale.state_since = Utc::now() - Duration::hours(49);
+        storage.save_transition(&stale, &record).unwrap();
+
+        let mut recent = Conversation::new("+33600000002", engine.initial_state());
+        let record = engine.apply(&mut recent, ConversationEvent::NegativeResponse);
+        storage.save_transition(&recent, &record).unwrap();
+
+        let frozen = lifecycle.run(&storage, &engine, &audit, Utc::now()).unwrap();
+
+        assert_eq!(frozen.len(), 1);
+        assert_eq!(frozen[0].conversation_id, stale.id);
+        assert_eq!(frozen[0].actor, TransitionActor::Scheduler);
+        assert_eq!(frozen[0].reason.as_deref(), Some("Negative for 48h"));
+        assert_eq!(storage.get_conversation(&stale.id).unwrap().unwrap().state, ConversationState::Frozen);
+        assert_eq!(storage.get_conversation(&recent.id).unwrap().unwrap().state, ConversationState::Negative);
+
+        let history = storage.get_transitions(&stale.id).unwrap();
+        assert_eq!(history[1].actor, TransitionActor::Scheduler);
+        assert_eq!(engine.replay(&history).unwrap(), ConversationState::Frozen);
+
+        // Nothing left to do on the next run
+        assert!(lifecycle.run(&storage, &engine, &audit, Utc::now()).unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_frozen_reopens_after_30_days_on_inbound() {
+        let engine = ConversationEngine::new();
+        let lifecycle = LifecycleEngine::default();
+        let mut conversation = Conversation::new("+33600000003", engine.initial_state());
+        conversation.state = ConversationState::Frozen;
+        conversation.state_since = Utc::now() - Duration::days(29);
+
+        assert!(lifecycle.reopen_on_inbound(&engine, &mut conversation, Utc::now()).is_none());
+        assert_eq!(conversation.state, ConversationState::Frozen);
+
+        let record = lifecycle
+            .reopen_on_inbound(&engine, &mut conversation, Utc::now() + Duration::days(1))
+            .unwrap();
+        assert_eq!(record.event, ConversationEvent::Reopen);
+        assert_eq!(conversation.state, ConversationState::Discovery);
+    }
+}
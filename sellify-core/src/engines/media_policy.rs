@@ -0,0 +1,112 @@
+use serde::{Deserialize, Serialize};
+
+use crate::engines::classifier::EventClassifier;
+use crate::engines::conversation::ConversationState;
+use crate::engines::knowledge_base::{Media, MediaType, Product};
+
+/// When the bot may answer with a product photo or video
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct MediaPolicy {
+    pub enabled: bool,
+    /// Words or phrases asking to see a product (matched as whole words, case-insensitively)
+    pub request_keywords: Vec<String>,
+    /// States in which media may be sent
+    pub states: Vec<ConversationState>,
+    pub allow_video: bool,
+}
+
+impl Default for MediaPolicy {
+    fn default() -> Self {
+        let words = |list: &[&str]| list.iter().map(|w| w.to_string()).collect::<Vec<_>>();
+        Self {
+            enabled: true,
+            request_keywords: words(&[
+                "photo", "photos", "image", "voir", "montrer", "montrez", "vidéo",
+                "picture", "pictures", "pic", "show me", "see it", "video",
+                "صورة", "صور", "فيديو",
+            ]),
+            states: vec![
+                ConversationState::Discovery,
+                ConversationState::Interest,
+                ConversationState::Intent,
+                ConversationState::Objection,
+            ],
+            allow_video: true,
+        }
+    }
+}
+
+impl MediaPolicy {
+    /// The customer asks to see a product
+    pub fn is_request(&self, message: &str) -> bool {
+        let text = EventClassifier::normalize(message);
+        self.request_keywords.iter().any(|keyword| EventClassifier::contains_phrase(&text, keyword))
+    }
+
+    /// Media the policy allows for these products in `state`: the first one of the first product that has one
+    /// Quotas are checked by the caller
+    pub fn select<'a>(&self, state: &ConversationState, products: &[&'a Product]) -> Option<&'a Media> {
+        if !self.enabled || !self.states.contains(state) {
+            return None;
+        }
+        products.iter()
+            .filter(|product| product.is_in_stock())
+            .find_map(|product| {
+                product.media.iter().find(|media| self.allow_video || !matches!(media.media_type, MediaType::Video))
+            })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::engines::knowledge_base::Price;
+
+    fn product(media: Vec<Media>) -> Product {
+        Product {
+            id: "prod-001".to_string(),
+            name: "Montre".into(),
+            short_description: "Montre connectée".into(),
+            long_description: "".into(),
+            price: Price::parse("49.90", "EUR").unwrap(),
+            keywords: vec!["montre".to_string()],
+            objections: vec![],
+            media,
+            variants: vec![],
+            promotions: vec![],
+            stock: None,
+        }
+    }
+
+    fn media(id: &str, media_type: MediaType) -> Media {
+        Media {
+            id: id.to_string(),
+            media_type,
+            url: format!("https://cdn.example.com/{}", id),
+        }
+    }
+
+    #[test]
+    fn test_detects_media_requests() {
+        let policy = MediaPolicy::default();
+        assert!(policy.is_request("Je peux voir la montre ?"));
+        assert!(policy.is_request("Send me a PICTURE please"));
+        assert!(!policy.is_request("Au revoir"));
+    }
+
+    #[test]
+    fn test_select_follows_state_and_video_rules() {
+        let mut policy = MediaPolicy::default();
+        let watch = product(vec![media("vid-001", MediaType::Video), media("img-001", MediaType::Image)]);
+
+        assert_eq!(policy.select(&ConversationState::Interest, &[&watch]).unwrap().id, "vid-001");
+        assert!(policy.select(&ConversationState::Negative, &[&watch]).is_none());
+
+        policy.allow_video = false;
+        assert_eq!(policy.select(&ConversationState::Interest, &[&watch]).unwrap().id, "img-001");
+
+        let mut sold_out = watch.clone();
+        sold_out.stock = Some(0);
+        assert!(policy.select(&ConversationState::Interest, &[&sold_out]).is_none());
+    }
+}
@@ -7,11 +7,23 @@ pub mod config;
 pub mod knowledge_base;
 pub mod conversation;
 pub mod quota;
+pub mod quota_accounts;
 pub mod decision;
+pub mod policy;
+pub mod media_policy;
 pub mod alert;
 pub mod ia_gateway;
 pub mod anti_hallucination;
 pub mod audit;
+pub mod language;
+pub mod grounding;
+pub mod classifier;
+pub mod sentiment;
+pub mod state_machine;
+pub mod lifecycle;
+pub mod analytics;
+pub mod send_scheduler;
+pub mod outbound;
 
 // Re-exports for convenience
 pub use license::LicenseEngine;
@@ -20,8 +32,16 @@ pub use config::ConfigEngine;
 pub use knowledge_base::KnowledgeBaseEngine;
 pub use conversation::ConversationEngine;
 pub use quota::QuotaEngine;
+pub use quota_accounts::QuotaAccounts;
 pub use decision::DecisionEngine;
 pub use alert::AlertEngine;
 pub use ia_gateway::IAGateway;
 pub use anti_hallucination::AntiHallucinationEngine;
 pub use audit::AuditEngine;
+pub use language::LanguageDetector;
+pub use classifier::EventClassifier;
+pub use sentiment::SentimentEngine;
+pub use lifecycle::LifecycleEngine;
+pub use analytics::AnalyticsEngine;
+pub use send_scheduler::SendScheduler;
+pub use outbound::OutboundQueue;
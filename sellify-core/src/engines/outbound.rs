@@ -0,0 +1,576 @@
+use serde::{Deserialize, Serialize};
+use anyhow::{Result, anyhow};
+use chrono::{DateTime, Duration, Utc};
+use std::future::Future;
+use std::sync::Arc;
+
+use crate::engines::config::ActiveHours;
+use crate::engines::conversation::{Conversation, ConversationState};
+use crate::engines::quota::DeliveryFeedback;
+use crate::engines::quota_accounts::QuotaAccounts;
+use crate::engines::storage::StorageEngine;
+
+/// Content of a queued reply
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum OutboundPayload {
+    Text { text: String },
+    Media { text: String, media_id: String, is_video: bool },
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum OutboundStatus {
+    Pending,
+    Sent,
+    /// Gave up after `max_attempts` failed sends
+    Failed,
+    Cancelled,
+}
+
+impl std::fmt::Display for OutboundStatus {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let name = match self {
+            OutboundStatus::Pending => "pending",
+            OutboundStatus::Sent => "sent",
+            OutboundStatus::Failed => "failed",
+            OutboundStatus::Cancelled => "cancelled",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+impl std::str::FromStr for OutboundStatus {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s {
+            "pending" => Ok(OutboundStatus::Pending),
+            "sent" => Ok(OutboundStatus::Sent),
+            "failed" => Ok(OutboundStatus::Failed),
+            "cancelled" => Ok(OutboundStatus::Cancelled),
+            _ => Err(anyhow!("Unknown outbound status: {}", s)),
+        }
+    }
+}
+
+/// Reply waiting in the outbound queue
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct OutboundMessage {
+    pub id: String,
+    /// Enqueuing twice with the same key returns the first entry
+    pub idempotency_key: String,
+    /// WhatsApp account (instance) that sends the reply, and whose quotas apply
+    pub account: String,
+    pub conversation_id: String,
+    pub recipient: String,
+    pub payload: OutboundPayload,
+    /// State when the reply was queued; the reply is cancelled if the conversation moves
+    pub state_at_enqueue: ConversationState,
+    pub scheduled_at: DateTime<Utc>,
+    pub status: OutboundStatus,
+    pub attempts: u32,
+    pub last_error: Option<String>,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+/// Retry and polling parameters of the outbound worker
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct OutboundConfig {
+    pub max_attempts: u32,
+    /// Retry delay after the first failure, doubled after each one
+    pub base_backoff_seconds: u64,
+    pub max_backoff_seconds: u64,
+    /// Next check when a send waits for a reply from the contact
+    pub recheck_seconds: u64,
+    /// Entries handled per drain
+    pub batch_size: usize,
+}
+
+impl Default for OutboundConfig {
+    fn default() -> Self {
+        Self {
+            max_attempts: 5,
+            base_backoff_seconds: 30,
+            max_backoff_seconds: 3600,
+            recheck_seconds: 300,
+            batch_size: 50,
+        }
+    }
+}
+
+/// Delivers a message to the WhatsApp gateway
+pub trait OutboundSender {
+    fn send(&self, message: &OutboundMessage) -> impl Future<Output = Result<()>> + Send;
+}
+
+/// Send error meaning WhatsApp is throttling us; tightens the quotas harder than other failures
+#[derive(Debug, thiserror::Error)]
+#[error("Gateway rate limited: {0}")]
+pub struct GatewayRateLimited(pub String);
+
+/// A gateway that does not answer must not stall the worker
+const GATEWAY_CONNECT_TIMEOUT_SECONDS: u64 = 5;
+const GATEWAY_REQUEST_TIMEOUT_SECONDS: u64 = 30;
+
+/// Posts each message as JSON to a gateway URL; any non-2xx answer or timeout is a failure
+pub struct WebhookSender {
+    url: String,
+    client: reqwest::Client,
+}
+
+impl WebhookSender {
+    pub fn new(url: impl Into<String>) -> Result<Self> {
+        let client = reqwest::Client::builder()
+            .connect_timeout(std::time::Duration::from_secs(GATEWAY_CONNECT_TIMEOUT_SECONDS))
+            .timeout(std::time::Duration::from_secs(GATEWAY_REQUEST_TIMEOUT_SECONDS))
+            .build()?;
+        Ok(Self {
+            url: url.into(),
+            client,
+        })
+    }
+}
+
+impl OutboundSender for WebhookSender {
+    fn send(&self, message: &OutboundMessage) -> impl Future<Output = Result<()>> + Send {
+        let request = self.client.post(&self.url).json(message);
+        async move {
+            let response = request.send().await?;
+            if response.status() == reqwest::StatusCode::TOO_MANY_REQUESTS {
+                return Err(GatewayRateLimited(response.status().to_string()).into());
+            }
+            if !response.status().is_success() {
+                return Err(anyhow!("Gateway answered {}", response.status()));
+            }
+            Ok(())
+        }
+    }
+}
+
+/// What a drain did with the due entries
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct DrainReport {
+    pub sent: usize,
+    /// Moved to a later time by the quotas or the active hours
+    pub rescheduled: usize,
+    pub retried: usize,
+    pub failed: usize,
+    pub cancelled: usize,
+}
+
+/// Outbound Queue - Durable delayed delivery of replies
+pub struct OutboundQueue {
+    storage: Arc<StorageEngine>,
+    config: OutboundConfig,
+    active_hours: ActiveHours,
+}
+
+impl OutboundQueue {
+    pub fn new(storage: Arc<StorageEngine>, config: OutboundConfig, active_hours: ActiveHours) -> Self {
+        Self {
+            storage,
+            config,
+            active_hours,
+        }
+    }
+
+    /// Hours during which replies are delivered
+    pub fn active_hours(&self) -> &ActiveHours {
+        &self.active_hours
+    }
+
+    /// Queues a reply to `conversation`; returns the entry and whether it was created
+    /// An existing entry with the same idempotency key is returned unchanged
+    pub fn enqueue(
+        &self,
+        conversation: &Conversation,
+        account: &str,
+        idempotency_key: &str,
+        payload: OutboundPayload,
+        scheduled_at: DateTime<Utc>,
+    ) -> Result<(OutboundMessage, bool)> {
+        QuotaAccounts::validate(account)?;
+        let now = Utc::now();
+        let message = OutboundMessage {
+            id: uuid::Uuid::new_v4().to_string(),
+            idempotency_key: idempotency_key.to_string(),
+            account: account.to_string(),
+            conversation_id: conversation.id.clone(),
+            recipient: conversation.phone_number.clone(),
+            payload,
+            state_at_enqueue: conversation.state.clone(),
+            scheduled_at,
+            status: OutboundStatus::Pending,
+            attempts: 0,
+            last_error: None,
+            created_at: now,
+            updated_at: now,
+        };
+        self.storage.enqueue_outbound(&message)
+    }
+
+    pub fn get(&self, id: &str) -> Result<Option<OutboundMessage>> {
+        self.storage.get_outbound(id)
+    }
+
+    pub fn list(&self, status: Option<OutboundStatus>) -> Result<Vec<OutboundMessage>> {
+        self.storage.list_outbound(status)
+    }
+
+    /// Cancels a pending entry; errors if it was already sent, failed or cancelled
+    pub fn cancel(&self, id: &str, reason: &str) -> Result<Option<OutboundMessage>> {
+        let Some(mut message) = self.get(id)? else {
+            return Ok(None);
+        };
+        if message.status != OutboundStatus::Pending {
+            return Err(anyhow!("Outbound message {} is {}", id, message.status));
+        }
+        self.finish(&mut message, OutboundStatus::Cancelled, Some(reason.to_string()))?;
+        Ok(Some(message))
+    }
+
+    /// Worker body: sends the due entries, re-checking conversation state,
+    /// active hours and quotas right before each send
+    pub async fn drain<S: OutboundSender>(
+        &self,
+        sender: &S,
+        quotas: &QuotaAccounts,
+        now: DateTime<Utc>,
+    ) -> Result<DrainReport> {
+        let mut report = DrainReport::default();
+
+        for mut message in self.storage.due_outbound(now, self.config.batch_size)? {
+            if let Some(reason) = self.cancel_reason(&message, now)? {
+                self.finish(&mut message, OutboundStatus::Cancelled, Some(reason))?;
+                report.cancelled += 1;
+                continue;
+            }
+
+            if !self.active_hours.contains(now) {
+                self.reschedule(&mut message, self.active_hours.next_start(now))?;
+                report.rescheduled += 1;
+                continue;
+            }
+
+            // The send is counted under the quota lock, which is released during delivery
+            let Some(quota) = quotas.get(&message.account)? else {
+                let reason = format!("Unknown account: {}", message.account);
+                self.finish(&mut message, OutboundStatus::Failed, Some(reason))?;
+                report.failed += 1;
+                continue;
+            };
+            let recipient = Some(message.recipient.as_str());
+            let reservation = {
+                let mut quota = quota.lock().await;
+                let check = match &message.payload {
+                    OutboundPayload::Text { .. } => quota.can_send_message(recipient, now),
+                    OutboundPayload::Media { is_video, .. } => quota.can_send_media(recipient, *is_video, now),
+                };
+                if !check.is_allowed() {
+                    let retry_at = check.allowed_at
+                        .unwrap_or(now + Duration::seconds(self.config.recheck_seconds as i64));
+                    self.reschedule(&mut message, retry_at)?;
+                    report.rescheduled += 1;
+                    continue;
+                }
+                match &message.payload {
+                    OutboundPayload::Text { .. } => quota.record_message(recipient)?,
+                    OutboundPayload::Media { is_video, .. } => quota.record_media(recipient, *is_video)?,
+                }
+            };
+
+            match sender.send(&message).await {
+                Ok(()) => {
+                    self.finish(&mut message, OutboundStatus::Sent, None)?;
+                    report.sent += 1;
+                }
+                Err(e) => {
+                    let feedback = if e.is::<GatewayRateLimited>() {
+                        DeliveryFeedback::RateLimited
+                    } else {
+                        DeliveryFeedback::SendFailed
+                    };
+                    let mut quota = quota.lock().await;
+                    quota.cancel_send(&reservation)?;
+                    quota.record_feedback(feedback, now)?;
+                    drop(quota);
+                    message.attempts += 1;
+                    if message.attempts >= self.config.max_attempts {
+                        self.finish(&mut message, OutboundStatus::Failed, Some(e.to_string()))?;
+                        report.failed += 1;
+                    } else {
+                        message.last_error = Some(e.to_string());
+                        let retry_at = now + self.backoff(message.attempts);
+                        self.reschedule(&mut message, retry_at)?;
+                        report.retried += 1;
+                    }
+                }
+            }
+        }
+
+        Ok(report)
+    }
+
+    /// Why a due entry must not be sent anymore, if any
+    fn cancel_reason(&self, message: &OutboundMessage, now: DateTime<Utc>) -> Result<Option<String>> {
+        let Some(conversation) = self.storage.get_conversation(&message.conversation_id)? else {
+            return Ok(Some("Conversation not found".to_string()));
+        };
+        if conversation.state != message.state_at_enqueue {
+            return Ok(Some(format!(
+                "Conversation moved from {} to {}",
+                message.state_at_enqueue, conversation.state
+            )));
+        }
+        if conversation.is_under_takeover(now) {
+            return Ok(Some("Conversation under human takeover".to_string()));
+        }
+        Ok(None)
+    }
+
+    /// Exponential backoff: base, 2×base, 4×base... capped
+    fn backoff(&self, attempts: u32) -> Duration {
+        let factor = 2u64.saturating_pow(attempts.saturating_sub(1));
+        let seconds = self.config.base_backoff_seconds
+            .saturating_mul(factor)
+            .min(self.config.max_backoff_seconds);
+        Duration::seconds(seconds as i64)
+    }
+
+    fn reschedule(&self, message: &mut OutboundMessage, at: DateTime<Utc>) -> Result<()> {
+        message.scheduled_at = at;
+        message.updated_at = Utc::now();
+        self.storage.update_outbound(message)
+    }
+
+    fn finish(&self, message: &mut OutboundMessage, status: OutboundStatus, error: Option<String>) -> Result<()> {
+        message.status = status;
+        if error.is_some() {
+            message.last_error = error;
+        }
+        message.updated_at = Utc::now();
+        self.storage.update_outbound(message)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::engines::conversation::{ConversationEngine, ConversationEvent};
+    use crate::engines::quota::{QuotaCalendar, QuotaLimits, DEFAULT_ACCOUNT};
+    use chrono::TimeZone;
+    use std::collections::HashMap;
+    use std::sync::Mutex;
+
+    /// Records the messages it sends; fails the first `failures` sends
+    #[derive(Default)]
+    struct FakeSender {
+        sent: Mutex<Vec<String>>,
+        failures: Mutex<u32>,
+        rate_limited: bool,
+    }
+
+    impl OutboundSender for FakeSender {
+        fn send(&self, message: &OutboundMessage) -> impl Future<Output = Result<()>> + Send {
+            let result = {
+                let mut failures = self.failures.lock().unwrap();
+                if *failures > 0 {
+                    *failures -= 1;
+                    if self.rate_limited {
+                        Err(GatewayRateLimited("429 Too Many Requests".to_string()).into())
+                    } else {
+                        Err(anyhow!("Gateway unavailable"))
+                    }
+                } else {
+                    self.sent.lock().unwrap().push(message.id.clone());
+                    Ok(())
+                }
+            };
+            async move { result }
+        }
+    }
+
+    fn always_active() -> ActiveHours {
+        ActiveHours {
+            start: "00:00".to_string(),
+            end: "00:00".to_string(),
+            timezone: "UTC".to_string(),
+        }
+    }
+
+    fn create_storage(db_path: &std::path::Path) -> Arc<StorageEngine> {
+        let mut storage = StorageEngine::new(db_path.to_path_buf()).unwrap();
+        storage.initialize().unwrap();
+        Arc::new(storage)
+    }
+
+    fn setup(active_hours: ActiveHours) -> (OutboundQueue, Conversation, QuotaAccounts) {
+        let db_path = std::env::temp_dir().join(format!("test_outbound_{}.db", uuid::Uuid::new_v4()));
+        let storage = create_storage(&db_path);
+        let conversation = Conversation::new("+33600000001", ConversationEngine::new().initial_state());
+        storage.save_conversation(&conversation).unwrap();
+        let queue = OutboundQueue::new(storage, OutboundConfig::default(), active_hours);
+        let quota = QuotaAccounts::new(
+            QuotaLimits::default(),
+            HashMap::from([("shop-b".to_string(), QuotaLimits::default())]),
+            QuotaCalendar::default(),
+        );
+        (queue, conversation, quota)
+    }
+
+    fn text(text: &str) -> OutboundPayload {
+        OutboundPayload::Text { text: text.to_string() }
+    }
+
+    #[tokio::test]
+    async fn test_delayed_delivery_and_idempotency() {
+        let (queue, conversation, quota) = setup(always_active());
+        let now = Utc::now();
+        let (message, created) = queue.enqueue(&conversation, DEFAULT_ACCOUNT, "reply-1", text("Bonjour"), now + Duration::seconds(60)).unwrap();
+        assert!(created);
+        let (again, created) = queue.enqueue(&conversation, DEFAULT_ACCOUNT, "reply-1", text("Autre"), now).unwrap();
+        assert!(!created);
+        assert_eq!(again.id, message.id);
+        assert_eq!(again.payload, text("Bonjour"));
+
+        let sender = FakeSender::default();
+        assert_eq!(queue.drain(&sender, &quota, now).await.unwrap(), DrainReport::default());
+
+        let report = queue.drain(&sender, &quota, now + Duration::seconds(61)).await.unwrap();
+        assert_eq!(report.sent, 1);
+        assert_eq!(*sender.sent.lock().unwrap(), vec![message.id.clone()]);
+        assert_eq!(queue.get(&message.id).unwrap().unwrap().status, OutboundStatus::Sent);
+        assert_eq!(quota.default_account().lock().await.get_usage().messages_today, 1);
+    }
+
+    #[tokio::test]
+    async fn test_retry_with_backoff_then_fail() {
+        let (queue, conversation, quota) = setup(always_active());
+        let now = Utc::now();
+        let (message, _) = queue.enqueue(&conversation, DEFAULT_ACCOUNT, "reply-1", text("Bonjour"), now).unwrap();
+        let sender = FakeSender { failures: Mutex::new(u32::MAX), ..Default::default() };
+
+        let report = queue.drain(&sender, &quota, now).await.unwrap();
+        assert_eq!(report.retried, 1);
+        let retried = queue.get(&message.id).unwrap().unwrap();
+        assert_eq!(retried.attempts, 1);
+        assert_eq!(retried.scheduled_at.timestamp(), (now + Duration::seconds(30)).timestamp());
+        assert_eq!(retried.last_error.as_deref(), Some("Gateway unavailable"));
+        // The failed send was not counted
+        assert_eq!(quota.default_account().lock().await.get_usage().messages_today, 0);
+        assert_eq!(queue.backoff(3), Duration::seconds(120));
+        assert_eq!(queue.backoff(20), Duration::seconds(3600));
+
+        let mut at = now;
+        for _ in 1..5 {
+            at += Duration::hours(2);
+            queue.drain(&sender, &quota, at).await.unwrap();
+        }
+        let failed = queue.get(&message.id).unwrap().unwrap();
+        assert_eq!(failed.status, OutboundStatus::Failed);
+        assert_eq!(failed.attempts, 5);
+    }
+
+    #[tokio::test]
+    async fn test_rate_limited_send_throttles_quotas() {
+        let (queue, conversation, quota) = setup(always_active());
+        let now = Utc::now();
+        queue.enqueue(&conversation, DEFAULT_ACCOUNT, "reply-1", text("Bonjour"), now).unwrap();
+        let sender = FakeSender { failures: Mutex::new(1), rate_limited: true, ..Default::default() };
+
+        assert_eq!(queue.drain(&sender, &quota, now).await.unwrap().retried, 1);
+        let quota = quota.default_account();
+        let quota = quota.lock().await;
+        assert_eq!(quota.throttle_factor(now), 0.5);
+        assert_eq!(quota.effective_limits(now).messages_per_hour, 15);
+    }
+
+    #[tokio::test]
+    async fn test_state_change_cancels_pending_replies() {
+        let (queue, mut conversation, quota) = setup(always_active());
+        let now = Utc::now();
+        let (message, _) = queue.enqueue(&conversation, DEFAULT_ACCOUNT, "reply-1", text("Bonjour"), now).unwrap();
+
+        let engine = ConversationEngine::new();
+        let record = engine.apply(&mut conversation, ConversationEvent::NegativeResponse);
+        queue.storage.save_transition(&conversation, &record).unwrap();
+
+        let cancelled = queue.get(&message.id).unwrap().unwrap();
+        assert_eq!(cancelled.status, OutboundStatus::Cancelled);
+        assert_eq!(cancelled.last_error.as_deref(), Some("Conversation moved from Discovery to Negative"));
+        let sender = FakeSender::default();
+        assert_eq!(queue.drain(&sender, &quota, now).await.unwrap().sent, 0);
+        assert!(sender.sent.lock().unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_quota_reschedules_instead_of_sending() {
+        let (queue, conversation, quota) = setup(always_active());
+        let now = Utc::now();
+        let (first, _) = queue.enqueue(&conversation, DEFAULT_ACCOUNT, "reply-1", text("Un"), now).unwrap();
+        let (second, _) = queue.enqueue(&conversation, DEFAULT_ACCOUNT, "reply-2", text("Deux"), now).unwrap();
+
+        // Second send hits the minimum gap between messages
+        let report = queue.drain(&FakeSender::default(), &quota, now).await.unwrap();
+        assert_eq!((report.sent, report.rescheduled), (1, 1));
+        assert_eq!(queue.get(&first.id).unwrap().unwrap().status, OutboundStatus::Sent);
+        let delayed = queue.get(&second.id).unwrap().unwrap();
+        assert_eq!(delayed.status, OutboundStatus::Pending);
+        assert!(delayed.scheduled_at > now);
+    }
+
+    #[tokio::test]
+    async fn test_each_account_uses_its_own_quota() {
+        let (queue, conversation, quota) = setup(always_active());
+        let now = Utc::now();
+        queue.enqueue(&conversation, DEFAULT_ACCOUNT, "reply-1", text("Un"), now).unwrap();
+        let (other, _) = queue.enqueue(&conversation, "shop-b", "reply-2", text("Deux"), now).unwrap();
+        assert_eq!(queue.get(&other.id).unwrap().unwrap().account, "shop-b");
+        let (unknown, _) = queue.enqueue(&conversation, "shop-z", "reply-4", text("Quatre"), now).unwrap();
+
+        // No minimum gap between two different numbers; an unknown account is never created
+        let report = queue.drain(&FakeSender::default(), &quota, now).await.unwrap();
+        assert_eq!((report.sent, report.failed), (2, 1));
+        assert_eq!(queue.get(&unknown.id).unwrap().unwrap().status, OutboundStatus::Failed);
+        assert!(quota.get("shop-z").unwrap().is_none());
+        assert_eq!(quota.get("shop-b").unwrap().unwrap().lock().await.get_usage().messages_today, 1);
+        assert_eq!(quota.default_account().lock().await.get_usage().messages_today, 1);
+        assert!(queue.enqueue(&conversation, "shop b", "reply-3", text("Trois"), now).is_err());
+    }
+
+    #[tokio::test]
+    async fn test_outside_active_hours_waits_for_opening() {
+        let (queue, conversation, quota) = setup(ActiveHours {
+            start: "09:00".to_string(),
+            end: "18:00".to_string(),
+            timezone: "UTC".to_string(),
+        });
+        let night = Utc.with_ymd_and_hms(2026, 3, 2, 3, 0, 0).unwrap();
+        let (message, _) = queue.enqueue(&conversation, DEFAULT_ACCOUNT, "reply-1", text("Bonjour"), night).unwrap();
+
+        let report = queue.drain(&FakeSender::default(), &quota, night).await.unwrap();
+        assert_eq!(report.rescheduled, 1);
+        let delayed = queue.get(&message.id).unwrap().unwrap();
+        assert_eq!(delayed.scheduled_at, Utc.with_ymd_and_hms(2026, 3, 2, 9, 0, 0).unwrap());
+        assert_eq!(delayed.attempts, 0);
+    }
+
+    #[tokio::test]
+    async fn test_queue_survives_restart() {
+        let db_path = std::env::temp_dir().join(format!("test_outbound_{}.db", uuid::Uuid::new_v4()));
+        let conversation = Conversation::new("+33600000002", ConversationEngine::new().initial_state());
+        {
+            let storage = create_storage(&db_path);
+            storage.save_conversation(&conversation).unwrap();
+            let queue = OutboundQueue::new(storage, OutboundConfig::default(), always_active());
+            queue.enqueue(&conversation, DEFAULT_ACCOUNT, "reply-1", text("Bonjour"), Utc::now()).unwrap();
+        }
+
+        let queue = OutboundQueue::new(create_storage(&db_path), OutboundConfig::default(), always_active());
+        let pending = queue.list(Some(OutboundStatus::Pending)).unwrap();
+        assert_eq!(pending.len(), 1);
+        assert_eq!(pending[0].recipient, "+33600000002");
+    }
+}
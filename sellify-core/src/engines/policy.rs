@@ -0,0 +1,414 @@
+use serde::{Deserialize, Serialize};
+use serde_json::{Value, json};
+use anyhow::{Result, anyhow};
+use std::collections::HashSet;
+use std::path::Path;
+
+use crate::engines::classifier::EventClassifier;
+use crate::engines::conversation::ConversationState;
+use crate::engines::decision::{Action, DecisionContext};
+use crate::engines::state_machine::TransitionTable;
+
+/// Built-in policy, used when no policy is configured
+const BUILTIN_POLICY: &str = include_str!("decision_policy.toml");
+
+/// Declarative decision policy, as written in TOML or JSON
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PolicyDefinition {
+    /// Evaluated in order, the first matching rule wins
+    pub rules: Vec<PolicyRule>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PolicyRule {
+    pub name: String,
+    /// All conditions must hold; a rule without conditions always matches
+    #[serde(default)]
+    pub when: RuleConditions,
+    pub action: Action,
+}
+
+/// Conditions of a rule, unset ones are ignored
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct RuleConditions {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub human_takeover: Option<bool>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub active_hours: Option<bool>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub quotas_available: Option<bool>,
+    /// The conversation is in a terminal state (e.g. Escalated, Frozen)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub terminal_state: Option<bool>,
+    /// The customer asks to see a product (media policy keywords)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub media_requested: Option<bool>,
+    /// The media policy and the quotas allow sending a product media
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub media_allowed: Option<bool>,
+    /// Conversation state is one of these
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub states: Option<Vec<ConversationState>>,
+    /// The message mentions (or does not mention) a knowledge base product
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub kb_match: Option<bool>,
+    /// Local hour in [start, end), in the business timezone; wraps past midnight when end < start
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub hours: Option<[u32; 2]>,
+    /// The message contains one of these words or phrases
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub keywords: Option<Vec<String>>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub min_misunderstandings: Option<u32>,
+    /// The misunderstanding count reached the configured escalation threshold
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub misunderstanding_limit: Option<bool>,
+    /// Detected sentiment is one of these
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub sentiments: Option<Vec<String>>,
+}
+
+/// One condition of a rule, with the context value it was checked against
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ConditionTrace {
+    pub condition: String,
+    pub expected: Value,
+    pub actual: Value,
+    pub matched: bool,
+}
+
+/// Evaluation of one rule (explainability)
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RuleTrace {
+    pub rule: String,
+    pub matched: bool,
+    pub conditions: Vec<ConditionTrace>,
+}
+
+impl RuleConditions {
+    pub fn is_empty(&self) -> bool {
+        *self == Self::default()
+    }
+
+    /// True when every set condition holds for `context`
+    pub fn matches(&self, context: &DecisionContext) -> bool {
+        self.check(context).iter().all(|check| check.matched)
+    }
+
+    /// Checks each set condition against `context`
+    pub fn check(&self, context: &DecisionContext) -> Vec<ConditionTrace> {
+        let mut checks = vec![];
+        let mut push = |condition: &str, expected: Value, actual: Value, matched: bool| {
+            checks.push(ConditionTrace {
+                condition: condition.to_string(),
+                expected,
+                actual,
+                matched,
+            });
+        };
+
+        let flags = [
+            ("human_takeover", self.human_takeover, context.human_takeover),
+            ("active_hours", self.active_hours, context.is_active_hours),
+            ("quotas_available", self.quotas_available, context.quotas_available),
+            ("terminal_state", self.terminal_state, context.terminal_state),
+            ("media_requested", self.media_requested, context.media_requested),
+            ("misunderstanding_limit", self.misunderstanding_limit, context.misunderstanding_limit_reached),
+        ];
+        for (condition, expected, actual) in flags {
+            if let Some(expected) = expected {
+                push(condition, json!(expected), json!(actual), expected == actual);
+            }
+        }
+        if let Some(media_allowed) = self.media_allowed {
+            let matched = media_allowed == context.media_id.is_some();
+            push("media_allowed", json!(media_allowed), json!(context.media_id), matched);
+        }
+        if let Some(states) = &self.states {
+            let matched = states.iter().any(|state| state.to_string() == context.conversation_state);
+            push("states", json!(states), json!(context.conversation_state), matched);
+        }
+        if let Some(kb_match) = self.kb_match {
+            let matched = kb_match != context.kb_matches.is_empty();
+            push("kb_match", json!(kb_match), json!(context.kb_matches), matched);
+        }
+        if let Some([start, end]) = self.hours {
+            let hour = context.local_hour;
+            let matched = if start < end {
+                start <= hour && hour < end
+            } else {
+                hour >= start || hour < end
+            };
+            push("hours", json!([start, end]), json!(hour), matched);
+        }
+        if let Some(keywords) = &self.keywords {
+            let text = EventClassifier::normalize(&context.incoming_message);
+            let found = keywords.iter().find(|keyword| EventClassifier::contains_phrase(&text, keyword));
+            push("keywords", json!(keywords), json!(found), found.is_some());
+        }
+        if let Some(min) = self.min_misunderstandings {
+            push("min_misunderstandings", json!(min), json!(context.misunderstandings), context.misunderstandings >= min);
+        }
+        if let Some(sentiments) = &self.sentiments {
+            let matched = context.sentiment_detected.as_ref().is_some_and(|detected| {
+                sentiments.iter().any(|sentiment| sentiment.eq_ignore_ascii_case(detected))
+            });
+            push("sentiments", json!(sentiments), json!(context.sentiment_detected), matched);
+        }
+        checks
+    }
+}
+
+/// Validated, ordered rule set used by the Decision Engine
+#[derive(Debug, Clone)]
+pub struct DecisionPolicy {
+    definition: PolicyDefinition,
+}
+
+impl DecisionPolicy {
+    /// Reproduces the historical hard-coded decisions
+    pub fn builtin() -> Self {
+        Self::from_toml(BUILTIN_POLICY, &TransitionTable::builtin()).expect("Built-in decision policy is valid")
+    }
+
+    /// States used by the rules must exist in `table`
+    pub fn from_toml(data: &str, table: &TransitionTable) -> Result<Self> {
+        let definition: PolicyDefinition = toml::from_str(data)
+            .map_err(|e| anyhow!("Invalid decision policy: {}", e))?;
+        Self::from_definition(definition, table)
+    }
+
+    pub fn from_json(data: &str, table: &TransitionTable) -> Result<Self> {
+        let definition: PolicyDefinition = serde_json::from_str(data)
+            .map_err(|e| anyhow!("Invalid decision policy: {}", e))?;
+        Self::from_definition(definition, table)
+    }
+
+    /// Loads a `.toml` or `.json` file
+    pub fn load(path: &Path, table: &TransitionTable) -> Result<Self> {
+        let data = std::fs::read_to_string(path)
+            .map_err(|e| anyhow!("Cannot read {}: {}", path.display(), e))?;
+        match path.extension().and_then(|e| e.to_str()) {
+            Some("json") => Self::from_json(&data, table),
+            Some("toml") => Self::from_toml(&data, table),
+            _ => Err(anyhow!("Unsupported decision policy format: {}", path.display())),
+        }
+    }
+
+    /// Validates the rules; the last one must match any context
+    pub fn from_definition(definition: PolicyDefinition, table: &TransitionTable) -> Result<Self> {
+        let mut problems = vec![];
+
+        if definition.rules.is_empty() {
+            problems.push("no rule".to_string());
+        }
+
+        let mut names = HashSet::new();
+        let mut catch_all: Option<&str> = None;
+        for rule in &definition.rules {
+            if rule.name.trim().is_empty() {
+                problems.push("a rule has no name".to_string());
+            } else if !names.insert(rule.name.as_str()) {
+                problems.push(format!("rule {} is declared twice", rule.name));
+            }
+            if let Some(previous) = catch_all {
+                problems.push(format!("rule {} is unreachable after {}, which always matches", rule.name, previous));
+            }
+            if rule.when.is_empty() {
+                catch_all.get_or_insert(&rule.name);
+            }
+
+            let when = &rule.when;
+            for state in when.states.iter().flatten() {
+                if !table.has_state(state) {
+                    problems.push(format!("rule {} uses undeclared state {}", rule.name, state));
+                }
+            }
+            if let Some([start, end]) = when.hours {
+                if start > 23 || end > 23 || start == end {
+                    problems.push(format!("rule {} has invalid hours [{}, {}]", rule.name, start, end));
+                }
+            }
+            let lists = [
+                ("states", when.states.as_ref().map(Vec::len)),
+                ("keywords", when.keywords.as_ref().map(Vec::len)),
+                ("sentiments", when.sentiments.as_ref().map(Vec::len)),
+            ];
+            for (field, len) in lists {
+                if len == Some(0) {
+                    problems.push(format!("rule {} has an empty {} list", rule.name, field));
+                }
+            }
+            let sends_any_media = matches!(&rule.action, Action::RespondWithMedia { media_id, .. } if media_id.is_empty());
+            if sends_any_media && when.media_allowed != Some(true) {
+                problems.push(format!("rule {} sends media without media_allowed = true", rule.name));
+            }
+            let blank = |list: &Option<Vec<String>>| list.iter().flatten().any(|item| item.trim().is_empty());
+            if blank(&when.keywords) || blank(&when.sentiments) {
+                problems.push(format!("rule {} has a blank keyword or sentiment", rule.name));
+            }
+        }
+
+        if !definition.rules.is_empty() && catch_all.is_none() {
+            problems.push("the last rule must have no condition".to_string());
+        }
+
+        if !problems.is_empty() {
+            return Err(anyhow!("Invalid decision policy: {}", problems.join("; ")));
+        }
+
+        Ok(Self { definition })
+    }
+
+    pub fn definition(&self) -> &PolicyDefinition {
+        &self.definition
+    }
+
+    /// First rule matching `context` (validation guarantees there is one),
+    /// with the evaluation of every rule up to it
+    pub fn evaluate(&self, context: &DecisionContext) -> (Option<&PolicyRule>, Vec<RuleTrace>) {
+        let mut trace = vec![];
+        for rule in &self.definition.rules {
+            let conditions = rule.when.check(context);
+            let matched = conditions.iter().all(|check| check.matched);
+            trace.push(RuleTrace {
+                rule: rule.name.clone(),
+                matched,
+                conditions,
+            });
+            if matched {
+                return (Some(rule), trace);
+            }
+        }
+        (None, trace)
+    }
+}
+
+impl Default for DecisionPolicy {
+    fn default() -> Self {
+        Self::builtin()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const POLICY: &str = r#"
+        [[rules]]
+        name = "night"
+        when = { hours = [22, 7] }
+        action = "Ignore"
+
+        [[rules]]
+        name = "lost_customer"
+        when = { min_misunderstandings = 3, states = ["Interest", "Intent"] }
+        action = { AlertHuman = { reason = "Customer not understood" } }
+
+        [[rules]]
+        name = "show_product"
+        when = { kb_match = true, keywords = ["photo", "voir"], media_allowed = true }
+        action = { RespondWithMedia = { text = "", media_id = "" } }
+
+        [[rules]]
+        name = "respond"
+        action = { RespondText = { text = "" } }
+    "#;
+
+    fn context(message: &str, hour: u32) -> DecisionContext {
+        DecisionContext {
+            incoming_message: message.to_string(),
+            conversation_state: "Interest".to_string(),
+            quotas_available: true,
+            is_active_hours: true,
+            local_hour: hour,
+            ..Default::default()
+        }
+    }
+
+    fn fired(policy: &DecisionPolicy, context: &DecisionContext) -> String {
+        policy.evaluate(context).0.unwrap().name.clone()
+    }
+
+    #[test]
+    fn test_first_matching_rule_wins() {
+        let policy = DecisionPolicy::from_toml(POLICY, &TransitionTable::builtin()).unwrap();
+
+        assert_eq!(fired(&policy, &context("Bonsoir", 23)), "night");
+        assert_eq!(fired(&policy, &context("Bonjour", 6)), "night");
+        assert_eq!(fired(&policy, &context("Bonjour", 10)), "respond");
+
+        let mut lost = context("Quoi ?", 10);
+        lost.misunderstandings = 3;
+        assert_eq!(fired(&policy, &lost), "lost_customer");
+        lost.conversation_state = "Discovery".to_string();
+        assert_eq!(fired(&policy, &lost), "respond");
+
+        let mut show = context("Je peux voir une photo ?", 10);
+        assert_eq!(fired(&policy, &show), "respond");
+        show.kb_matches = vec!["prod-001".to_string()];
+        assert_eq!(fired(&policy, &show), "respond");
+        show.media_id = Some("img-001".to_string());
+        assert_eq!(fired(&policy, &show), "show_product");
+    }
+
+    #[test]
+    fn test_trace_explains_each_rule() {
+        let policy = DecisionPolicy::from_toml(POLICY, &TransitionTable::builtin()).unwrap();
+        let mut context = context("Une photo svp", 10);
+        context.kb_matches = vec!["prod-001".to_string()];
+        context.media_id = Some("img-001".to_string());
+
+        let (rule, trace) = policy.evaluate(&context);
+
+        assert_eq!(rule.unwrap().name, "show_product");
+        let evaluated: Vec<_> = trace.iter().map(|t| (t.rule.as_str(), t.matched)).collect();
+        assert_eq!(evaluated, vec![("night", false), ("lost_customer", false), ("show_product", true)]);
+        assert_eq!(trace[0].conditions[0].actual, json!(10));
+        let keywords = trace[2].conditions.iter().find(|c| c.condition == "keywords").unwrap();
+        assert_eq!(keywords.actual, json!("photo"));
+        // Every condition is reported: the state matches, the count does not
+        assert_eq!(trace[1].conditions.len(), 2);
+        assert!(trace[1].conditions[0].matched);
+        assert!(!trace[1].conditions[1].matched);
+    }
+
+    #[test]
+    fn test_builtin_policy_json_round_trip() {
+        let policy = DecisionPolicy::builtin();
+        let json = serde_json::to_string(policy.definition()).unwrap();
+        let reloaded = DecisionPolicy::from_json(&json, &TransitionTable::builtin()).unwrap();
+        assert_eq!(reloaded.definition(), policy.definition());
+    }
+
+    #[test]
+    fn test_rejects_invalid_rules() {
+        let data = POLICY
+            .replace("hours = [22, 7]", "hours = [22, 24]")
+            .replace(r#""Intent""#, r#""Shipped""#)
+            .replace("name = \"show_product\"", "name = \"night\"");
+        let error = DecisionPolicy::from_toml(&data, &TransitionTable::builtin()).unwrap_err().to_string();
+        assert!(error.contains("rule night has invalid hours [22, 24]"), "{}", error);
+        assert!(error.contains("rule lost_customer uses undeclared state Shipped"), "{}", error);
+        assert!(error.contains("rule night is declared twice"), "{}", error);
+
+        let no_media_check = POLICY.replace(", media_allowed = true", "");
+        let error = DecisionPolicy::from_toml(&no_media_check, &TransitionTable::builtin()).unwrap_err().to_string();
+        assert!(error.contains("rule show_product sends media without media_allowed = true"), "{}", error);
+
+        let unknown = POLICY.replace("hours = [22, 7]", "hour = 22");
+        assert!(DecisionPolicy::from_toml(&unknown, &TransitionTable::builtin()).is_err());
+    }
+
+    #[test]
+    fn test_requires_final_catch_all_rule() {
+        let data = POLICY.replace("name = \"respond\"", "name = \"respond\"\nwhen = { active_hours = true }");
+        let error = DecisionPolicy::from_toml(&data, &TransitionTable::builtin()).unwrap_err().to_string();
+        assert!(error.contains("the last rule must have no condition"), "{}", error);
+
+        let data = format!("{}\n[[rules]]\nname = \"late\"\naction = \"Ignore\"\n", POLICY);
+        let error = DecisionPolicy::from_toml(&data, &TransitionTable::builtin()).unwrap_err().to_string();
+        assert!(error.contains("rule late is unreachable after respond"), "{}", error);
+    }
+}
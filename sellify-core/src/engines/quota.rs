@@ -1,5 +1,18 @@
 use serde::{Deserialize, Serialize};
 use anyhow::Result;
+use chrono::{DateTime, Datelike, Duration, NaiveDate, NaiveTime, Utc};
+use chrono_tz::Tz;
+use std::collections::{HashMap, VecDeque};
+use std::sync::Arc;
+
+use crate::engines::config::{ActiveHours, AntiBanConfig};
+use crate::engines::storage::StorageEngine;
+
+/// Account used when none is given; also the storage key of single-account installs
+pub const DEFAULT_ACCOUNT: &str = "default";
+
+/// Contacts idle for this long are forgotten and count as new again
+const CONTACT_RETENTION_DAYS: i64 = 30;
 
 /// Quota tracking structure
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -8,6 +21,175 @@ pub struct QuotaLimits {
     pub messages_per_week: u32,
     pub images_per_day: u32,
     pub videos_per_week: u32,
+    /// Sends (text and media) in any sliding 60 minutes
+    pub messages_per_hour: u32,
+    /// Minimum delay between two sends
+    pub min_gap_seconds: u64,
+    /// Delay after an image or a video before the next send
+    pub media_cooldown_seconds: u64,
+    /// Sends to one contact in any sliding 60 minutes
+    pub messages_per_contact_per_hour: u32,
+    /// Consecutive outbound messages to a contact without a reply
+    pub max_unanswered: u32,
+    /// Contacts messaged for the first time per day
+    pub new_contacts_per_day: u32,
+    /// How delivery feedback tightens the limits
+    #[serde(default)]
+    pub throttle: ThrottleConfig,
+    /// Ramp of the daily and weekly limits for a newly connected number
+    #[serde(default)]
+    pub warmup: WarmupConfig,
+}
+
+/// Warm-up of a new number: daily and weekly limits start at `start_ratio`
+/// of their configured value and grow linearly to it over `days`
+/// By default no number is warming up: the ramp starts from `connected_at`,
+/// or from the date recorded through the warm-up route, which takes precedence
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct WarmupConfig {
+    /// 0 disables the warm-up
+    pub days: u32,
+    pub start_ratio: f64,
+    /// Connection date of the number, when known at deployment
+    #[serde(default)]
+    pub connected_at: Option<DateTime<Utc>>,
+}
+
+impl Default for WarmupConfig {
+    fn default() -> Self {
+        Self {
+            days: 14,
+            start_ratio: 0.1,
+            connected_at: None,
+        }
+    }
+}
+
+/// Where the account stands in its warm-up
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct WarmupStatus {
+    /// None when no warm-up was started (no ramp)
+    pub connected_at: Option<DateTime<Utc>>,
+    /// Days since the connection, starting at 0
+    pub day: u32,
+    pub days: u32,
+    /// Share of the configured daily and weekly limits allowed today
+    pub ratio: f64,
+    pub completed: bool,
+}
+
+/// Adaptive throttling: feedback lowers a factor applied to every limit,
+/// which then recovers linearly
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ThrottleConfig {
+    /// Factor multiplier per feedback event
+    pub rate_limited_penalty: f64,
+    pub blocked_penalty: f64,
+    pub send_failed_penalty: f64,
+    /// Share of recently messaged contacts (24h) left at `max_unanswered` above which limits tighten
+    pub unanswered_ratio_threshold: f64,
+    /// Contacts needed before the unanswered ratio is trusted
+    pub unanswered_min_contacts: usize,
+    pub unanswered_penalty: f64,
+    /// The factor never goes below this
+    pub min_factor: f64,
+    /// Factor regained per hour without incident
+    pub recovery_per_hour: f64,
+}
+
+impl Default for ThrottleConfig {
+    fn default() -> Self {
+        Self {
+            rate_limited_penalty: 0.5,
+            blocked_penalty: 0.7,
+            send_failed_penalty: 0.9,
+            unanswered_ratio_threshold: 0.6,
+            unanswered_min_contacts: 10,
+            unanswered_penalty: 0.8,
+            min_factor: 0.2,
+            recovery_per_hour: 0.05,
+        }
+    }
+}
+
+/// Gateway feedback that tightens the limits
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum DeliveryFeedback {
+    /// WhatsApp refused or throttled our sends
+    RateLimited,
+    /// A contact blocked or reported the number
+    Blocked,
+    SendFailed,
+}
+
+impl QuotaLimits {
+    /// Counts scaled down by `factor` (never below 1), delays scaled up
+    pub fn scaled(&self, factor: f64) -> Self {
+        let count = |value: u32| scale_count(value, factor);
+        let delay = |seconds: u64| (seconds as f64 / factor).ceil() as u64;
+        Self {
+            messages_per_day: count(self.messages_per_day),
+            messages_per_week: count(self.messages_per_week),
+            images_per_day: count(self.images_per_day),
+            videos_per_week: count(self.videos_per_week),
+            messages_per_hour: count(self.messages_per_hour),
+            min_gap_seconds: delay(self.min_gap_seconds),
+            media_cooldown_seconds: delay(self.media_cooldown_seconds),
+            messages_per_contact_per_hour: count(self.messages_per_contact_per_hour),
+            max_unanswered: self.max_unanswered,
+            new_contacts_per_day: count(self.new_contacts_per_day),
+            throttle: self.throttle.clone(),
+            warmup: self.warmup.clone(),
+        }
+    }
+
+    /// Only the daily and weekly counts scaled down by `ratio` (warm-up)
+    pub fn ramped(&self, ratio: f64) -> Self {
+        Self {
+            messages_per_day: scale_count(self.messages_per_day, ratio),
+            messages_per_week: scale_count(self.messages_per_week, ratio),
+            images_per_day: scale_count(self.images_per_day, ratio),
+            videos_per_week: scale_count(self.videos_per_week, ratio),
+            new_contacts_per_day: scale_count(self.new_contacts_per_day, ratio),
+            ..self.clone()
+        }
+    }
+
+    /// Limits from the anti-ban configuration
+    pub fn from_anti_ban(config: &AntiBanConfig) -> Self {
+        Self {
+            messages_per_day: config.max_messages_per_day,
+            messages_per_week: config.max_messages_per_week,
+            images_per_day: config.max_images_per_day,
+            videos_per_week: config.max_videos_per_week,
+            messages_per_hour: config.max_messages_per_hour,
+            min_gap_seconds: config.min_seconds_between_messages,
+            media_cooldown_seconds: config.media_cooldown_seconds,
+            messages_per_contact_per_hour: config.max_messages_per_contact_per_hour,
+            max_unanswered: config.max_unanswered,
+            new_contacts_per_day: config.max_new_contacts_per_day,
+            throttle: config.throttle.clone(),
+            warmup: config.warmup.clone(),
+        }
+    }
+}
+
+/// `from` plus `seconds`; a delay too long to add waits forever instead of panicking
+fn after_seconds(from: DateTime<Utc>, seconds: u64) -> DateTime<Utc> {
+    i64::try_from(seconds).ok()
+        .and_then(Duration::try_seconds)
+        .and_then(|delay| from.checked_add_signed(delay))
+        .unwrap_or(DateTime::<Utc>::MAX_UTC)
+}
+
+/// A limit scaled by `factor`, never below 1 unless it is 0
+fn scale_count(value: u32, factor: f64) -> u32 {
+    if value == 0 {
+        0
+    } else {
+        ((value as f64 * factor).floor() as u32).max(1)
+    }
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -17,17 +199,196 @@ pub struct QuotaUsage {
     pub images_today: u32,
     pub videos_this_week: u32,
     pub last_reset: chrono::DateTime<chrono::Utc>,
+    /// Send times of the last hour, oldest first
+    #[serde(default)]
+    pub recent_sends: VecDeque<DateTime<Utc>>,
+    #[serde(default)]
+    pub last_send_at: Option<DateTime<Utc>>,
+    #[serde(default)]
+    pub last_media_at: Option<DateTime<Utc>>,
+    #[serde(default)]
+    pub new_contacts_today: u32,
+    /// Share of the configured limits allowed after the last feedback (1.0 = not throttled)
+    #[serde(default = "full_speed")]
+    pub throttle_factor: f64,
+    #[serde(default)]
+    pub throttle_updated_at: Option<DateTime<Utc>>,
+    /// When the number was connected, start of the warm-up
+    #[serde(default)]
+    pub connected_at: Option<DateTime<Utc>>,
+    /// Phone number -> per-contact usage
+    #[serde(default)]
+    pub contacts: HashMap<String, ContactUsage>,
+}
+
+fn full_speed() -> f64 {
+    1.0
+}
+
+/// Usage towards a single contact
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ContactUsage {
+    /// Send times of the last hour, oldest first
+    pub recent_sends: VecDeque<DateTime<Utc>>,
+    /// Outbound messages since the contact last replied
+    pub unanswered: u32,
+    pub last_activity: DateTime<Utc>,
+}
+
+impl ContactUsage {
+    fn new(now: DateTime<Utc>) -> Self {
+        Self {
+            recent_sends: VecDeque::new(),
+            unanswered: 0,
+            last_activity: now,
+        }
+    }
+}
+
+/// A recorded send, kept by a caller that delivers it later so it can be undone on failure
+/// (the quota lock need not be held during delivery)
+#[derive(Debug, Clone, PartialEq)]
+pub struct SendReservation {
+    recipient: Option<String>,
+    /// None for a text message, Some(is_video) for a media
+    media: Option<bool>,
+    at: DateTime<Utc>,
+    new_contact: bool,
+}
+
+/// Limit that prevents a send right now
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum QuotaBlock {
+    DailyMessages,
+    WeeklyMessages,
+    HourlyMessages,
+    MinimumGap,
+    MediaCooldown,
+    DailyImages,
+    WeeklyVideos,
+    ContactHourlyMessages,
+    /// Only a reply from the contact lifts this one
+    AwaitingReply,
+    NewContactsPerDay,
+}
+
+/// Result of a quota check: when the send is allowed, and what delays it
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SendCheck {
+    /// Earliest allowed send time (the check time when allowed now)
+    /// None when the send waits for a reply from the contact
+    pub allowed_at: Option<DateTime<Utc>>,
+    /// Limit that sets `allowed_at`; None when the send is allowed now
+    pub blocked_by: Option<QuotaBlock>,
+}
+
+impl SendCheck {
+    fn allowed(now: DateTime<Utc>) -> Self {
+        Self {
+            allowed_at: Some(now),
+            blocked_by: None,
+        }
+    }
+
+    /// Pushes `allowed_at` back to `until` if it is later
+    fn wait_until(&mut self, until: DateTime<Utc>, block: QuotaBlock) {
+        if self.allowed_at.is_some_and(|allowed_at| until > allowed_at) {
+            self.allowed_at = Some(until);
+            self.blocked_by = Some(block);
+        }
+    }
+
+    /// No time bound: blocked until the contact replies
+    fn wait_for_reply(&mut self) {
+        self.allowed_at = None;
+        self.blocked_by = Some(QuotaBlock::AwaitingReply);
+    }
+
+    pub fn is_allowed(&self) -> bool {
+        self.blocked_by.is_none()
+    }
+}
+
+/// Quota periods in the business timezone: days start at local midnight,
+/// weeks on Monday (ISO weeks)
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct QuotaCalendar {
+    timezone: Tz,
+}
+
+impl QuotaCalendar {
+    pub fn new(timezone: Tz) -> Self {
+        Self { timezone }
+    }
+
+    /// Uses `ActiveHours.timezone`; an unknown timezone falls back to UTC
+    pub fn from_active_hours(active_hours: &ActiveHours) -> Self {
+        let timezone = active_hours.tz().unwrap_or_else(|e| {
+            log::error!("{}, quota periods use UTC", e);
+            Tz::UTC
+        });
+        Self::new(timezone)
+    }
+
+    pub fn timezone(&self) -> Tz {
+        self.timezone
+    }
+
+    /// Local date of `now`
+    pub fn date(&self, now: DateTime<Utc>) -> NaiveDate {
+        now.with_timezone(&self.timezone).date_naive()
+    }
+
+    /// Start of the local day containing `now`
+    pub fn day_start(&self, now: DateTime<Utc>) -> DateTime<Utc> {
+        self.midnight(self.date(now))
+    }
+
+    /// Start of the local ISO week (Monday) containing `now`
+    pub fn week_start(&self, now: DateTime<Utc>) -> DateTime<Utc> {
+        let date = self.date(now);
+        self.midnight(date - Duration::days(date.weekday().num_days_from_monday() as i64))
+    }
+
+    /// Start of the next local day
+    pub fn next_day(&self, now: DateTime<Utc>) -> DateTime<Utc> {
+        self.midnight(self.date(now) + Duration::days(1))
+    }
+
+    /// Start of the next local week (Monday)
+    pub fn next_week(&self, now: DateTime<Utc>) -> DateTime<Utc> {
+        let date = self.date(now);
+        self.midnight(date + Duration::days(7 - date.weekday().num_days_from_monday() as i64))
+    }
+
+    /// Local midnight of `date`; where DST skips midnight, the first instant of the day
+    fn midnight(&self, date: NaiveDate) -> DateTime<Utc> {
+        ActiveHours::resolve_local(self.timezone, date.and_time(NaiveTime::MIN))
+    }
+}
+
+impl Default for QuotaCalendar {
+    fn default() -> Self {
+        Self::new(Tz::UTC)
+    }
 }
 
 /// Quota & Anti-Ban Engine - Prevents automated-looking behavior
 pub struct QuotaEngine {
+    /// WhatsApp account (instance) the counters belong to
+    account: String,
     limits: QuotaLimits,
     usage: QuotaUsage,
+    calendar: QuotaCalendar,
+    /// When set, usage is saved on every change so it survives restarts
+    storage: Option<Arc<StorageEngine>>,
 }
 
 impl QuotaEngine {
     pub fn new(limits: QuotaLimits) -> Self {
         Self {
+            account: DEFAULT_ACCOUNT.to_string(),
             limits,
             usage: QuotaUsage {
                 messages_today: 0,
@@ -35,30 +396,355 @@ impl QuotaEngine {
                 images_today: 0,
                 videos_this_week: 0,
                 last_reset: chrono::Utc::now(),
+                recent_sends: VecDeque::new(),
+                last_send_at: None,
+                last_media_at: None,
+                new_contacts_today: 0,
+                throttle_factor: full_speed(),
+                throttle_updated_at: None,
+                // Warm-up is started explicitly (configuration or warm-up route)
+                connected_at: None,
+                contacts: HashMap::new(),
             },
+            calendar: QuotaCalendar::default(),
+            storage: None,
+        }
+    }
+
+    /// Same engine with quota periods in another timezone
+    pub fn with_calendar(mut self, calendar: QuotaCalendar) -> Self {
+        self.calendar = calendar;
+        self
+    }
+
+    /// Same engine, reported under another account ID
+    pub fn with_account(mut self, account: &str) -> Self {
+        self.account = account.to_string();
+        self
+    }
+
+    /// Copy of the engine that is never saved, for dry runs
+    pub fn detached(&self) -> QuotaEngine {
+        QuotaEngine {
+            account: self.account.clone(),
+            limits: self.limits.clone(),
+            usage: self.usage.clone(),
+            calendar: self.calendar,
+            storage: None,
+        }
+    }
+
+    pub fn calendar(&self) -> &QuotaCalendar {
+        &self.calendar
+    }
+
+    pub fn account(&self) -> &str {
+        &self.account
+    }
+
+    /// Configured limits, before warm-up and throttling
+    pub fn limits(&self) -> &QuotaLimits {
+        &self.limits
+    }
+
+    /// Creates the engine of `account` backed by storage, restoring its saved usage
+    /// Daily/weekly resets missed while the server was down are applied immediately
+    pub fn with_storage(
+        account: &str,
+        limits: QuotaLimits,
+        calendar: QuotaCalendar,
+        storage: Arc<StorageEngine>,
+    ) -> Result<Self> {
+        let mut engine = Self::new(limits).with_calendar(calendar).with_account(account);
+        if let Some(usage) = storage.load_quota_usage(account)? {
+            engine.usage = usage;
+        }
+        engine.storage = Some(storage);
+
+        let (daily, weekly) = engine.apply_pending_resets();
+        if daily || weekly {
+            log::info!("Quota usage restored with pending resets (daily: {}, weekly: {})", daily, weekly);
+        }
+        engine.persist()?;
+        Ok(engine)
+    }
+
+    /// Applies the resets due since `last_reset`; returns (daily, weekly)
+    /// Both checks are made before resetting, as a reset moves `last_reset`
+    pub fn apply_pending_resets(&mut self) -> (bool, bool) {
+        let daily = self.needs_daily_reset();
+        let weekly = self.needs_weekly_reset();
+        if daily {
+            self.reset_daily();
+        }
+        if weekly {
+            self.reset_weekly();
+        }
+        (daily, weekly)
+    }
+
+    /// Saves the current usage (no-op without storage)
+    fn persist(&self) -> Result<()> {
+        match &self.storage {
+            Some(storage) => storage.save_quota_usage(&self.account, &self.usage),
+            None => Ok(()),
         }
     }
 
-    /// Check if sending a message would exceed quotas
-    pub fn can_send_message(&self) -> bool {
-        self.usage.messages_today < self.limits.messages_per_day
-            && self.usage.messages_this_week < self.limits.messages_per_week
+    /// Saves after a reset; a failure is only logged, since the reset
+    /// will be applied again on restore (last_reset is not moved in storage)
+    fn persist_reset(&self) {
+        if let Err(e) = self.persist() {
+            log::error!("Failed to persist quota reset: {}", e);
+        }
+    }
+
+    /// Check when a text message may be sent, to `recipient` if given
+    pub fn can_send_message(&self, recipient: Option<&str>, now: DateTime<Utc>) -> SendCheck {
+        let limits = self.effective_limits(now);
+        let mut check = self.spacing_check(&limits, recipient, now);
+        if self.usage.messages_today >= limits.messages_per_day {
+            check.wait_until(self.calendar.next_day(now), QuotaBlock::DailyMessages);
+        }
+        if self.usage.messages_this_week >= limits.messages_per_week {
+            check.wait_until(self.calendar.next_week(now), QuotaBlock::WeeklyMessages);
+        }
+        check
     }
 
-    /// Check if sending media would exceed quotas
-    pub fn can_send_media(&self, is_video: bool) -> bool {
+    /// Check when an image or a video may be sent, to `recipient` if given
+    pub fn can_send_media(&self, recipient: Option<&str>, is_video: bool, now: DateTime<Utc>) -> SendCheck {
+        let limits = self.effective_limits(now);
+        let mut check = self.spacing_check(&limits, recipient, now);
         if is_video {
-            self.usage.videos_this_week < self.limits.videos_per_week
+            if self.usage.videos_this_week >= limits.videos_per_week {
+                check.wait_until(self.calendar.next_week(now), QuotaBlock::WeeklyVideos);
+            }
+        } else if self.usage.images_today >= limits.images_per_day {
+            check.wait_until(self.calendar.next_day(now), QuotaBlock::DailyImages);
+        }
+        check
+    }
+
+    /// Limits shared by every send: hourly window, minimum gap, media cooldown, per-contact limits
+    fn spacing_check(&self, limits: &QuotaLimits, recipient: Option<&str>, now: DateTime<Utc>) -> SendCheck {
+        let mut check = SendCheck::allowed(now);
+
+        if let Some(until) = Self::window_frees_at(&self.usage.recent_sends, limits.messages_per_hour, now) {
+            check.wait_until(until, QuotaBlock::HourlyMessages);
+        }
+        if let Some(last) = self.usage.last_send_at {
+            check.wait_until(after_seconds(last, limits.min_gap_seconds), QuotaBlock::MinimumGap);
+        }
+        if let Some(last) = self.usage.last_media_at {
+            check.wait_until(after_seconds(last, limits.media_cooldown_seconds), QuotaBlock::MediaCooldown);
+        }
+
+        if let Some(recipient) = recipient {
+            match self.usage.contacts.get(recipient) {
+                Some(contact) => {
+                    let per_hour = limits.messages_per_contact_per_hour;
+                    if let Some(until) = Self::window_frees_at(&contact.recent_sends, per_hour, now) {
+                        check.wait_until(until, QuotaBlock::ContactHourlyMessages);
+                    }
+                    if contact.unanswered >= limits.max_unanswered {
+                        check.wait_for_reply();
+                    }
+                }
+                None if self.usage.new_contacts_today >= limits.new_contacts_per_day => {
+                    check.wait_until(self.calendar.next_day(now), QuotaBlock::NewContactsPerDay);
+                }
+                None => {}
+            }
+        }
+        check
+    }
+
+    /// When a sliding one-hour window of `sends` drops below `limit`, None if it already is
+    fn window_frees_at(sends: &VecDeque<DateTime<Utc>>, limit: u32, now: DateTime<Utc>) -> Option<DateTime<Utc>> {
+        let window: Vec<&DateTime<Utc>> = sends.iter()
+            .filter(|sent| **sent > now - Duration::hours(1))
+            .collect();
+        let limit = limit as usize;
+        if window.len() < limit {
+            return None;
+        }
+        // The window frees up when enough of the oldest sends leave it
+        Some(window.get(window.len() - limit)
+            .map(|sent| **sent + Duration::hours(1))
+            .unwrap_or(now + Duration::hours(1)))
+    }
+
+    /// Configured limits after the warm-up ramp and the current throttle factor
+    pub fn effective_limits(&self, now: DateTime<Utc>) -> QuotaLimits {
+        self.limits
+            .ramped(self.warmup_status(now).ratio)
+            .scaled(self.throttle_factor(now))
+    }
+
+    /// Warm-up stage at `now`
+    pub fn warmup_status(&self, now: DateTime<Utc>) -> WarmupStatus {
+        let config = &self.limits.warmup;
+        let connected_at = self.usage.connected_at.or(config.connected_at);
+        let day = connected_at
+            .map(|at| (now - at).num_days().max(0) as u32)
+            .unwrap_or(config.days);
+        let completed = day >= config.days;
+        let ratio = if completed {
+            1.0
         } else {
-            self.usage.images_today < self.limits.images_per_day
+            let start = config.start_ratio.clamp(0.0, 1.0);
+            start + (1.0 - start) * day as f64 / config.days as f64
+        };
+        WarmupStatus {
+            connected_at,
+            day,
+            days: config.days,
+            ratio,
+            completed,
         }
     }
 
-    /// Record message sent
-    pub fn record_message(&mut self) -> Result<()> {
+    /// Restarts the warm-up, e.g. when a new number is connected
+    pub fn set_connected_at(&mut self, connected_at: Option<DateTime<Utc>>) -> Result<()> {
+        self.usage.connected_at = connected_at;
+        self.persist()
+    }
+
+    /// Current throttle factor: feedback penalties after recovery, tightened
+    /// further while too many contacts leave us unanswered
+    pub fn throttle_factor(&self, now: DateTime<Utc>) -> f64 {
+        let config = &self.limits.throttle;
+        let mut factor = self.recovered_factor(now);
+        if self.unanswered_ratio(now).is_some_and(|ratio| ratio > config.unanswered_ratio_threshold) {
+            factor *= config.unanswered_penalty;
+        }
+        factor.clamp(config.min_factor, 1.0)
+    }
+
+    /// Stored factor plus the linear recovery since it was lowered
+    fn recovered_factor(&self, now: DateTime<Utc>) -> f64 {
+        let hours = self.usage.throttle_updated_at
+            .map(|at| (now - at).num_seconds().max(0) as f64 / 3600.0)
+            .unwrap_or(0.0);
+        (self.usage.throttle_factor + hours * self.limits.throttle.recovery_per_hour).min(1.0)
+    }
+
+    /// Share of the contacts active in the last 24h that got `max_unanswered` messages without replying
+    /// A conversation normally ends on our reply, so a single unanswered message is not counted
+    /// None below `unanswered_min_contacts` contacts
+    pub fn unanswered_ratio(&self, now: DateTime<Utc>) -> Option<f64> {
+        let recent: Vec<&ContactUsage> = self.usage.contacts.values()
+            .filter(|contact| now - contact.last_activity < Duration::hours(24))
+            .collect();
+        if recent.is_empty() || recent.len() < self.limits.throttle.unanswered_min_contacts {
+            return None;
+        }
+        let ignored_after = self.limits.max_unanswered.max(1);
+        let unanswered = recent.iter().filter(|contact| contact.unanswered >= ignored_after).count();
+        Some(unanswered as f64 / recent.len() as f64)
+    }
+
+    /// Tightens the limits after negative gateway feedback
+    pub fn record_feedback(&mut self, feedback: DeliveryFeedback, now: DateTime<Utc>) -> Result<()> {
+        let config = &self.limits.throttle;
+        let penalty = match feedback {
+            DeliveryFeedback::RateLimited => config.rate_limited_penalty,
+            DeliveryFeedback::Blocked => config.blocked_penalty,
+            DeliveryFeedback::SendFailed => config.send_failed_penalty,
+        };
+        let factor = (self.recovered_factor(now) * penalty).max(config.min_factor);
+        log::warn!("Quota throttled after {:?}: factor {:.2}", feedback, factor);
+        self.usage.throttle_factor = factor;
+        self.usage.throttle_updated_at = Some(now);
+        self.persist()
+    }
+
+    /// Adds a send to the hourly windows and the gap tracking
+    fn track_send(&mut self, recipient: Option<&str>, now: DateTime<Utc>) {
+        Self::push_send(&mut self.usage.recent_sends, self.limits.messages_per_hour, now);
+        self.usage.last_send_at = Some(now);
+
+        if let Some(recipient) = recipient {
+            if !self.usage.contacts.contains_key(recipient) {
+                self.usage.new_contacts_today += 1;
+            }
+            let contact = self.usage.contacts.entry(recipient.to_string()).or_insert_with(|| ContactUsage::new(now));
+            Self::push_send(&mut contact.recent_sends, self.limits.messages_per_contact_per_hour, now);
+            contact.unanswered += 1;
+            contact.last_activity = now;
+        }
+    }
+
+    /// Appends a send; only the last `limit` sends of the last hour are kept, as only they can block
+    fn push_send(sends: &mut VecDeque<DateTime<Utc>>, limit: u32, now: DateTime<Utc>) {
+        sends.push_back(now);
+        while sends.front().is_some_and(|sent| *sent <= now - Duration::hours(1))
+            || sends.len() > limit as usize
+        {
+            sends.pop_front();
+        }
+    }
+
+    /// Record message sent, to `recipient` if known
+    pub fn record_message(&mut self, recipient: Option<&str>) -> Result<SendReservation> {
+        let reservation = self.reservation(recipient, None);
         self.usage.messages_today += 1;
         self.usage.messages_this_week += 1;
-        Ok(())
+        self.track_send(recipient, reservation.at);
+        self.persist()?;
+        Ok(reservation)
+    }
+
+    fn reservation(&self, recipient: Option<&str>, media: Option<bool>) -> SendReservation {
+        SendReservation {
+            recipient: recipient.map(str::to_string),
+            media,
+            at: Utc::now(),
+            new_contact: recipient.is_some_and(|r| !self.usage.contacts.contains_key(r)),
+        }
+    }
+
+    /// Undoes a recorded send that could not be delivered
+    /// The minimum gap and media cooldown still run from the attempt
+    pub fn cancel_send(&mut self, reservation: &SendReservation) -> Result<()> {
+        let usage = &mut self.usage;
+        match reservation.media {
+            None => {
+                usage.messages_today = usage.messages_today.saturating_sub(1);
+                usage.messages_this_week = usage.messages_this_week.saturating_sub(1);
+            }
+            Some(true) => usage.videos_this_week = usage.videos_this_week.saturating_sub(1),
+            Some(false) => usage.images_today = usage.images_today.saturating_sub(1),
+        }
+        let remove = |sends: &mut VecDeque<DateTime<Utc>>| {
+            if let Some(index) = sends.iter().position(|sent| *sent == reservation.at) {
+                sends.remove(index);
+            }
+        };
+        remove(&mut usage.recent_sends);
+
+        if let Some(recipient) = &reservation.recipient {
+            if let Some(contact) = usage.contacts.get_mut(recipient) {
+                remove(&mut contact.recent_sends);
+                contact.unanswered = contact.unanswered.saturating_sub(1);
+                // Never reached: the contact is still new
+                if reservation.new_contact && contact.recent_sends.is_empty() && contact.unanswered == 0 {
+                    usage.contacts.remove(recipient);
+                    usage.new_contacts_today = usage.new_contacts_today.saturating_sub(1);
+                }
+            }
+        }
+        self.persist()
+    }
+
+    /// Record an inbound message: the contact is no longer waiting on us
+    pub fn record_reply(&mut self, contact: &str) -> Result<()> {
+        let now = Utc::now();
+        let usage = self.usage.contacts.entry(contact.to_string()).or_insert_with(|| ContactUsage::new(now));
+        usage.unanswered = 0;
+        usage.last_activity = now;
+        self.persist()
     }
 
     /// Calculate delay to avoid detection (random jitter + minimum delay)
@@ -70,7 +756,8 @@ impl QuotaEngine {
         let base_delay = rng.gen_range(2..8);
         
         // Progressive delay based on usage
-        let usage_ratio = self.usage.messages_today as f64 / self.limits.messages_per_day as f64;
+        let limits = self.effective_limits(Utc::now());
+        let usage_ratio = self.usage.messages_today as f64 / limits.messages_per_day as f64;
         let progressive_factor = if usage_ratio > 0.8 {
             3 // Near limit: triple delay
         } else if usage_ratio > 0.5 {
@@ -82,14 +769,18 @@ impl QuotaEngine {
         base_delay * progressive_factor
     }
     
-    /// Record media sent
-    pub fn record_media(&mut self, is_video: bool) -> Result<()> {
+    /// Record media sent, to `recipient` if known
+    pub fn record_media(&mut self, recipient: Option<&str>, is_video: bool) -> Result<SendReservation> {
+        let reservation = self.reservation(recipient, Some(is_video));
         if is_video {
             self.usage.videos_this_week += 1;
         } else {
             self.usage.images_today += 1;
         }
-        Ok(())
+        self.track_send(recipient, reservation.at);
+        self.usage.last_media_at = Some(reservation.at);
+        self.persist()?;
+        Ok(reservation)
     }
     
     /// Get current usage
@@ -97,11 +788,15 @@ impl QuotaEngine {
         &self.usage
     }
     
-    /// Reset daily quotas (messages_today, images_today)
+    /// Reset daily quotas (messages_today, images_today, new contacts) and forget idle contacts
     pub fn reset_daily(&mut self) {
+        let now = chrono::Utc::now();
         self.usage.messages_today = 0;
         self.usage.images_today = 0;
-        self.usage.last_reset = chrono::Utc::now();
+        self.usage.new_contacts_today = 0;
+        self.usage.contacts.retain(|_, contact| now - contact.last_activity < Duration::days(CONTACT_RETENTION_DAYS));
+        self.usage.last_reset = now;
+        self.persist_reset();
     }
     
     /// Reset weekly quotas (messages_this_week, videos_this_week)
@@ -109,44 +804,53 @@ impl QuotaEngine {
         self.usage.messages_this_week = 0;
         self.usage.videos_this_week = 0;
         self.usage.last_reset = chrono::Utc::now();
+        self.persist_reset();
     }
     
     /// Check if daily reset is needed (based on last_reset)
     pub fn needs_daily_reset(&self) -> bool {
-        let now = chrono::Utc::now();
-        let last_reset_date = self.usage.last_reset.date_naive();
-        let today = now.date_naive();
-        
-        today > last_reset_date
+        self.needs_daily_reset_at(chrono::Utc::now())
+    }
+    
+    /// True when the last reset happened before the local day of `now` started
+    pub fn needs_daily_reset_at(&self, now: DateTime<Utc>) -> bool {
+        self.usage.last_reset < self.calendar.day_start(now)
     }
     
     /// Check if weekly reset is needed (Monday 00:00)
     pub fn needs_weekly_reset(&self) -> bool {
-        use chrono::Datelike;
-        
-        let now = chrono::Utc::now();
-        let last_reset = self.usage.last_reset;
-        
-        // Get week number for both dates
-        let current_week = now.iso_week().week();
-        let last_reset_week = last_reset.iso_week().week();
-        
-        // Also check year to handle year transitions
-        let current_year = now.year();
-        let last_reset_year = last_reset.year();
-        
-        (current_year > last_reset_year) || (current_week > last_reset_week)
+        self.needs_weekly_reset_at(chrono::Utc::now())
+    }
+    
+    /// True when the last reset happened before the local ISO week of `now` started
+    /// (compares instants, so weeks straddling two years roll over correctly)
+    pub fn needs_weekly_reset_at(&self, now: DateTime<Utc>) -> bool {
+        self.usage.last_reset < self.calendar.week_start(now)
     }
 }
 
-impl Default for QuotaEngine {
+impl Default for QuotaLimits {
     fn default() -> Self {
-        Self::new(QuotaLimits {
+        Self {
             messages_per_day: 200,
             messages_per_week: 1000,
             images_per_day: 50,
             videos_per_week: 20,
-        })
+            messages_per_hour: 30,
+            min_gap_seconds: 5,
+            media_cooldown_seconds: 60,
+            messages_per_contact_per_hour: 10,
+            max_unanswered: 3,
+            new_contacts_per_day: 20,
+            throttle: ThrottleConfig::default(),
+            warmup: WarmupConfig::default(),
+        }
+    }
+}
+
+impl Default for QuotaEngine {
+    fn default() -> Self {
+        Self::new(QuotaLimits::default())
     }
 }
 
@@ -157,7 +861,7 @@ mod tests {
     #[test]
     fn test_can_send_message_when_under_quota() {
         let engine = QuotaEngine::default();
-        assert!(engine.can_send_message());
+        assert!(engine.can_send_message(None, Utc::now()).is_allowed());
     }
 
     #[test]
@@ -167,31 +871,36 @@ mod tests {
             messages_per_week: 1000,
             images_per_day: 50,
             videos_per_week: 20,
+            ..QuotaLimits::default()
         });
         
-        engine.record_message().unwrap();
-        engine.record_message().unwrap();
+        engine.record_message(None).unwrap();
+        engine.record_message(None).unwrap();
         
-        assert!(!engine.can_send_message());
+        let now = Utc::now();
+        let check = engine.can_send_message(None, now);
+        assert!(!check.is_allowed());
+        assert_eq!(check.blocked_by, Some(QuotaBlock::DailyMessages));
+        assert_eq!(check.allowed_at, Some(engine.calendar.next_day(now)));
     }
 
     #[test]
     fn test_can_send_media_image() {
         let engine = QuotaEngine::default();
-        assert!(engine.can_send_media(false));
+        assert!(engine.can_send_media(None, false, Utc::now()).is_allowed());
     }
 
     #[test]
     fn test_can_send_media_video() {
         let engine = QuotaEngine::default();
-        assert!(engine.can_send_media(true));
+        assert!(engine.can_send_media(None, true, Utc::now()).is_allowed());
     }
 
     #[test]
     fn test_record_message_increments() {
         let mut engine = QuotaEngine::default();
         let before = engine.usage.messages_today;
-        engine.record_message().unwrap();
+        engine.record_message(None).unwrap();
         assert_eq!(engine.usage.messages_today, before + 1);
     }
 
@@ -200,7 +909,7 @@ mod tests {
         let engine = QuotaEngine::default();
         for _ in 0..10 {
             let delay = engine.calculate_delay();
-            assert!(delay >= 2 && delay <= 24); // Max 8*3 for progressive
+            assert!((2..=24).contains(&delay)); // Max 8*3 for progressive
         }
     }
     
@@ -209,8 +918,8 @@ mod tests {
         let mut engine = QuotaEngine::default();
         
         // Record some usage
-        engine.record_message().unwrap();
-        engine.record_media(false).unwrap(); // image
+        engine.record_message(None).unwrap();
+        engine.record_media(None, false).unwrap(); // image
         
         assert_eq!(engine.usage.messages_today, 1);
         assert_eq!(engine.usage.images_today, 1);
@@ -229,8 +938,8 @@ mod tests {
         let mut engine = QuotaEngine::default();
         
         // Record some usage
-        engine.record_message().unwrap();
-        engine.record_media(true).unwrap(); // video
+        engine.record_message(None).unwrap();
+        engine.record_media(None, true).unwrap(); // video
         
         assert_eq!(engine.usage.messages_this_week, 1);
         assert_eq!(engine.usage.videos_this_week, 1);
@@ -266,6 +975,549 @@ mod tests {
         assert!(!engine.needs_weekly_reset()); // Just created, same week
     }
     
+    #[test]
+    fn test_minimum_gap_between_sends() {
+        let mut engine = QuotaEngine::default();
+        engine.record_message(None).unwrap();
+        let sent_at = engine.usage.last_send_at.unwrap();
+
+        let check = engine.can_send_message(None, sent_at + Duration::seconds(1));
+        assert_eq!(check.blocked_by, Some(QuotaBlock::MinimumGap));
+        assert_eq!(check.allowed_at, Some(sent_at + Duration::seconds(5)));
+        assert!(engine.can_send_message(None, sent_at + Duration::seconds(5)).is_allowed());
+    }
+
+    #[test]
+    fn test_out_of_range_delays_do_not_panic() {
+        let mut engine = QuotaEngine::new(QuotaLimits {
+            min_gap_seconds: u64::MAX,
+            media_cooldown_seconds: i64::MAX as u64,
+            ..QuotaLimits::default()
+        });
+        engine.record_media(None, false).unwrap();
+        let check = engine.can_send_message(None, Utc::now());
+        assert!(!check.is_allowed());
+        assert_eq!(check.allowed_at, Some(DateTime::<Utc>::MAX_UTC));
+    }
+
+    #[test]
+    fn test_media_cooldown_applies_to_next_send() {
+        let mut engine = QuotaEngine::default();
+        engine.record_media(None, false).unwrap();
+        let sent_at = engine.usage.last_media_at.unwrap();
+
+        let check = engine.can_send_message(None, sent_at + Duration::seconds(10));
+        assert_eq!(check.blocked_by, Some(QuotaBlock::MediaCooldown));
+        assert_eq!(check.allowed_at, Some(sent_at + Duration::seconds(60)));
+        assert!(engine.can_send_media(None, true, sent_at + Duration::seconds(60)).is_allowed());
+    }
+
+    #[test]
+    fn test_sliding_hourly_window() {
+        let mut engine = QuotaEngine::new(QuotaLimits {
+            messages_per_hour: 3,
+            min_gap_seconds: 0,
+            ..QuotaLimits::default()
+        });
+        let now = Utc::now();
+        engine.usage.recent_sends = [50, 40, 20, 10].iter()
+            .map(|minutes| now - Duration::minutes(*minutes))
+            .collect();
+
+        // 4 sends in the last hour: 2 must leave the window (at 50 and 40 minutes ago)
+        let check = engine.can_send_message(None, now);
+        assert_eq!(check.blocked_by, Some(QuotaBlock::HourlyMessages));
+        assert_eq!(check.allowed_at, Some(now + Duration::minutes(20)));
+
+        // Only the sends that can still block are kept
+        engine.record_message(None).unwrap();
+        assert_eq!(engine.usage.recent_sends.len(), 3);
+    }
+
+    #[test]
+    fn test_latest_constraint_wins() {
+        let mut engine = QuotaEngine::new(QuotaLimits {
+            videos_per_week: 1,
+            ..QuotaLimits::default()
+        });
+        engine.record_media(None, true).unwrap();
+        let now = engine.usage.last_media_at.unwrap();
+
+        let check = engine.can_send_media(None, true, now);
+        assert_eq!(check.blocked_by, Some(QuotaBlock::WeeklyVideos));
+        assert_eq!(check.allowed_at, Some(engine.calendar.next_week(now)));
+        assert_eq!(check.allowed_at.unwrap().weekday(), chrono::Weekday::Mon);
+    }
+
+    #[test]
+    fn test_unanswered_messages_wait_for_reply() {
+        let mut engine = QuotaEngine::new(QuotaLimits {
+            min_gap_seconds: 0,
+            ..QuotaLimits::default()
+        });
+        let contact = Some("+33600000001");
+        for _ in 0..3 {
+            engine.record_message(contact).unwrap();
+        }
+
+        let check = engine.can_send_message(contact, Utc::now());
+        assert_eq!(check.blocked_by, Some(QuotaBlock::AwaitingReply));
+        assert_eq!(check.allowed_at, None);
+        // Other contacts are not affected
+        assert!(engine.can_send_message(Some("+33600000002"), Utc::now()).is_allowed());
+
+        engine.record_reply("+33600000001").unwrap();
+        assert!(engine.can_send_message(contact, Utc::now()).is_allowed());
+    }
+
+    #[test]
+    fn test_contact_hourly_window() {
+        let mut engine = QuotaEngine::new(QuotaLimits {
+            min_gap_seconds: 0,
+            messages_per_contact_per_hour: 2,
+            ..QuotaLimits::default()
+        });
+        engine.record_message(Some("+33600000001")).unwrap();
+        engine.record_message(Some("+33600000001")).unwrap();
+        let first = engine.usage.contacts["+33600000001"].recent_sends[0];
+
+        let check = engine.can_send_message(Some("+33600000001"), Utc::now());
+        assert_eq!(check.blocked_by, Some(QuotaBlock::ContactHourlyMessages));
+        assert_eq!(check.allowed_at, Some(first + Duration::hours(1)));
+    }
+
+    #[test]
+    fn test_new_contacts_per_day() {
+        let mut engine = QuotaEngine::new(QuotaLimits {
+            min_gap_seconds: 0,
+            new_contacts_per_day: 2,
+            ..QuotaLimits::default()
+        });
+        engine.record_message(Some("+33600000001")).unwrap();
+        engine.record_message(Some("+33600000002")).unwrap();
+        // A contact who wrote first is not a first contact
+        engine.record_reply("+33600000003").unwrap();
+        engine.record_message(Some("+33600000003")).unwrap();
+
+        let now = Utc::now();
+        let check = engine.can_send_message(Some("+33600000004"), now);
+        assert_eq!(check.blocked_by, Some(QuotaBlock::NewContactsPerDay));
+        assert_eq!(check.allowed_at, Some(engine.calendar.next_day(now)));
+        assert!(engine.can_send_message(Some("+33600000001"), now).is_allowed());
+
+        engine.reset_daily();
+        assert!(engine.can_send_message(Some("+33600000004"), now).is_allowed());
+    }
+
+    #[test]
+    fn test_configured_anti_ban_limits_take_effect() {
+        let config = crate::engines::config::ConfigEngine::from_toml(r#"
+            [anti_ban]
+            max_messages_per_day = 200
+            max_messages_per_hour = 30
+            max_messages_per_week = 500
+            max_images_per_day = 5
+            max_videos_per_week = 2
+            max_messages_per_contact_per_hour = 4
+            max_unanswered = 2
+            max_new_contacts_per_day = 1
+            min_seconds_between_messages = 0
+            typing_simulation = true
+        "#).unwrap();
+        let limits = QuotaLimits::from_anti_ban(&config.get_config().anti_ban);
+        assert_eq!(
+            (limits.messages_per_week, limits.images_per_day, limits.videos_per_week),
+            (500, 5, 2),
+        );
+        assert_eq!((limits.messages_per_contact_per_hour, limits.max_unanswered), (4, 2));
+
+        let mut engine = QuotaEngine::new(limits);
+        engine.record_message(Some("+33600000001")).unwrap();
+        let check = engine.can_send_message(Some("+33600000002"), Utc::now());
+        assert_eq!(check.blocked_by, Some(QuotaBlock::NewContactsPerDay));
+    }
+
+    #[test]
+    fn test_cancelled_send_is_not_counted() {
+        let mut engine = QuotaEngine::new(QuotaLimits {
+            min_gap_seconds: 0,
+            new_contacts_per_day: 1,
+            ..QuotaLimits::default()
+        });
+        let reservation = engine.record_message(Some("+33600000001")).unwrap();
+        assert_eq!(engine.get_usage().new_contacts_today, 1);
+
+        engine.cancel_send(&reservation).unwrap();
+        let usage = engine.get_usage();
+        assert_eq!((usage.messages_today, usage.messages_this_week), (0, 0));
+        assert!(usage.recent_sends.is_empty());
+        // The contact was never reached: it is still a first contact, and the daily slot is free again
+        assert_eq!(usage.new_contacts_today, 0);
+        assert!(!usage.contacts.contains_key("+33600000001"));
+        assert!(engine.can_send_message(Some("+33600000002"), Utc::now()).is_allowed());
+
+        let video = engine.record_media(None, true).unwrap();
+        engine.cancel_send(&video).unwrap();
+        assert_eq!(engine.get_usage().videos_this_week, 0);
+    }
+
+    #[test]
+    fn test_feedback_tightens_then_recovers() {
+        let mut engine = QuotaEngine::default();
+        let now = Utc::now();
+        engine.record_feedback(DeliveryFeedback::RateLimited, now).unwrap();
+
+        assert_eq!(engine.throttle_factor(now), 0.5);
+        let limits = engine.effective_limits(now);
+        assert_eq!(limits.messages_per_day, 100);
+        assert_eq!(limits.messages_per_hour, 15);
+        assert_eq!(limits.min_gap_seconds, 10);
+        assert_eq!(limits.media_cooldown_seconds, 120);
+
+        // 0.05 per hour: back to 0.75 after 5 hours, fully recovered after 10
+        assert!((engine.throttle_factor(now + Duration::hours(5)) - 0.75).abs() < 1e-9);
+        assert_eq!(engine.throttle_factor(now + Duration::hours(10)), 1.0);
+        assert_eq!(engine.effective_limits(now + Duration::hours(12)).messages_per_day, 200);
+    }
+
+    #[test]
+    fn test_feedback_penalties_stack_down_to_the_floor() {
+        let mut engine = QuotaEngine::default();
+        let now = Utc::now();
+        for _ in 0..10 {
+            engine.record_feedback(DeliveryFeedback::RateLimited, now).unwrap();
+        }
+        assert_eq!(engine.throttle_factor(now), 0.2);
+        assert_eq!(engine.effective_limits(now).messages_per_day, 40);
+
+        let mut engine = QuotaEngine::default();
+        engine.record_feedback(DeliveryFeedback::Blocked, now).unwrap();
+        engine.record_feedback(DeliveryFeedback::SendFailed, now).unwrap();
+        assert!((engine.throttle_factor(now) - 0.63).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_unanswered_ratio_tightens_limits() {
+        let mut engine = QuotaEngine::new(QuotaLimits {
+            min_gap_seconds: 0,
+            ..QuotaLimits::default()
+        });
+        let contact = |i: usize| format!("+3360000000{}", i);
+        // Normal traffic: each contact writes in and gets a reply, which ends the conversation
+        for i in 0..10 {
+            engine.record_reply(&contact(i)).unwrap();
+            engine.record_message(Some(&contact(i))).unwrap();
+        }
+        let now = Utc::now();
+        assert_eq!(engine.unanswered_ratio(now), Some(0.0));
+        assert_eq!(engine.throttle_factor(now), 1.0);
+
+        // Follow-ups up to max_unanswered (3) that most contacts ignore
+        for i in 0..7 {
+            engine.record_message(Some(&contact(i))).unwrap();
+            engine.record_message(Some(&contact(i))).unwrap();
+        }
+        assert_eq!(engine.unanswered_ratio(now), Some(0.7));
+        assert_eq!(engine.throttle_factor(now), 0.8);
+
+        for i in 0..2 {
+            engine.record_reply(&contact(i)).unwrap();
+        }
+        assert_eq!(engine.unanswered_ratio(now), Some(0.5));
+        assert_eq!(engine.throttle_factor(now), 1.0);
+    }
+
+    #[test]
+    fn test_warmup_ramps_daily_and_weekly_limits() {
+        let mut engine = QuotaEngine::default();
+        let connected_at = Utc::now();
+        engine.set_connected_at(Some(connected_at)).unwrap();
+
+        let first_day = engine.warmup_status(connected_at);
+        assert_eq!(first_day.day, 0);
+        assert!(!first_day.completed);
+        let limits = engine.effective_limits(connected_at);
+        assert_eq!(limits.messages_per_day, 20);
+        assert_eq!(limits.messages_per_week, 100);
+        assert_eq!(limits.new_contacts_per_day, 2);
+        // Spacing is not part of the ramp
+        assert_eq!(limits.messages_per_hour, 30);
+        assert_eq!(limits.min_gap_seconds, 5);
+
+        // Halfway: 0.1 + 0.9 * 7/14
+        let halfway = connected_at + Duration::days(7);
+        assert_eq!(engine.warmup_status(halfway).ratio, 0.55);
+        assert_eq!(engine.effective_limits(halfway).messages_per_day, 110);
+
+        let done = engine.warmup_status(connected_at + Duration::days(14));
+        assert!(done.completed);
+        assert_eq!(engine.effective_limits(connected_at + Duration::days(14)).messages_per_day, 200);
+    }
+
+    #[test]
+    fn test_warmup_blocks_day_one_burst() {
+        // A number declared as connected today in the configuration
+        let mut engine = QuotaEngine::new(QuotaLimits {
+            min_gap_seconds: 0,
+            warmup: WarmupConfig { connected_at: Some(Utc::now()), ..WarmupConfig::default() },
+            ..QuotaLimits::default()
+        });
+        for _ in 0..20 {
+            engine.record_message(None).unwrap();
+        }
+        let check = engine.can_send_message(None, Utc::now());
+        assert_eq!(check.blocked_by, Some(QuotaBlock::DailyMessages));
+    }
+
+    #[test]
+    fn test_warmup_disabled_or_unknown_connection() {
+        let engine = QuotaEngine::new(QuotaLimits {
+            warmup: WarmupConfig { days: 0, ..WarmupConfig::default() },
+            ..QuotaLimits::default()
+        });
+        assert!(engine.warmup_status(Utc::now()).completed);
+        assert_eq!(engine.effective_limits(Utc::now()).messages_per_day, 200);
+
+        // No ramp until a warm-up is started
+        let status = QuotaEngine::default().warmup_status(Utc::now());
+        assert_eq!(status.connected_at, None);
+        assert_eq!(status.ratio, 1.0);
+    }
+
+    fn utc(value: &str) -> DateTime<Utc> {
+        value.parse().unwrap()
+    }
+
+    fn reset_at(last_reset: &str, calendar: QuotaCalendar) -> QuotaEngine {
+        let mut engine = QuotaEngine::default().with_calendar(calendar);
+        engine.usage.last_reset = utc(last_reset);
+        engine
+    }
+
+    #[test]
+    fn test_weekly_reset_across_year_boundaries() {
+        let calendar = QuotaCalendar::default();
+        // Thu 2020-12-31 and Fri 2021-01-01 are both in ISO week 53 of 2020
+        let engine = reset_at("2020-12-31T10:00:00Z", calendar);
+        assert!(!engine.needs_weekly_reset_at(utc("2021-01-01T10:00:00Z")));
+        assert!(engine.needs_weekly_reset_at(utc("2021-01-04T00:00:00Z")));
+
+        // Mon 2024-12-30 starts ISO week 1 of 2025
+        let engine = reset_at("2024-12-29T10:00:00Z", calendar);
+        assert!(engine.needs_weekly_reset_at(utc("2024-12-30T00:00:00Z")));
+        assert!(!reset_at("2024-12-30T10:00:00Z", calendar).needs_weekly_reset_at(utc("2025-01-05T23:59:59Z")));
+    }
+
+    #[test]
+    fn test_periods_follow_business_timezone() {
+        let calendar = QuotaCalendar::new(chrono_tz::Europe::Paris);
+        // 23:30 UTC on Sunday is already Monday 00:30 in Paris (UTC+1 in January)
+        let engine = reset_at("2026-01-18T22:00:00Z", calendar);
+        assert!(engine.needs_daily_reset_at(utc("2026-01-18T23:30:00Z")));
+        assert!(engine.needs_weekly_reset_at(utc("2026-01-18T23:30:00Z")));
+        assert!(!reset_at("2026-01-18T22:00:00Z", QuotaCalendar::default()).needs_daily_reset_at(utc("2026-01-18T23:30:00Z")));
+
+        assert_eq!(calendar.next_day(utc("2026-01-18T12:00:00Z")), utc("2026-01-18T23:00:00Z"));
+        assert_eq!(calendar.next_week(utc("2026-01-14T12:00:00Z")), utc("2026-01-18T23:00:00Z"));
+    }
+
+    #[test]
+    fn test_day_length_changes_with_dst() {
+        let calendar = QuotaCalendar::new(chrono_tz::Europe::Paris);
+        // Spring forward on 2026-03-29: a 23-hour day; fall back on 2026-10-25: 25 hours
+        let spring = calendar.day_start(utc("2026-03-29T12:00:00Z"));
+        assert_eq!(calendar.next_day(spring) - spring, Duration::hours(23));
+        let autumn = calendar.day_start(utc("2026-10-25T12:00:00Z"));
+        assert_eq!(calendar.next_day(autumn) - autumn, Duration::hours(25));
+        assert_eq!(autumn, utc("2026-10-24T22:00:00Z"));
+
+        // Havana skips midnight: the day starts at 01:00 local
+        let havana = QuotaCalendar::new(chrono_tz::America::Havana);
+        assert_eq!(havana.day_start(utc("2026-03-08T12:00:00Z")), utc("2026-03-08T05:00:00Z"));
+    }
+
+    mod calendar_properties {
+        use super::*;
+        use proptest::prelude::*;
+
+        const TIMEZONES: &[Tz] = &[
+            Tz::UTC,
+            chrono_tz::Europe::Paris,
+            chrono_tz::America::New_York,
+            chrono_tz::America::Havana,
+            chrono_tz::America::Santiago,
+            chrono_tz::Australia::Lord_Howe,
+            chrono_tz::Asia::Kolkata,
+            chrono_tz::Pacific::Chatham,
+            chrono_tz::Pacific::Kiritimati,
+        ];
+
+        fn instant() -> impl Strategy<Value = DateTime<Utc>> {
+            // 1990-01-01 .. 2100-01-01
+            (631_152_000i64..4_102_444_800).prop_map(|seconds| DateTime::from_timestamp(seconds, 0).unwrap())
+        }
+
+        /// Instants within a few days of a year change, where ISO weeks straddle two years
+        fn new_year() -> impl Strategy<Value = DateTime<Utc>> {
+            (1990i32..2100, -4 * 86_400i64..4 * 86_400).prop_map(|(year, offset)| {
+                utc(&format!("{}-01-01T00:00:00Z", year)) + Duration::seconds(offset)
+            })
+        }
+
+        fn calendar() -> impl Strategy<Value = QuotaCalendar> {
+            prop::sample::select(TIMEZONES).prop_map(QuotaCalendar::new)
+        }
+
+        proptest! {
+            #[test]
+            fn day_bounds_contain_now(calendar in calendar(), now in instant()) {
+                let start = calendar.day_start(now);
+                let next = calendar.next_day(now);
+                prop_assert!(start <= now && now < next);
+                prop_assert_eq!(calendar.date(start), calendar.date(now));
+                prop_assert_eq!(calendar.date(next), calendar.date(now) + Duration::days(1));
+                prop_assert_eq!(calendar.day_start(next), next);
+                prop_assert!(next - start >= Duration::hours(22) && next - start <= Duration::hours(26));
+            }
+
+            #[test]
+            fn week_bounds_contain_now(calendar in calendar(), now in prop_oneof![instant(), new_year()]) {
+                let start = calendar.week_start(now);
+                let next = calendar.next_week(now);
+                prop_assert!(start <= now && now < next);
+                prop_assert_eq!(calendar.date(start).weekday(), chrono::Weekday::Mon);
+                prop_assert_eq!(calendar.date(start).iso_week(), calendar.date(now).iso_week());
+                prop_assert_eq!(calendar.week_start(next), next);
+            }
+
+            #[test]
+            fn resets_match_local_calendar(
+                calendar in calendar(),
+                last in prop_oneof![instant(), new_year()],
+                elapsed in 0i64..30 * 86_400,
+            ) {
+                let now = last + Duration::seconds(elapsed);
+                let mut engine = QuotaEngine::default().with_calendar(calendar);
+                engine.usage.last_reset = last;
+
+                prop_assert_eq!(engine.needs_daily_reset_at(now), calendar.date(last) != calendar.date(now));
+                prop_assert_eq!(
+                    engine.needs_weekly_reset_at(now),
+                    calendar.date(last).iso_week() != calendar.date(now).iso_week()
+                );
+            }
+        }
+    }
+
+    fn create_storage(path: &std::path::Path) -> Arc<StorageEngine> {
+        let mut storage = StorageEngine::new(path.to_path_buf()).unwrap();
+        storage.initialize().unwrap();
+        Arc::new(storage)
+    }
+
+    #[test]
+    fn test_usage_restored_from_storage() {
+        let db_path = std::env::temp_dir().join(format!("test_quota_{}.db", uuid::Uuid::new_v4()));
+
+        let mut engine = QuotaEngine::with_storage(DEFAULT_ACCOUNT, QuotaLimits::default(), QuotaCalendar::default(), create_storage(&db_path)).unwrap();
+        assert_eq!(engine.usage.connected_at, None);
+        engine.set_connected_at(Some(Utc::now())).unwrap();
+        engine.record_message(None).unwrap();
+        engine.record_message(None).unwrap();
+        engine.record_media(None, false).unwrap();
+        let connected_at = engine.usage.connected_at;
+        drop(engine);
+
+        let restored = QuotaEngine::with_storage(DEFAULT_ACCOUNT, QuotaLimits::default(), QuotaCalendar::default(), create_storage(&db_path)).unwrap();
+        assert_eq!(restored.usage.messages_today, 2);
+        assert_eq!(restored.usage.messages_this_week, 2);
+        assert_eq!(restored.usage.images_today, 1);
+        // The warm-up goes on from the original connection date
+        assert!(connected_at.is_some());
+        assert_eq!(restored.usage.connected_at, connected_at);
+    }
+
+    #[test]
+    fn test_restore_applies_missed_resets() {
+        let db_path = std::env::temp_dir().join(format!("test_quota_{}.db", uuid::Uuid::new_v4()));
+        let storage = create_storage(&db_path);
+        storage.save_quota_usage(DEFAULT_ACCOUNT, &QuotaUsage {
+            messages_today: 5,
+            messages_this_week: 40,
+            images_today: 2,
+            videos_this_week: 3,
+            last_reset: chrono::Utc::now() - chrono::Duration::days(8),
+            recent_sends: VecDeque::new(),
+            last_send_at: None,
+            last_media_at: None,
+            new_contacts_today: 0,
+            throttle_factor: 1.0,
+            throttle_updated_at: None,
+            connected_at: None,
+            contacts: HashMap::new(),
+        }).unwrap();
+
+        let restored = QuotaEngine::with_storage(DEFAULT_ACCOUNT, QuotaLimits::default(), QuotaCalendar::default(), Arc::clone(&storage)).unwrap();
+
+        // Daily AND weekly resets, although the daily one moved last_reset
+        assert_eq!(restored.usage.messages_today, 0);
+        assert_eq!(restored.usage.messages_this_week, 0);
+        assert_eq!(restored.usage.videos_this_week, 0);
+        assert_eq!(storage.load_quota_usage(DEFAULT_ACCOUNT).unwrap().unwrap().messages_this_week, 0);
+    }
+
+    /// Crash safety: a child process records messages in a loop and is killed
+    /// with SIGKILL (kill -9); every acknowledged record must still be there
+    #[test]
+    fn test_usage_survives_kill() {
+        use std::io::{BufRead, BufReader};
+        use std::process::{Command, Stdio};
+
+        const CHILD_ENV: &str = "SELLIFY_QUOTA_CRASH_DB";
+        if let Ok(path) = std::env::var(CHILD_ENV) {
+            let mut engine = QuotaEngine::with_storage(DEFAULT_ACCOUNT, QuotaLimits {
+                messages_per_day: u32::MAX,
+                messages_per_week: u32::MAX,
+                images_per_day: u32::MAX,
+                videos_per_week: u32::MAX,
+                ..QuotaLimits::default()
+            }, QuotaCalendar::default(), create_storage(std::path::Path::new(&path))).unwrap();
+            loop {
+                engine.record_message(None).unwrap();
+                println!("recorded {}", engine.usage.messages_today);
+            }
+        }
+
+        let db_path = std::env::temp_dir().join(format!("test_quota_crash_{}.db", uuid::Uuid::new_v4()));
+        let mut child = Command::new(std::env::current_exe().unwrap())
+            .args(["--exact", "engines::quota::tests::test_usage_survives_kill", "--nocapture", "--test-threads=1"])
+            .env(CHILD_ENV, &db_path)
+            .stdout(Stdio::piped())
+            .stderr(Stdio::null())
+            .spawn()
+            .unwrap();
+
+        let mut acknowledged = 0;
+        for line in BufReader::new(child.stdout.take().unwrap()).lines() {
+            if let Some(count) = line.unwrap().strip_prefix("recorded ") {
+                acknowledged = count.parse().unwrap();
+                if acknowledged >= 50 {
+                    break;
+                }
+            }
+        }
+        // Child::kill sends SIGKILL on Unix
+        child.kill().unwrap();
+        child.wait().unwrap();
+
+        let storage = create_storage(&db_path);
+        assert!(storage.check_integrity().unwrap());
+        let restored = QuotaEngine::with_storage(DEFAULT_ACCOUNT, QuotaLimits::default(), QuotaCalendar::default(), storage).unwrap();
+        assert!(acknowledged >= 50);
+        assert!(restored.usage.messages_today >= acknowledged);
+        assert_eq!(restored.usage.messages_today, restored.usage.messages_this_week);
+    }
+
     #[test]
     fn test_needs_weekly_reset_true_when_different_week() {
         let mut engine = QuotaEngine::default();
@@ -0,0 +1,227 @@
+use anyhow::{Result, anyhow};
+use std::collections::{BTreeMap, HashMap};
+use std::sync::{Arc, Mutex};
+
+use crate::engines::config::GlobalConfig;
+use crate::engines::quota::{QuotaCalendar, QuotaEngine, QuotaLimits, DEFAULT_ACCOUNT};
+use crate::engines::storage::StorageEngine;
+
+/// Quota engine of one account, shared between the API and the workers
+pub type SharedQuota = Arc<tokio::sync::Mutex<QuotaEngine>>;
+
+/// Longest accepted account ID
+const MAX_ACCOUNT_LEN: usize = 64;
+
+/// Quota Accounts - One isolated quota engine per WhatsApp account (instance)
+pub struct QuotaAccounts {
+    default_limits: QuotaLimits,
+    /// Account ID -> limits, for accounts configured explicitly
+    limits: HashMap<String, QuotaLimits>,
+    calendar: QuotaCalendar,
+    storage: Option<Arc<StorageEngine>>,
+    engines: Mutex<BTreeMap<String, SharedQuota>>,
+}
+
+impl QuotaAccounts {
+    /// In-memory accounts (nothing survives a restart)
+    pub fn new(default_limits: QuotaLimits, limits: HashMap<String, QuotaLimits>, calendar: QuotaCalendar) -> Self {
+        Self {
+            default_limits,
+            limits,
+            calendar,
+            storage: None,
+            engines: Mutex::new(BTreeMap::new()),
+        }
+    }
+
+    /// Accounts backed by storage; every saved or configured account is restored upfront
+    pub fn with_storage(
+        default_limits: QuotaLimits,
+        limits: HashMap<String, QuotaLimits>,
+        calendar: QuotaCalendar,
+        storage: Arc<StorageEngine>,
+    ) -> Result<Self> {
+        let mut accounts = Self::new(default_limits, limits, calendar);
+        let mut known = storage.list_quota_keys()?;
+        known.extend(accounts.limits.keys().cloned());
+        accounts.storage = Some(storage);
+        for account in known {
+            accounts.load(&account)?;
+        }
+        Ok(accounts)
+    }
+
+    /// Limits from `anti_ban` and `accounts`, periods in `active_hours.timezone`
+    pub fn from_config(config: &GlobalConfig, storage: Arc<StorageEngine>) -> Result<Self> {
+        let limits = config.accounts.iter()
+            .map(|(account, anti_ban)| {
+                Self::validate(account)?;
+                Ok((account.clone(), QuotaLimits::from_anti_ban(anti_ban)))
+            })
+            .collect::<Result<HashMap<_, _>>>()?;
+        Self::with_storage(
+            QuotaLimits::from_anti_ban(&config.anti_ban),
+            limits,
+            QuotaCalendar::from_active_hours(&config.active_hours),
+            storage,
+        )
+    }
+
+    /// Account IDs are instance names: letters, digits, '-', '_' and '.'
+    pub fn validate(account: &str) -> Result<()> {
+        let valid = !account.is_empty()
+            && account.len() <= MAX_ACCOUNT_LEN
+            && account.chars().all(|c| c.is_ascii_alphanumeric() || matches!(c, '-' | '_' | '.'));
+        if valid {
+            Ok(())
+        } else {
+            Err(anyhow!("Invalid account ID: {:?}", account))
+        }
+    }
+
+    /// Limits of `account` (its own when configured, the default ones otherwise)
+    pub fn limits_for(&self, account: &str) -> &QuotaLimits {
+        self.limits.get(account).unwrap_or(&self.default_limits)
+    }
+
+    /// Whether `account` is the default account or listed in the configuration
+    pub fn is_configured(&self, account: &str) -> bool {
+        account == DEFAULT_ACCOUNT || self.limits.contains_key(account)
+    }
+
+    /// Engine of a known account (loaded, restored from storage or configured);
+    /// None for any other account, which is never created here
+    pub fn get(&self, account: &str) -> Result<Option<SharedQuota>> {
+        Self::validate(account)?;
+        if let Some(engine) = self.find(account) {
+            return Ok(Some(engine));
+        }
+        if !self.is_configured(account) {
+            return Ok(None);
+        }
+        self.load(account).map(Some)
+    }
+
+    /// Engine of `account`, created (or restored from storage) if not loaded yet
+    fn load(&self, account: &str) -> Result<SharedQuota> {
+        Self::validate(account)?;
+        let mut engines = self.engines.lock().map_err(|_| anyhow!("Quota accounts lock poisoned"))?;
+        if let Some(engine) = engines.get(account) {
+            return Ok(Arc::clone(engine));
+        }
+
+        let limits = self.limits_for(account).clone();
+        let engine = match &self.storage {
+            Some(storage) => QuotaEngine::with_storage(account, limits, self.calendar, Arc::clone(storage))?,
+            None => QuotaEngine::new(limits).with_calendar(self.calendar).with_account(account),
+        };
+        let engine = Arc::new(tokio::sync::Mutex::new(engine));
+        engines.insert(account.to_string(), Arc::clone(&engine));
+        log::info!("Quota account {} loaded", account);
+        Ok(engine)
+    }
+
+    /// Engine of `account` if it is already loaded (never creates one)
+    pub fn find(&self, account: &str) -> Option<SharedQuota> {
+        self.engines.lock().ok()?.get(account).cloned()
+    }
+
+    /// Engine of the default account
+    pub fn default_account(&self) -> SharedQuota {
+        self.load(DEFAULT_ACCOUNT).expect("Default account ID is valid")
+    }
+
+    /// Loaded accounts, sorted by ID
+    pub fn accounts(&self) -> Vec<(String, SharedQuota)> {
+        self.engines.lock()
+            .map(|engines| engines.iter().map(|(id, engine)| (id.clone(), Arc::clone(engine))).collect())
+            .unwrap_or_default()
+    }
+
+    pub fn calendar(&self) -> &QuotaCalendar {
+        &self.calendar
+    }
+}
+
+impl Default for QuotaAccounts {
+    fn default() -> Self {
+        Self::new(QuotaLimits::default(), HashMap::new(), QuotaCalendar::default())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::Utc;
+
+    fn create_storage(path: &std::path::Path) -> Arc<StorageEngine> {
+        let mut storage = StorageEngine::new(path.to_path_buf()).unwrap();
+        storage.initialize().unwrap();
+        Arc::new(storage)
+    }
+
+    #[tokio::test]
+    async fn test_accounts_are_isolated() {
+        let accounts = QuotaAccounts::new(
+            QuotaLimits { min_gap_seconds: 0, ..QuotaLimits::default() },
+            HashMap::from([
+                ("shop-a".to_string(), QuotaLimits { min_gap_seconds: 0, ..QuotaLimits::default() }),
+                ("shop-b".to_string(), QuotaLimits { messages_per_day: 1, min_gap_seconds: 0, ..QuotaLimits::default() }),
+            ]),
+            QuotaCalendar::default(),
+        );
+        accounts.get("shop-a").unwrap().unwrap().lock().await.record_message(None).unwrap();
+        accounts.get("shop-b").unwrap().unwrap().lock().await.record_message(None).unwrap();
+
+        let shop_a = accounts.get("shop-a").unwrap().unwrap();
+        assert_eq!(shop_a.lock().await.get_usage().messages_today, 1);
+        assert!(shop_a.lock().await.can_send_message(None, Utc::now()).is_allowed());
+        // shop-b has its own limit of 1 message a day
+        assert!(!accounts.get("shop-b").unwrap().unwrap().lock().await.can_send_message(None, Utc::now()).is_allowed());
+        assert_eq!(accounts.default_account().lock().await.get_usage().messages_today, 0);
+
+        let ids: Vec<String> = accounts.accounts().into_iter().map(|(id, _)| id).collect();
+        assert_eq!(ids, vec!["default", "shop-a", "shop-b"]);
+    }
+
+    #[test]
+    fn test_rejects_invalid_account_ids() {
+        let accounts = QuotaAccounts::default();
+        assert!(accounts.get("instance_01.fr-2").is_ok());
+        assert!(accounts.get(DEFAULT_ACCOUNT).unwrap().is_some());
+        for invalid in ["", "shop a", "../default", &"x".repeat(65)] {
+            assert!(accounts.get(invalid).is_err(), "{:?}", invalid);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_saved_and_configured_accounts_are_restored() {
+        let db_path = std::env::temp_dir().join(format!("test_quota_accounts_{}.db", uuid::Uuid::new_v4()));
+        let configured = HashMap::from([("shop-c".to_string(), QuotaLimits::default())]);
+
+        // shop-a was configured when it was used, and no longer is
+        let accounts = QuotaAccounts::with_storage(
+            QuotaLimits::default(),
+            HashMap::from([("shop-a".to_string(), QuotaLimits::default())]),
+            QuotaCalendar::default(),
+            create_storage(&db_path),
+        ).unwrap();
+        accounts.get("shop-a").unwrap().unwrap().lock().await.record_message(None).unwrap();
+        drop(accounts);
+
+        let restored = QuotaAccounts::with_storage(
+            QuotaLimits::default(), configured, QuotaCalendar::default(), create_storage(&db_path),
+        ).unwrap();
+        let ids: Vec<String> = restored.accounts().into_iter().map(|(id, _)| id).collect();
+        assert_eq!(ids, vec!["shop-a", "shop-c"]);
+        let shop_a = restored.get("shop-a").unwrap().unwrap();
+        let shop_a = shop_a.lock().await;
+        assert_eq!(shop_a.account(), "shop-a");
+        assert_eq!(shop_a.get_usage().messages_today, 1);
+        drop(shop_a);
+
+        // Neither configured nor saved: looked up, never created
+        assert!(restored.get("shop-z").unwrap().is_none());
+        assert!(restored.find("shop-z").is_none());
+    }
+}
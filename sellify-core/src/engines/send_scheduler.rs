@@ -0,0 +1,342 @@
+use serde::{Deserialize, Serialize};
+use chrono::{DateTime, Duration, Utc};
+use rand::Rng;
+
+use crate::engines::config::{GlobalConfig, ResponseDelay};
+
+/// Random noise added to each delay of the timeline
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum JitterDistribution {
+    None,
+    /// Uniform in [-spread_ms, +spread_ms]
+    Uniform { spread_ms: u64 },
+    /// Normal distribution, clamped to ±3 standard deviations
+    Gaussian { std_dev_ms: u64 },
+}
+
+/// Typing simulation and message splitting parameters
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SendScheduleConfig {
+    /// Simulated typing speed
+    pub chars_per_second: f64,
+    pub min_typing_ms: u64,
+    pub max_typing_ms: u64,
+    /// Longer texts are split into several messages
+    pub max_message_chars: usize,
+    /// Pause between two parts of a split text
+    pub pause_between_parts_ms: u64,
+    pub jitter: JitterDistribution,
+}
+
+impl Default for SendScheduleConfig {
+    fn default() -> Self {
+        Self {
+            chars_per_second: 5.0,
+            min_typing_ms: 1000,
+            max_typing_ms: 15000,
+            max_message_chars: 300,
+            pause_between_parts_ms: 1500,
+            jitter: JitterDistribution::Gaussian { std_dev_ms: 400 },
+        }
+    }
+}
+
+/// Action a WhatsApp gateway executes
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum GatewayAction {
+    MarkRead,
+    /// Show "typing…" for `duration_ms`
+    Composing { duration_ms: u64 },
+    /// `part` starts at 1
+    Send { text: String, part: usize },
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ScheduledAction {
+    pub at: DateTime<Utc>,
+    /// Milliseconds since the start of the plan
+    pub offset_ms: u64,
+    #[serde(flatten)]
+    pub action: GatewayAction,
+}
+
+/// Timeline of gateway actions for one reply, ordered by time
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SendPlan {
+    pub actions: Vec<ScheduledAction>,
+    pub parts: usize,
+    pub total_ms: u64,
+}
+
+/// Send Scheduler - Plans human-like delivery of a reply
+pub struct SendScheduler {
+    response_delay: ResponseDelay,
+    typing_simulation: bool,
+    config: SendScheduleConfig,
+}
+
+impl SendScheduler {
+    pub fn new(response_delay: ResponseDelay, typing_simulation: bool, config: SendScheduleConfig) -> Self {
+        Self {
+            response_delay,
+            typing_simulation,
+            config,
+        }
+    }
+
+    /// Uses `response_delay`, `anti_ban.typing_simulation` and `send_schedule`
+    pub fn from_config(config: &GlobalConfig) -> Self {
+        Self::new(
+            config.response_delay.clone(),
+            config.anti_ban.typing_simulation,
+            config.send_schedule.clone(),
+        )
+    }
+
+    /// Plans the delivery of `text`, starting at `start` (usually when the inbound message arrived)
+    pub fn plan(&self, text: &str, start: DateTime<Utc>) -> SendPlan {
+        self.plan_with_rng(text, start, &mut rand::thread_rng())
+    }
+
+    /// Same as `plan` with a given random source (seeded in tests)
+    pub fn plan_with_rng<R: Rng>(&self, text: &str, start: DateTime<Utc>, rng: &mut R) -> SendPlan {
+        let mut actions = vec![];
+        let mut offset_ms = 0;
+        let mut push = |offset_ms: u64, action: GatewayAction| {
+            actions.push(ScheduledAction {
+                at: start + Duration::milliseconds(offset_ms as i64),
+                offset_ms,
+                action,
+            });
+        };
+
+        // Read delay: the message is not read instantly
+        let (min, max) = (self.response_delay.min_seconds * 1000, self.response_delay.max_seconds * 1000);
+        let read_delay = if max > min { rng.gen_range(min..=max) } else { min };
+        offset_ms += self.jitter(read_delay, rng);
+        push(offset_ms, GatewayAction::MarkRead);
+
+        let parts = self.split(text);
+        for (index, part) in parts.iter().enumerate() {
+            if index > 0 {
+                offset_ms += self.jitter(self.config.pause_between_parts_ms, rng);
+            }
+            if self.typing_simulation {
+                let duration_ms = self.jitter(self.typing_ms(part), rng);
+                push(offset_ms, GatewayAction::Composing { duration_ms });
+                offset_ms += duration_ms;
+            }
+            push(offset_ms, GatewayAction::Send {
+                text: part.clone(),
+                part: index + 1,
+            });
+        }
+
+        SendPlan {
+            actions,
+            parts: parts.len(),
+            total_ms: offset_ms,
+        }
+    }
+
+    /// Typing time proportional to the length, within [min_typing_ms, max_typing_ms]
+    fn typing_ms(&self, text: &str) -> u64 {
+        let ms = text.chars().count() as f64 / self.config.chars_per_second.max(0.1) * 1000.0;
+        (ms as u64).clamp(self.config.min_typing_ms, self.config.max_typing_ms.max(self.config.min_typing_ms))
+    }
+
+    /// Adds jitter to a delay, never going below zero
+    fn jitter<R: Rng>(&self, delay_ms: u64, rng: &mut R) -> u64 {
+        let noise = match &self.config.jitter {
+            JitterDistribution::None => 0.0,
+            JitterDistribution::Uniform { spread_ms } => {
+                let spread = *spread_ms as f64;
+                rng.gen_range(-spread..=spread)
+            }
+            JitterDistribution::Gaussian { std_dev_ms } => {
+                // Box-Muller transform
+                let std_dev = *std_dev_ms as f64;
+                let (u1, u2): (f64, f64) = (rng.gen_range(f64::EPSILON..1.0), rng.gen());
+                let z = (-2.0 * u1.ln()).sqrt() * (2.0 * std::f64::consts::PI * u2).cos();
+                (z * std_dev).clamp(-3.0 * std_dev, 3.0 * std_dev)
+            }
+        };
+        (delay_ms as f64 + noise).max(0.0).round() as u64
+    }
+
+    /// Splits a long text into messages of at most `max_message_chars` characters
+    /// Cuts at paragraph, then sentence, then word boundaries
+    /// Paragraphs kept in the same message stay on separate lines
+    pub fn split(&self, text: &str) -> Vec<String> {
+        let max = self.config.max_message_chars.max(1);
+        let mut parts: Vec<String> = vec![];
+
+        for (piece, new_paragraph) in Self::sentences(text) {
+            let fits_last = parts.last()
+                .is_some_and(|last| last.chars().count() + 1 + piece.chars().count() <= max);
+            if fits_last {
+                let last = parts.last_mut().expect("checked above");
+                last.push(if new_paragraph { '\n' } else { ' ' });
+                last.push_str(&piece);
+            } else if piece.chars().count() <= max {
+                parts.push(piece);
+            } else {
+                parts.extend(Self::split_words(&piece, max));
+            }
+        }
+        parts
+    }
+
+    /// Sentences, trimmed, each flagged when it starts a paragraph
+    /// A paragraph break always ends a sentence
+    fn sentences(text: &str) -> Vec<(String, bool)> {
+        let mut sentences = vec![];
+        for paragraph in text.split('\n').map(str::trim).filter(|p| !p.is_empty()) {
+            let first = sentences.len();
+            let mut current = String::new();
+            let mut chars = paragraph.chars().peekable();
+            while let Some(c) = chars.next() {
+                current.push(c);
+                if matches!(c, '.' | '!' | '?') && chars.peek().is_some_and(|next| next.is_whitespace()) {
+                    sentences.push((current.trim().to_string(), sentences.len() == first));
+                    current.clear();
+                }
+            }
+            if !current.trim().is_empty() {
+                sentences.push((current.trim().to_string(), sentences.len() == first));
+            }
+        }
+        sentences
+    }
+
+    /// Word-wraps a sentence longer than `max`; a single longer word is cut
+    fn split_words(sentence: &str, max: usize) -> Vec<String> {
+        let mut parts = vec![];
+        let mut current = String::new();
+        for word in sentence.split_whitespace() {
+            let mut word: Vec<char> = word.chars().collect();
+            while word.len() > max {
+                if !current.is_empty() {
+                    parts.push(std::mem::take(&mut current));
+                }
+                parts.push(word.drain(..max).collect());
+            }
+            let word: String = word.into_iter().collect();
+            if current.is_empty() {
+                current = word;
+            } else if current.chars().count() + 1 + word.chars().count() <= max {
+                current.push(' ');
+                current.push_str(&word);
+            } else {
+                parts.push(std::mem::replace(&mut current, word));
+            }
+        }
+        if !current.is_empty() {
+            parts.push(current);
+        }
+        parts
+    }
+}
+
+impl Default for SendScheduler {
+    fn default() -> Self {
+        Self::from_config(crate::engines::config::ConfigEngine::new().get_config())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::SeedableRng;
+    use rand::rngs::StdRng;
+
+    fn scheduler(typing_simulation: bool, config: SendScheduleConfig) -> SendScheduler {
+        SendScheduler::new(ResponseDelay { min_seconds: 2, max_seconds: 8 }, typing_simulation, config)
+    }
+
+    #[test]
+    fn test_timeline_read_compose_send() {
+        let scheduler = scheduler(true, SendScheduleConfig {
+            jitter: JitterDistribution::None,
+            ..Default::default()
+        });
+        let start = Utc::now();
+        let plan = scheduler.plan_with_rng("Oui, elle est disponible en noir.", start, &mut StdRng::seed_from_u64(7));
+
+        assert_eq!(plan.parts, 1);
+        assert_eq!(plan.actions.len(), 3);
+        assert_eq!(plan.actions[0].action, GatewayAction::MarkRead);
+        assert!((2000..=8000).contains(&plan.actions[0].offset_ms));
+        // 33 characters at 5 per second
+        assert_eq!(plan.actions[1].action, GatewayAction::Composing { duration_ms: 6600 });
+        assert_eq!(plan.actions[2].offset_ms, plan.actions[0].offset_ms + 6600);
+        assert_eq!(plan.actions[2].at, start + Duration::milliseconds(plan.total_ms as i64));
+    }
+
+    #[test]
+    fn test_typing_duration_is_clamped() {
+        let scheduler = scheduler(true, SendScheduleConfig::default());
+        assert_eq!(scheduler.typing_ms("Ok"), 1000);
+        assert_eq!(scheduler.typing_ms(&"a".repeat(300)), 15000);
+    }
+
+    #[test]
+    fn test_no_composing_without_typing_simulation() {
+        let scheduler = scheduler(false, SendScheduleConfig::default());
+        let plan = scheduler.plan("Bonjour", Utc::now());
+        let kinds: Vec<_> = plan.actions.iter().map(|a| std::mem::discriminant(&a.action)).collect();
+        assert_eq!(kinds, vec![
+            std::mem::discriminant(&GatewayAction::MarkRead),
+            std::mem::discriminant(&GatewayAction::Send { text: String::new(), part: 1 }),
+        ]);
+    }
+
+    #[test]
+    fn test_long_text_is_split() {
+        let scheduler = scheduler(true, SendScheduleConfig {
+            max_message_chars: 40,
+            ..Default::default()
+        });
+        let text = "Bonjour ! La montre coûte 49,90 €.\nLivraison en 48h partout en France métropolitaine, sans frais supplémentaires.";
+        let parts = scheduler.split(text);
+
+        assert_eq!(parts[0], "Bonjour ! La montre coûte 49,90 €.");
+        assert!(parts.iter().all(|p| p.chars().count() <= 40), "{:?}", parts);
+        assert_eq!(parts.join(" ").split_whitespace().count(), text.split_whitespace().count());
+
+        let plan = scheduler.plan(text, Utc::now());
+        assert_eq!(plan.parts, parts.len());
+        assert!(plan.actions.windows(2).all(|w| w[0].offset_ms <= w[1].offset_ms));
+    }
+
+    #[test]
+    fn test_merged_paragraphs_keep_line_breaks() {
+        let scheduler = scheduler(true, SendScheduleConfig {
+            max_message_chars: 60,
+            ..Default::default()
+        });
+        let parts = scheduler.split("Bonjour !\nLa montre coûte 49,90 €. Livrée en 48h.\n\nÀ bientôt");
+
+        assert_eq!(parts, vec!["Bonjour !\nLa montre coûte 49,90 €. Livrée en 48h.\nÀ bientôt"]);
+    }
+
+    #[test]
+    fn test_jitter_stays_within_bounds() {
+        let mut rng = StdRng::seed_from_u64(42);
+        let uniform = scheduler(true, SendScheduleConfig {
+            jitter: JitterDistribution::Uniform { spread_ms: 500 },
+            ..Default::default()
+        });
+        let gaussian = scheduler(true, SendScheduleConfig {
+            jitter: JitterDistribution::Gaussian { std_dev_ms: 200 },
+            ..Default::default()
+        });
+        for _ in 0..200 {
+            assert!((500..=1500).contains(&uniform.jitter(1000, &mut rng)));
+            assert!((400..=1600).contains(&gaussian.jitter(1000, &mut rng)));
+            assert!(gaussian.jitter(0, &mut rng) <= 600);
+        }
+    }
+}
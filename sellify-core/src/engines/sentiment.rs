@@ -0,0 +1,348 @@
+use serde::{Deserialize, Serialize};
+use std::fmt;
+
+use crate::engines::classifier::EventClassifier;
+
+/// Sentiment of a customer message
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Sentiment {
+    Anger,
+    Threat,
+    Frustration,
+    Neutral,
+    Positive,
+}
+
+impl fmt::Display for Sentiment {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            Sentiment::Anger => "anger",
+            Sentiment::Threat => "threat",
+            Sentiment::Frustration => "frustration",
+            Sentiment::Neutral => "neutral",
+            Sentiment::Positive => "positive",
+        };
+        f.write_str(name)
+    }
+}
+
+/// Word or phrase and how strongly it signals its sentiment
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct WeightedTerm {
+    pub term: String,
+    pub weight: f64,
+}
+
+/// Emoji and the sentiment it signals
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct WeightedEmoji {
+    pub emoji: String,
+    pub sentiment: Sentiment,
+    pub weight: f64,
+}
+
+/// Lexicons and rules of the sentiment engine (French, English and Arabic by default)
+/// Terms are matched as whole words or phrases, case-insensitively; threat terms
+/// are explicit ("tuer", "kill"): everyday verbs such as "hurt" or "détruire" are left out
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SentimentConfig {
+    pub threat: Vec<WeightedTerm>,
+    pub anger: Vec<WeightedTerm>,
+    pub frustration: Vec<WeightedTerm>,
+    pub positive: Vec<WeightedTerm>,
+    /// Words cancelling a term among the 3 words before it ("pas content" is frustration, not positive)
+    pub negations: Vec<String>,
+    /// Words multiplying a term among the 3 words before it by `intensifier_factor`
+    pub intensifiers: Vec<String>,
+    pub intensifier_factor: f64,
+    pub emoji: Vec<WeightedEmoji>,
+    /// Added to anger when a message written in capitals already carries anger or
+    /// frustration; capitals alone ("OK MERCI BEAUCOUP") are not anger
+    pub shouting_weight: f64,
+    /// Below this score the message is neutral
+    pub min_score: f64,
+}
+
+impl Default for SentimentConfig {
+    fn default() -> Self {
+        let terms = |list: &[(&str, f64)]| {
+            list.iter()
+                .map(|(term, weight)| WeightedTerm { term: term.to_string(), weight: *weight })
+                .collect::<Vec<_>>()
+        };
+        let words = |list: &[&str]| list.iter().map(|w| w.to_string()).collect::<Vec<_>>();
+        let emoji = |list: &[(&str, Sentiment, f64)]| {
+            list.iter()
+                .map(|(emoji, sentiment, weight)| WeightedEmoji { emoji: emoji.to_string(), sentiment: *sentiment, weight: *weight })
+                .collect::<Vec<_>>()
+        };
+        Self {
+            threat: terms(&[
+                ("tuer", 1.0), ("frapper", 0.8), ("menace", 0.6), ("vous allez le regretter", 1.0),
+                ("kill", 1.0), ("you will regret", 1.0),
+                ("أقتلك", 1.0), ("سأضربك", 1.0),
+            ]),
+            anger: terms(&[
+                ("colère", 0.6), ("furieux", 0.8), ("furieuse", 0.8), ("inadmissible", 0.6), ("honteux", 0.6),
+                ("voleur", 0.8), ("voleurs", 0.8), ("escroc", 0.8), ("arnaque", 0.5), ("nul", 0.4), ("merde", 0.6),
+                ("angry", 0.6), ("furious", 0.8), ("unacceptable", 0.6), ("thief", 0.8), ("thieves", 0.8),
+                ("scam", 0.5), ("ridiculous", 0.4), ("shit", 0.6),
+                ("غاضب", 0.6), ("حرامي", 0.8), ("نصاب", 0.8),
+            ]),
+            frustration: terms(&[
+                ("toujours pas", 0.5), ("encore", 0.2), ("j'attends", 0.4), ("déçu", 0.5), ("déçue", 0.5),
+                ("marre", 0.6), ("pas normal", 0.5), ("rien reçu", 0.6), ("personne ne répond", 0.6),
+                ("still", 0.2), ("still waiting", 0.6), ("disappointed", 0.5), ("fed up", 0.6),
+                ("nobody answers", 0.6), ("again", 0.2),
+                ("مازال", 0.4), ("خايب", 0.5),
+            ]),
+            positive: terms(&[
+                ("merci", 0.4), ("super", 0.5), ("parfait", 0.6), ("génial", 0.6), ("top", 0.4),
+                ("content", 0.5), ("contente", 0.5), ("satisfait", 0.5), ("satisfaite", 0.5), ("bravo", 0.5),
+                ("thanks", 0.4), ("thank you", 0.4), ("great", 0.5), ("perfect", 0.6), ("happy", 0.5),
+                ("awesome", 0.6), ("love", 0.5),
+                ("شكرا", 0.4), ("ممتاز", 0.6), ("زوين", 0.5),
+            ]),
+            negations: words(&[
+                "pas", "jamais", "ni", "aucun", "aucune", "not", "never", "no", "don", "doesn", "isn",
+                "لا", "ما", "مش", "ماشي",
+            ]),
+            intensifiers: words(&[
+                "très", "vraiment", "tellement", "trop", "complètement", "very", "really", "so", "totally",
+                "extremely", "جدا", "بزاف",
+            ]),
+            intensifier_factor: 1.5,
+            emoji: emoji(&[
+                ("😡", Sentiment::Anger, 0.6), ("🤬", Sentiment::Anger, 0.8), ("😠", Sentiment::Anger, 0.5),
+                ("🔪", Sentiment::Threat, 0.6), ("💣", Sentiment::Threat, 0.6),
+                ("😤", Sentiment::Frustration, 0.4), ("😒", Sentiment::Frustration, 0.3), ("🙄", Sentiment::Frustration, 0.3),
+                ("😞", Sentiment::Frustration, 0.3),
+                ("😊", Sentiment::Positive, 0.4), ("😍", Sentiment::Positive, 0.5), ("👍", Sentiment::Positive, 0.3),
+                ("🙏", Sentiment::Positive, 0.3), ("❤️", Sentiment::Positive, 0.4),
+            ]),
+            shouting_weight: 0.5,
+            min_score: 0.5,
+        }
+    }
+}
+
+/// Detected sentiment, with what produced it (explainability)
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SentimentAnalysis {
+    pub sentiment: Sentiment,
+    /// Strength of `sentiment` in [0, 1]; 0 for neutral
+    pub score: f64,
+    /// Signals found, e.g. "anger:furieux", "intensifier:très", "negation:pas", "emoji:😡", "capitals"
+    pub signals: Vec<String>,
+}
+
+/// Sentiment Engine - Local, deterministic sentiment detection (no network, no AI)
+/// Same message + same lexicons = same result
+pub struct SentimentEngine {
+    config: SentimentConfig,
+}
+
+impl SentimentEngine {
+    pub fn new(config: SentimentConfig) -> Self {
+        Self { config }
+    }
+
+    /// Scores each sentiment and returns the strongest one above `min_score`
+    /// Threat wins over the others as soon as it reaches `min_score`
+    pub fn analyze(&self, message: &str) -> SentimentAnalysis {
+        let text = EventClassifier::normalize(message);
+        let words: Vec<&str> = text.split_whitespace().collect();
+        let mut scores = Scores::default();
+        let mut signals = vec![];
+
+        let lexicons = [
+            (Sentiment::Threat, &self.config.threat),
+            (Sentiment::Anger, &self.config.anger),
+            (Sentiment::Frustration, &self.config.frustration),
+            (Sentiment::Positive, &self.config.positive),
+        ];
+        for (sentiment, lexicon) in lexicons {
+            for term in lexicon {
+                for start in Self::find_term(&words, &term.term) {
+                    let before = &words[start.saturating_sub(3)..start];
+                    let mut weight = term.weight;
+                    if let Some(word) = Self::find_word(before, &self.config.intensifiers) {
+                        weight *= self.config.intensifier_factor;
+                        signals.push(format!("intensifier:{}", word));
+                    }
+                    match Self::find_word(before, &self.config.negations) {
+                        // A negated positive term is a complaint ("pas satisfait"), other negated terms are dropped
+                        Some(word) => {
+                            signals.push(format!("negation:{}", word));
+                            if sentiment == Sentiment::Positive {
+                                scores.add(Sentiment::Frustration, weight);
+                                signals.push(format!("frustration:not {}", term.term));
+                            }
+                        }
+                        None => {
+                            scores.add(sentiment, weight);
+                            signals.push(format!("{}:{}", sentiment, term.term));
+                        }
+                    }
+                }
+            }
+        }
+
+        for emoji in &self.config.emoji {
+            let count = message.matches(emoji.emoji.as_str()).count();
+            if count > 0 {
+                scores.add(emoji.sentiment, emoji.weight * count as f64);
+                signals.push(format!("emoji:{}", emoji.emoji));
+            }
+        }
+
+        if Self::is_shouting(message) && (scores.anger > 0.0 || scores.frustration > 0.0) {
+            scores.add(Sentiment::Anger, self.config.shouting_weight);
+            signals.push("capitals".to_string());
+        }
+
+        let (sentiment, score) = scores.strongest(self.config.min_score);
+        SentimentAnalysis { sentiment, score, signals }
+    }
+
+    /// Detected sentiment name for the Decision Engine, `None` when neutral
+    pub fn detect(&self, message: &str) -> Option<String> {
+        let analysis = self.analyze(message);
+        (analysis.sentiment != Sentiment::Neutral).then(|| analysis.sentiment.to_string())
+    }
+
+    /// Start index of each occurrence of `term` in `words`
+    fn find_term(words: &[&str], term: &str) -> Vec<usize> {
+        let normalized = EventClassifier::normalize(term);
+        let term: Vec<&str> = normalized.split_whitespace().collect();
+        if term.is_empty() || term.len() > words.len() {
+            return vec![];
+        }
+        (0..=words.len() - term.len())
+            .filter(|&start| words[start..start + term.len()] == term[..])
+            .collect()
+    }
+
+    fn find_word<'a>(words: &[&'a str], list: &[String]) -> Option<&'a str> {
+        words.iter().copied().find(|word| list.iter().any(|entry| entry.to_lowercase() == *word))
+    }
+
+    /// At least 6 cased letters, 80% of them in capitals
+    fn is_shouting(message: &str) -> bool {
+        let cased: Vec<char> = message.chars().filter(|c| c.is_uppercase() || c.is_lowercase()).collect();
+        let upper = cased.iter().filter(|c| c.is_uppercase()).count();
+        cased.len() >= 6 && upper * 5 >= cased.len() * 4
+    }
+}
+
+impl Default for SentimentEngine {
+    fn default() -> Self {
+        Self::new(SentimentConfig::default())
+    }
+}
+
+#[derive(Default)]
+struct Scores {
+    threat: f64,
+    anger: f64,
+    frustration: f64,
+    positive: f64,
+}
+
+impl Scores {
+    fn add(&mut self, sentiment: Sentiment, weight: f64) {
+        match sentiment {
+            Sentiment::Threat => self.threat += weight,
+            Sentiment::Anger => self.anger += weight,
+            Sentiment::Frustration => self.frustration += weight,
+            Sentiment::Positive => self.positive += weight,
+            Sentiment::Neutral => {}
+        }
+    }
+
+    /// On equal scores the first of threat, anger, frustration, positive wins
+    fn strongest(&self, min_score: f64) -> (Sentiment, f64) {
+        let clamp = |score: f64| score.min(1.0);
+        if clamp(self.threat) >= min_score {
+            return (Sentiment::Threat, clamp(self.threat));
+        }
+        let mut best = (Sentiment::Neutral, 0.0);
+        for (sentiment, score) in [
+            (Sentiment::Anger, self.anger),
+            (Sentiment::Frustration, self.frustration),
+            (Sentiment::Positive, self.positive),
+        ] {
+            if clamp(score) >= min_score && clamp(score) > best.1 {
+                best = (sentiment, clamp(score));
+            }
+        }
+        best
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sentiment(message: &str) -> Sentiment {
+        SentimentEngine::default().analyze(message).sentiment
+    }
+
+    #[test]
+    fn test_detects_each_sentiment() {
+        assert_eq!(sentiment("Je vais te tuer"), Sentiment::Threat);
+        assert_eq!(sentiment("Vous êtes des voleurs"), Sentiment::Anger);
+        assert_eq!(sentiment("J'en ai marre d'attendre"), Sentiment::Frustration);
+        assert_eq!(sentiment("Parfait, merci !"), Sentiment::Positive);
+        assert_eq!(sentiment("Bonjour, je voudrais des informations"), Sentiment::Neutral);
+        assert_eq!(SentimentEngine::default().analyze("Bonjour").score, 0.0);
+    }
+
+    #[test]
+    fn test_negation_and_intensifiers() {
+        let engine = SentimentEngine::default();
+        assert_eq!(sentiment("Je ne vais pas te frapper"), Sentiment::Neutral);
+        assert_eq!(sentiment("Je ne suis pas satisfait"), Sentiment::Frustration);
+        let analysis = engine.analyze("Je ne suis vraiment pas satisfait 😤");
+        assert_eq!(analysis.score, 1.0);
+        assert_eq!(analysis.signals, ["intensifier:vraiment", "negation:pas", "frustration:not satisfait", "emoji:😤"]);
+
+        // "top" alone stays below the threshold, "vraiment top" does not
+        assert_eq!(sentiment("C'est top"), Sentiment::Neutral);
+        let analysis = engine.analyze("C'est vraiment top");
+        assert_eq!(analysis.sentiment, Sentiment::Positive);
+        assert!((analysis.score - 0.6).abs() < 1e-9);
+        assert!(analysis.signals.contains(&"intensifier:vraiment".to_string()));
+    }
+
+    #[test]
+    fn test_emoji_and_capitals() {
+        assert_eq!(sentiment("Toujours rien 😡"), Sentiment::Anger);
+        assert_eq!(sentiment("Reçu 😍"), Sentiment::Positive);
+
+        // Capitals only raise a complaint to anger
+        let analysis = SentimentEngine::default().analyze("TOUJOURS PAS REÇU");
+        assert_eq!(analysis.sentiment, Sentiment::Anger);
+        assert_eq!(analysis.signals, ["frustration:toujours pas", "capitals"]);
+        assert_eq!(sentiment("JE VEUX MON COLIS"), Sentiment::Neutral);
+        assert_eq!(sentiment("OK MERCI BEAUCOUP"), Sentiment::Neutral);
+        assert_eq!(sentiment("SUPER MERCI"), Sentiment::Positive);
+        assert_eq!(sentiment("OK"), Sentiment::Neutral);
+    }
+
+    #[test]
+    fn test_everyday_words_are_not_threats() {
+        assert_eq!(sentiment("Will it hurt my skin?"), Sentiment::Neutral);
+        assert_eq!(sentiment("Can the charger destroy the battery?"), Sentiment::Neutral);
+        assert_eq!(sentiment("Le carton risque de détruire la montre ?"), Sentiment::Neutral);
+    }
+
+    #[test]
+    fn test_threat_takes_precedence() {
+        let analysis = SentimentEngine::default().analyze("Merci, mais si ça continue je vais vous frapper");
+        assert_eq!(analysis.sentiment, Sentiment::Threat);
+        assert_eq!(SentimentEngine::default().detect("Parfait"), Some("positive".to_string()));
+        assert_eq!(SentimentEngine::default().detect("Bonjour"), None);
+    }
+}
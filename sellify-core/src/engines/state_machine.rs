@@ -0,0 +1,316 @@
+use serde::{Deserialize, Serialize};
+use anyhow::{Result, anyhow};
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::path::Path;
+
+use crate::engines::conversation::{ConversationEvent, ConversationState};
+
+/// Built-in funnel, used when no definition is configured
+const BUILTIN_DEFINITION: &str = include_str!("state_machine.toml");
+
+/// Declarative state machine, as written in TOML or JSON
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct StateMachineDefinition {
+    pub initial: ConversationState,
+    pub states: Vec<StateDefinition>,
+    pub events: Vec<ConversationEvent>,
+    /// Pairs not listed here keep the current state
+    pub transitions: Vec<TransitionDefinition>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct StateDefinition {
+    pub name: ConversationState,
+    /// Terminal states stop the automation
+    #[serde(default)]
+    pub terminal: bool,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct TransitionDefinition {
+    pub from: ConversationState,
+    pub event: ConversationEvent,
+    pub to: ConversationState,
+}
+
+/// Validated transition table used by the Conversation Engine
+#[derive(Debug, Clone)]
+pub struct TransitionTable {
+    definition: StateMachineDefinition,
+    transitions: HashMap<(ConversationState, ConversationEvent), ConversationState>,
+    terminal: HashSet<ConversationState>,
+}
+
+impl TransitionTable {
+    /// The PRD funnel (Discovery → Interest → Intent ...)
+    pub fn builtin() -> Self {
+        Self::from_toml(BUILTIN_DEFINITION).expect("Built-in state machine is valid")
+    }
+
+    pub fn from_toml(data: &str) -> Result<Self> {
+        let definition: StateMachineDefinition = toml::from_str(data)
+            .map_err(|e| anyhow!("Invalid state machine definition: {}", e))?;
+        Self::from_definition(definition)
+    }
+
+    pub fn from_json(data: &str) -> Result<Self> {
+        let definition: StateMachineDefinition = serde_json::from_str(data)
+            .map_err(|e| anyhow!("Invalid state machine definition: {}", e))?;
+        Self::from_definition(definition)
+    }
+
+    /// Loads a `.toml` or `.json` file
+    pub fn load(path: &Path) -> Result<Self> {
+        let data = std::fs::read_to_string(path)
+            .map_err(|e| anyhow!("Cannot read {}: {}", path.display(), e))?;
+        match path.extension().and_then(|e| e.to_str()) {
+            Some("json") => Self::from_json(&data),
+            Some("toml") => Self::from_toml(&data),
+            _ => Err(anyhow!("Unsupported state machine format: {}", path.display())),
+        }
+    }
+
+    /// Validates the definition and builds the lookup table
+    pub fn from_definition(definition: StateMachineDefinition) -> Result<Self> {
+        let mut problems = vec![];
+
+        let mut states = HashSet::new();
+        for state in &definition.states {
+            if !states.insert(state.name.clone()) {
+                problems.push(format!("state {} is declared twice", state.name));
+            }
+        }
+        let mut events = HashSet::new();
+        for event in &definition.events {
+            if !events.insert(event.clone()) {
+                problems.push(format!("event {} is declared twice", event));
+            }
+        }
+        if !states.contains(&definition.initial) {
+            problems.push(format!("initial state {} is not declared", definition.initial));
+        }
+
+        let mut transitions = HashMap::new();
+        for t in &definition.transitions {
+            for state in [&t.from, &t.to] {
+                if !states.contains(state) {
+                    problems.push(format!("transition uses undeclared state {}", state));
+                }
+            }
+            if !events.contains(&t.event) {
+                problems.push(format!("transition uses undeclared event {}", t.event));
+            }
+            if transitions.insert((t.from.clone(), t.event.clone()), t.to.clone()).is_some() {
+                problems.push(format!("transition {} + {} is defined twice", t.from, t.event));
+            }
+        }
+
+        let terminal: HashSet<ConversationState> = definition.states.iter()
+            .filter(|s| s.terminal)
+            .map(|s| s.name.clone())
+            .collect();
+        if terminal.is_empty() {
+            problems.push("no terminal state".to_string());
+        }
+
+        if problems.is_empty() {
+            let reachable = Self::reachable_from(std::slice::from_ref(&definition.initial), &definition.transitions, false);
+            let reaches_terminal = Self::reachable_from(
+                &terminal.iter().cloned().collect::<Vec<_>>(),
+                &definition.transitions,
+                true,
+            );
+            for state in &definition.states {
+                if !reachable.contains(&state.name) {
+                    problems.push(format!("state {} is unreachable from {}", state.name, definition.initial));
+                }
+                if !reaches_terminal.contains(&state.name) {
+                    problems.push(format!("state {} has no path to a terminal state", state.name));
+                }
+            }
+        }
+
+        if !problems.is_empty() {
+            return Err(anyhow!("Invalid state machine definition: {}", problems.join("; ")));
+        }
+
+        Ok(Self {
+            definition,
+            transitions,
+            terminal,
+        })
+    }
+
+    /// Breadth-first search over the transitions (backwards when `reverse`)
+    fn reachable_from(
+        start: &[ConversationState],
+        transitions: &[TransitionDefinition],
+        reverse: bool,
+    ) -> HashSet<ConversationState> {
+        let mut seen: HashSet<ConversationState> = start.iter().cloned().collect();
+        let mut queue: VecDeque<ConversationState> = start.iter().cloned().collect();
+        while let Some(state) = queue.pop_front() {
+            for t in transitions {
+                let (source, target) = if reverse { (&t.to, &t.from) } else { (&t.from, &t.to) };
+                if *source == state && seen.insert(target.clone()) {
+                    queue.push_back(target.clone());
+                }
+            }
+        }
+        seen
+    }
+
+    pub fn definition(&self) -> &StateMachineDefinition {
+        &self.definition
+    }
+
+    pub fn initial_state(&self) -> ConversationState {
+        self.definition.initial.clone()
+    }
+
+    /// Next state, or the current one when the pair is not in the table
+    pub fn next_state(&self, current: &ConversationState, event: &ConversationEvent) -> ConversationState {
+        self.transitions
+            .get(&(current.clone(), event.clone()))
+            .cloned()
+            .unwrap_or_else(|| current.clone())
+    }
+
+    pub fn is_terminal(&self, state: &ConversationState) -> bool {
+        self.terminal.contains(state)
+    }
+
+    pub fn has_state(&self, state: &ConversationState) -> bool {
+        self.definition.states.iter().any(|s| s.name == *state)
+    }
+
+    pub fn has_event(&self, event: &ConversationEvent) -> bool {
+        self.definition.events.contains(event)
+    }
+}
+
+impl Default for TransitionTable {
+    fn default() -> Self {
+        Self::builtin()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const NEGOTIATION_FUNNEL: &str = r#"
+        initial = "Discovery"
+        events = ["ProductQuestion", "PurchaseIntent", "PaymentReceived", "ThreatDetected"]
+        states = [
+            { name = "Discovery" },
+            { name = "Negotiation" },
+            { name = "AwaitingPayment" },
+            { name = "Paid", terminal = true },
+            { name = "Escalated", terminal = true },
+        ]
+        transitions = [
+            { from = "Discovery", event = "ProductQuestion", to = "Negotiation" },
+            { from = "Negotiation", event = "PurchaseIntent", to = "AwaitingPayment" },
+            { from = "AwaitingPayment", event = "PaymentReceived", to = "Paid" },
+            { from = "Discovery", event = "ThreatDetected", to = "Escalated" },
+            { from = "Negotiation", event = "ThreatDetected", to = "Escalated" },
+        ]
+    "#;
+
+    #[test]
+    fn test_builtin_table_is_valid() {
+        let table = TransitionTable::builtin();
+        assert_eq!(table.initial_state(), ConversationState::Discovery);
+        assert!(table.is_terminal(&ConversationState::Frozen));
+        assert!(!table.is_terminal(&ConversationState::Negative));
+        assert_eq!(
+            table.next_state(&ConversationState::Negative, &ConversationEvent::ProductQuestion),
+            ConversationState::Negative
+        );
+    }
+
+    #[test]
+    fn test_builtin_table_is_the_baseline_funnel() {
+        use ConversationEvent::*;
+        use ConversationState::*;
+        // Changes of state of the original hard-coded funnel; every other pair keeps the state
+        let baseline: HashSet<(ConversationState, ConversationEvent, ConversationState)> = [
+            (Discovery, ProductQuestion, Interest),
+            (Discovery, NegativeResponse, Negative),
+            (Discovery, ThreatDetected, Escalated),
+            (Interest, PriceInterest, Intent),
+            (Interest, ObjectionRaised, Objection),
+            (Interest, NegativeResponse, Negative),
+            (Interest, ThreatDetected, Escalated),
+            (Intent, ObjectionRaised, Objection),
+            (Intent, NegativeResponse, Negative),
+            (Intent, ThreatDetected, Escalated),
+            (Objection, ProductQuestion, Interest),
+            (Objection, NegativeResponse, Negative),
+            (Objection, ThreatDetected, Escalated),
+            (Negative, Freeze, Frozen),
+            (Escalated, Freeze, Frozen),
+        ].into_iter().collect();
+
+        let table = TransitionTable::builtin();
+        let definition = table.definition();
+        let mut changes = HashSet::new();
+        for state in &definition.states {
+            for event in &definition.events {
+                let next = table.next_state(&state.name, event);
+                if next != state.name {
+                    changes.insert((state.name.clone(), event.clone(), next));
+                }
+            }
+        }
+        assert_eq!(changes, baseline);
+    }
+
+    #[test]
+    fn test_custom_states_and_events() {
+        let table = TransitionTable::from_toml(NEGOTIATION_FUNNEL).unwrap();
+        let negotiation: ConversationState = "Negotiation".parse().unwrap();
+        let awaiting: ConversationState = "AwaitingPayment".parse().unwrap();
+        let paid: ConversationEvent = "PaymentReceived".parse().unwrap();
+
+        assert_eq!(table.next_state(&ConversationState::Discovery, &ConversationEvent::ProductQuestion), negotiation);
+        assert_eq!(table.next_state(&negotiation, &ConversationEvent::PurchaseIntent), awaiting);
+        assert!(table.is_terminal(&table.next_state(&awaiting, &paid)));
+    }
+
+    #[test]
+    fn test_json_round_trip() {
+        let table = TransitionTable::builtin();
+        let json = serde_json::to_string(table.definition()).unwrap();
+        let reloaded = TransitionTable::from_json(&json).unwrap();
+        assert_eq!(reloaded.definition(), table.definition());
+    }
+
+    #[test]
+    fn test_rejects_unreachable_state() {
+        let data = NEGOTIATION_FUNNEL.replace(
+            r#"{ from = "Discovery", event = "ProductQuestion", to = "Negotiation" },"#,
+            "",
+        );
+        let error = TransitionTable::from_toml(&data).unwrap_err().to_string();
+        assert!(error.contains("state Negotiation is unreachable"), "{}", error);
+    }
+
+    #[test]
+    fn test_rejects_missing_terminal_path() {
+        let data = NEGOTIATION_FUNNEL.replace(
+            r#"{ from = "AwaitingPayment", event = "PaymentReceived", to = "Paid" },"#,
+            r#"{ from = "Negotiation", event = "PaymentReceived", to = "Paid" },"#,
+        );
+        let error = TransitionTable::from_toml(&data).unwrap_err().to_string();
+        assert!(error.contains("state AwaitingPayment has no path to a terminal state"), "{}", error);
+    }
+
+    #[test]
+    fn test_rejects_undeclared_names() {
+        let data = NEGOTIATION_FUNNEL.replace(r#"to = "Paid""#, r#"to = "Shipped""#);
+        let error = TransitionTable::from_toml(&data).unwrap_err().to_string();
+        assert!(error.contains("undeclared state Shipped"), "{}", error);
+    }
+}
@@ -1,17 +1,24 @@
 use anyhow::{Result, anyhow};
-use rusqlite::Connection;
+use chrono::{DateTime, Utc};
+use rusqlite::{Connection, OptionalExtension};
 use serde::{Deserialize, Serialize};
 use std::path::PathBuf;
+use std::sync::{Mutex, MutexGuard};
 use aes_gcm::{
     aead::{Aead, KeyInit, OsRng, AeadCore},
     Aes256Gcm, Nonce, Key
 };
 use sha2::{Sha256, Digest};
+use crate::engines::knowledge_base::CatalogVersion;
+use crate::engines::conversation::{Conversation, ConversationState, HumanTakeover, TransitionRecord};
+use crate::engines::outbound::{OutboundMessage, OutboundStatus};
+use crate::engines::quota::QuotaUsage;
 
 /// Storage Engine - Local encrypted and transactional storage
+/// The connection is guarded internally so the engine can be shared as `Arc<StorageEngine>`
 pub struct StorageEngine {
     db_path: PathBuf,
-    conn: Option<Connection>,
+    conn: Option<Mutex<Connection>>,
     encryption_key: Option<Key<Aes256Gcm>>,
 }
 
@@ -51,7 +58,7 @@ impl StorageEngine {
 
     /// Initializes the database and creates tables
     pub fn initialize(&mut self) -> Result<()> {
-        let conn = Connection::open(&self.db_path)?;
+        let mut conn = Connection::open(&self.db_path)?;
         
         // Create tables for encrypted data storage
         conn.execute(
@@ -70,12 +77,85 @@ impl StorageEngine {
                 id TEXT PRIMARY KEY,
                 phone_number TEXT NOT NULL,
                 state TEXT NOT NULL,
+                focus_product_id TEXT,
+                misunderstanding_count INTEGER NOT NULL DEFAULT 0,
                 created_at INTEGER NOT NULL,
                 updated_at INTEGER NOT NULL
             )",
             [],
         )?;
 
+        // Databases created before these columns existed
+        Self::ensure_column(&conn, "conversations", "focus_product_id", "TEXT")?;
+        Self::ensure_column(&conn, "conversations", "misunderstanding_count", "INTEGER NOT NULL DEFAULT 0")?;
+        Self::ensure_column(&conn, "conversations", "state_since", "INTEGER")?;
+        Self::ensure_column(&conn, "conversations", "takeover_operator", "TEXT")?;
+        Self::ensure_column(&conn, "conversations", "takeover_claimed_at", "INTEGER")?;
+        Self::ensure_column(&conn, "conversations", "takeover_expires_at", "INTEGER")?;
+
+        conn.execute(
+            "CREATE TABLE IF NOT EXISTS conversation_transitions (
+                id INTEGER PRIMARY KEY AUTOINCREMENT,
+                conversation_id TEXT NOT NULL,
+                event TEXT NOT NULL,
+                from_state TEXT NOT NULL,
+                to_state TEXT NOT NULL,
+                product_id TEXT,
+                timestamp INTEGER NOT NULL,
+                actor TEXT NOT NULL DEFAULT 'automation',
+                reason TEXT,
+                FOREIGN KEY(conversation_id) REFERENCES conversations(id)
+            )",
+            [],
+        )?;
+        Self::ensure_column(&conn, "conversation_transitions", "actor", "TEXT NOT NULL DEFAULT 'automation'")?;
+        Self::ensure_column(&conn, "conversation_transitions", "reason", "TEXT")?;
+
+        conn.execute(
+            "CREATE TABLE IF NOT EXISTS quota_usage (
+                key TEXT PRIMARY KEY,
+                usage TEXT NOT NULL,
+                updated_at INTEGER NOT NULL
+            )",
+            [],
+        )?;
+
+        conn.execute(
+            "CREATE TABLE IF NOT EXISTS outbound_queue (
+                id TEXT PRIMARY KEY,
+                idempotency_key TEXT NOT NULL UNIQUE,
+                conversation_id TEXT NOT NULL,
+                recipient TEXT NOT NULL,
+                payload TEXT NOT NULL,
+                state_at_enqueue TEXT NOT NULL,
+                scheduled_at INTEGER NOT NULL,
+                status TEXT NOT NULL,
+                attempts INTEGER NOT NULL DEFAULT 0,
+                last_error TEXT,
+                created_at INTEGER NOT NULL,
+                updated_at INTEGER NOT NULL,
+                account TEXT NOT NULL DEFAULT 'default',
+                FOREIGN KEY(conversation_id) REFERENCES conversations(id)
+            )",
+            [],
+        )?;
+        Self::ensure_column(&conn, "outbound_queue", "account", "TEXT NOT NULL DEFAULT 'default'")?;
+        conn.execute(
+            "CREATE INDEX IF NOT EXISTS idx_outbound_due ON outbound_queue (status, scheduled_at)",
+            [],
+        )?;
+
+        conn.execute(
+            "CREATE TABLE IF NOT EXISTS catalog_versions (
+                version INTEGER PRIMARY KEY,
+                hash TEXT NOT NULL,
+                created_at INTEGER NOT NULL,
+                restored_from INTEGER,
+                products TEXT NOT NULL
+            )",
+            [],
+        )?;
+
         conn.execute(
             "CREATE TABLE IF NOT EXISTS messages (
                 id TEXT PRIMARY KEY,
@@ -99,10 +179,78 @@ impl StorageEngine {
             [],
         )?;
 
-        self.conn = Some(conn);
+        // One conversation per phone number (an index, so that existing databases get it too);
+        // created last, once the rows of older databases are merged
+        Self::merge_duplicate_phones(&mut conn)?;
+        conn.execute(
+            "CREATE UNIQUE INDEX IF NOT EXISTS idx_conversations_phone ON conversations (phone_number)",
+            [],
+        )?;
+
+        self.conn = Some(Mutex::new(conn));
+        Ok(())
+    }
+
+    /// Merges the conversations sharing a phone number into the most recently updated one,
+    /// with the history, messages, queued replies and audit entries of the others
+    /// (databases created before phone numbers were unique)
+    fn merge_duplicate_phones(conn: &mut Connection) -> Result<()> {
+        let indexed: bool = conn.query_row(
+            "SELECT EXISTS (SELECT 1 FROM sqlite_master WHERE type = 'index' AND name = 'idx_conversations_phone')",
+            [],
+            |row| row.get(0),
+        )?;
+        if indexed {
+            return Ok(());
+        }
+
+        let tx = conn.transaction()?;
+        let phone_numbers = tx
+            .prepare("SELECT phone_number FROM conversations GROUP BY phone_number HAVING COUNT(*) > 1")?
+            .query_map([], |row| row.get::<_, String>(0))?
+            .collect::<rusqlite::Result<Vec<_>>>()?;
+        for phone_number in &phone_numbers {
+            let ids = tx
+                .prepare("SELECT id FROM conversations WHERE phone_number = ?1 ORDER BY updated_at DESC, id")?
+                .query_map([phone_number], |row| row.get::<_, String>(0))?
+                .collect::<rusqlite::Result<Vec<_>>>()?;
+            let (kept, merged) = ids.split_first().expect("duplicates have several rows");
+            for id in merged {
+                for table in ["conversation_transitions", "messages", "outbound_queue", "audit_logs"] {
+                    tx.execute(
+                        &format!("UPDATE {} SET conversation_id = ?1 WHERE conversation_id = ?2", table),
+                        [kept, id],
+                    )?;
+                }
+                tx.execute("DELETE FROM conversations WHERE id = ?1", [id])?;
+            }
+            log::warn!("Merged {} duplicate conversations of {} into {}", merged.len(), phone_number, kept);
+        }
+        tx.commit()?;
+        Ok(())
+    }
+
+    /// Adds a column to an existing table if it is missing (schema migration)
+    fn ensure_column(conn: &Connection, table: &str, column: &str, definition: &str) -> Result<()> {
+        let mut stmt = conn.prepare(&format!("PRAGMA table_info({})", table))?;
+        let exists = stmt.query_map([], |row| row.get::<_, String>(1))?
+            .collect::<rusqlite::Result<Vec<_>>>()?
+            .iter()
+            .any(|name| name == column);
+        if !exists {
+            conn.execute(&format!("ALTER TABLE {} ADD COLUMN {} {}", table, column, definition), [])?;
+        }
         Ok(())
     }
 
+    /// Locks the database connection
+    fn conn(&self) -> Result<MutexGuard<'_, Connection>> {
+        self.conn.as_ref()
+            .ok_or_else(|| anyhow!("Database not initialized"))?
+            .lock()
+            .map_err(|_| anyhow!("Database connection poisoned"))
+    }
+
     /// Stores data with encryption (atomic operation)
     pub fn store(&self, key: &str, value: &[u8]) -> Result<()> {
         let encryption_key = self.encryption_key.as_ref()
@@ -118,8 +266,7 @@ impl StorageEngine {
             .map_err(|e| anyhow!("Encryption failed: {}", e))?;
         
         // Store in database
-        let conn = self.conn.as_ref()
-            .ok_or_else(|| anyhow!("Database not initialized"))?;
+        let conn = self.conn()?;
         
         let timestamp = chrono::Utc::now().timestamp();
         
@@ -137,8 +284,7 @@ impl StorageEngine {
         let encryption_key = self.encryption_key.as_ref()
             .ok_or_else(|| anyhow!("Encryption key not set"))?;
         
-        let conn = self.conn.as_ref()
-            .ok_or_else(|| anyhow!("Database not initialized"))?;
+        let conn = self.conn()?;
         
         let mut stmt = conn.prepare(
             "SELECT nonce, ciphertext FROM encrypted_data WHERE key = ?1"
@@ -167,8 +313,7 @@ impl StorageEngine {
 
     /// Checks database integrity
     pub fn check_integrity(&self) -> Result<bool> {
-        let conn = self.conn.as_ref()
-            .ok_or_else(|| anyhow!("Database not initialized"))?;
+        let conn = self.conn()?;
         
         let result: String = conn.query_row("PRAGMA integrity_check", [], |row| row.get(0))?;
         Ok(result == "ok")
@@ -176,8 +321,7 @@ impl StorageEngine {
     
     /// Deletes encrypted data by key
     pub fn delete(&self, key: &str) -> Result<bool> {
-        let conn = self.conn.as_ref()
-            .ok_or_else(|| anyhow!("Database not initialized"))?;
+        let conn = self.conn()?;
         
         let rows_affected = conn.execute(
             "DELETE FROM encrypted_data WHERE key = ?1",
@@ -186,6 +330,525 @@ impl StorageEngine {
         
         Ok(rows_affected > 0)
     }
+
+    // ============== QUOTAS ==============
+
+    /// Saves quota counters (one row per key, committed immediately)
+    pub fn save_quota_usage(&self, key: &str, usage: &QuotaUsage) -> Result<()> {
+        let conn = self.conn()?;
+        conn.execute(
+            "INSERT OR REPLACE INTO quota_usage (key, usage, updated_at) VALUES (?1, ?2, ?3)",
+            (key, serde_json::to_string(usage)?, Utc::now().timestamp()),
+        )?;
+        Ok(())
+    }
+
+    /// Keys of all saved quota counters (one per account)
+    pub fn list_quota_keys(&self) -> Result<Vec<String>> {
+        let conn = self.conn()?;
+        let mut stmt = conn.prepare("SELECT key FROM quota_usage ORDER BY key")?;
+        let keys = stmt.query_map([], |row| row.get(0))?.collect::<rusqlite::Result<Vec<String>>>()?;
+        Ok(keys)
+    }
+
+    /// Loads quota counters saved under `key`
+    pub fn load_quota_usage(&self, key: &str) -> Result<Option<QuotaUsage>> {
+        let conn = self.conn()?;
+        let usage: Option<String> = conn.query_row(
+            "SELECT usage FROM quota_usage WHERE key = ?1",
+            [key],
+            |row| row.get(0),
+        ).optional()?;
+        usage.map(|json| Ok(serde_json::from_str(&json)?)).transpose()
+    }
+
+    // ============== CATALOG ==============
+
+    /// Saves a catalog version (versions are immutable, a duplicate number is an error)
+    pub fn save_catalog_version(&self, version: &CatalogVersion) -> Result<()> {
+        let conn = self.conn()?;
+        conn.execute(
+            "INSERT INTO catalog_versions (version, hash, created_at, restored_from, products)
+             VALUES (?1, ?2, ?3, ?4, ?5)",
+            (
+                version.version,
+                &version.hash,
+                version.created_at.timestamp(),
+                version.restored_from,
+                serde_json::to_string(&version.products)?,
+            ),
+        )?;
+        Ok(())
+    }
+
+    /// All saved catalog versions, oldest first
+    pub fn load_catalog_versions(&self) -> Result<Vec<CatalogVersion>> {
+        let conn = self.conn()?;
+        let mut stmt = conn.prepare(
+            "SELECT version, hash, created_at, restored_from, products FROM catalog_versions ORDER BY version"
+        )?;
+        let rows = stmt.query_map([], |row| {
+            Ok((
+                row.get::<_, u32>(0)?,
+                row.get::<_, String>(1)?,
+                Self::datetime_column(row, 2)?,
+                row.get::<_, Option<u32>>(3)?,
+                row.get::<_, String>(4)?,
+            ))
+        })?.collect::<rusqlite::Result<Vec<_>>>()?;
+
+        rows.into_iter().map(|(version, hash, created_at, restored_from, products)| {
+            Ok(CatalogVersion {
+                version,
+                hash,
+                created_at,
+                restored_from,
+                products: serde_json::from_str(&products)?,
+            })
+        }).collect()
+    }
+
+    // ============== OUTBOUND QUEUE ==============
+
+    /// Inserts an entry unless its idempotency key is already queued
+    /// Returns the stored entry and whether it was inserted
+    pub fn enqueue_outbound(&self, message: &OutboundMessage) -> Result<(OutboundMessage, bool)> {
+        let conn = self.conn()?;
+        let inserted = conn.execute(
+            "INSERT OR IGNORE INTO outbound_queue
+                (id, idempotency_key, conversation_id, recipient, payload, state_at_enqueue,
+                 scheduled_at, status, attempts, last_error, created_at, updated_at, account)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13)",
+            (
+                &message.id,
+                &message.idempotency_key,
+                &message.conversation_id,
+                &message.recipient,
+                serde_json::to_string(&message.payload)?,
+                message.state_at_enqueue.to_string(),
+                message.scheduled_at.timestamp(),
+                message.status.to_string(),
+                message.attempts,
+                &message.last_error,
+                message.created_at.timestamp(),
+                message.updated_at.timestamp(),
+                &message.account,
+            ),
+        )? == 1;
+        let stored = conn.query_row(
+            &format!("{} WHERE idempotency_key = ?1", Self::SELECT_OUTBOUND),
+            [&message.idempotency_key],
+            Self::outbound_from_row,
+        )?;
+        Ok((stored, inserted))
+    }
+
+    /// Saves the mutable fields of a queued entry (status, schedule, attempts)
+    pub fn update_outbound(&self, message: &OutboundMessage) -> Result<()> {
+        let conn = self.conn()?;
+        conn.execute(
+            "UPDATE outbound_queue SET scheduled_at = ?1, status = ?2, attempts = ?3, last_error = ?4, updated_at = ?5
+             WHERE id = ?6",
+            (
+                message.scheduled_at.timestamp(),
+                message.status.to_string(),
+                message.attempts,
+                &message.last_error,
+                message.updated_at.timestamp(),
+                &message.id,
+            ),
+        )?;
+        Ok(())
+    }
+
+    pub fn get_outbound(&self, id: &str) -> Result<Option<OutboundMessage>> {
+        let conn = self.conn()?;
+        let message = conn.query_row(
+            &format!("{} WHERE id = ?1", Self::SELECT_OUTBOUND),
+            [id],
+            Self::outbound_from_row,
+        ).optional()?;
+        Ok(message)
+    }
+
+    /// Pending entries scheduled at or before `now`, oldest first
+    pub fn due_outbound(&self, now: DateTime<Utc>, limit: usize) -> Result<Vec<OutboundMessage>> {
+        let conn = self.conn()?;
+        let mut stmt = conn.prepare(&format!(
+            "{} WHERE status = ?1 AND scheduled_at <= ?2 ORDER BY scheduled_at, created_at LIMIT ?3",
+            Self::SELECT_OUTBOUND
+        ))?;
+        let messages = stmt.query_map(
+            (OutboundStatus::Pending.to_string(), now.timestamp(), limit as i64),
+            Self::outbound_from_row,
+        )?.collect::<rusqlite::Result<Vec<_>>>()?;
+        Ok(messages)
+    }
+
+    /// Queued entries, all or with one status, by scheduled time
+    pub fn list_outbound(&self, status: Option<OutboundStatus>) -> Result<Vec<OutboundMessage>> {
+        let conn = self.conn()?;
+        let messages = match status {
+            Some(status) => {
+                let mut stmt = conn.prepare(&format!("{} WHERE status = ?1 ORDER BY scheduled_at", Self::SELECT_OUTBOUND))?;
+                let rows = stmt.query_map([status.to_string()], Self::outbound_from_row)?;
+                rows.collect::<rusqlite::Result<Vec<_>>>()?
+            }
+            None => {
+                let mut stmt = conn.prepare(&format!("{} ORDER BY scheduled_at", Self::SELECT_OUTBOUND))?;
+                let rows = stmt.query_map([], Self::outbound_from_row)?;
+                rows.collect::<rusqlite::Result<Vec<_>>>()?
+            }
+        };
+        Ok(messages)
+    }
+
+    const SELECT_OUTBOUND: &'static str =
+        "SELECT id, idempotency_key, conversation_id, recipient, payload, state_at_enqueue,
+                scheduled_at, status, attempts, last_error, created_at, updated_at, account
+         FROM outbound_queue";
+
+    fn outbound_from_row(row: &rusqlite::Row) -> rusqlite::Result<OutboundMessage> {
+        let payload: String = row.get(4)?;
+        Ok(OutboundMessage {
+            id: row.get(0)?,
+            idempotency_key: row.get(1)?,
+            account: row.get(12)?,
+            conversation_id: row.get(2)?,
+            recipient: row.get(3)?,
+            payload: serde_json::from_str(&payload).map_err(|e| {
+                rusqlite::Error::FromSqlConversionFailure(4, rusqlite::types::Type::Text, e.into())
+            })?,
+            state_at_enqueue: Self::parsed_column(row, 5)?,
+            scheduled_at: Self::datetime_column(row, 6)?,
+            status: Self::parsed_column(row, 7)?,
+            attempts: row.get(8)?,
+            last_error: row.get(9)?,
+            created_at: Self::datetime_column(row, 10)?,
+            updated_at: Self::datetime_column(row, 11)?,
+        })
+    }
+
+    // ============== CONVERSATIONS ==============
+
+    /// Inserts or updates a conversation
+    pub fn save_conversation(&self, conversation: &Conversation) -> Result<()> {
+        let conn = self.conn()?;
+        Self::upsert_conversation(&conn, conversation)
+    }
+
+    /// Inserts a conversation unless its phone number already has one
+    /// Returns the stored conversation and whether it was inserted
+    pub fn create_conversation(&self, conversation: &Conversation) -> Result<(Conversation, bool)> {
+        let conn = self.conn()?;
+        let inserted = conn.execute(
+            "INSERT INTO conversations
+                (id, phone_number, state, focus_product_id, misunderstanding_count, state_since, created_at, updated_at)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8)
+             ON CONFLICT(phone_number) DO NOTHING",
+            (
+                &conversation.id,
+                &conversation.phone_number,
+                conversation.state.to_string(),
+                &conversation.focus_product_id,
+                conversation.misunderstanding_count,
+                conversation.state_since.timestamp(),
+                conversation.created_at.timestamp(),
+                conversation.updated_at.timestamp(),
+            ),
+        )? > 0;
+
+        let stored = conn.query_row(
+            &format!("{} WHERE phone_number = ?1", Self::SELECT_CONVERSATION),
+            [&conversation.phone_number],
+            Self::conversation_from_row,
+        )?;
+        Ok((stored, inserted))
+    }
+
+    /// Re-reads a conversation, applies `change` and saves it with the transitions it returns, in one transaction
+    /// Nothing is saved if `change` fails; None if the conversation does not exist
+    pub fn update_conversation<F>(&self, id: &str, change: F) -> Result<Option<(Conversation, Vec<TransitionRecord>)>>
+    where
+        F: FnOnce(&mut Conversation) -> Result<Vec<TransitionRecord>>,
+    {
+        let mut conn = self.conn()?;
+        let tx = conn.transaction_with_behavior(rusqlite::TransactionBehavior::Immediate)?;
+        let conversation = tx.query_row(
+            &format!("{} WHERE id = ?1", Self::SELECT_CONVERSATION),
+            [id],
+            Self::conversation_from_row,
+        ).optional()?;
+        let Some(mut conversation) = conversation else {
+            return Ok(None);
+        };
+
+        let transitions = change(&mut conversation)?;
+        Self::upsert_conversation(&tx, &conversation)?;
+        for record in &transitions {
+            Self::insert_transition(&tx, record)?;
+        }
+        tx.commit()?;
+        Ok(Some((conversation, transitions)))
+    }
+
+    /// Persists a transition and the resulting conversation atomically
+    pub fn save_transition(&self, conversation: &Conversation, record: &TransitionRecord) -> Result<()> {
+        let mut conn = self.conn()?;
+        let tx = conn.transaction()?;
+        Self::upsert_conversation(&tx, conversation)?;
+        Self::insert_transition(&tx, record)?;
+        tx.commit()?;
+        Ok(())
+    }
+
+    /// Records a transition; a change of state cancels the replies still queued for the conversation
+    fn insert_transition(conn: &Connection, record: &TransitionRecord) -> Result<()> {
+        if record.from_state != record.to_state {
+            conn.execute(
+                "UPDATE outbound_queue SET status = ?1, last_error = ?2, updated_at = ?3
+                 WHERE conversation_id = ?4 AND status = ?5",
+                (
+                    OutboundStatus::Cancelled.to_string(),
+                    format!("Conversation moved from {} to {}", record.from_state, record.to_state),
+                    Utc::now().timestamp(),
+                    &record.conversation_id,
+                    OutboundStatus::Pending.to_string(),
+                ),
+            )?;
+        }
+        conn.execute(
+            "INSERT INTO conversation_transitions
+                (conversation_id, event, from_state, to_state, product_id, timestamp, actor, reason)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8)",
+            (
+                &record.conversation_id,
+                record.event.to_string(),
+                record.from_state.to_string(),
+                record.to_state.to_string(),
+                &record.product_id,
+                record.timestamp.timestamp(),
+                record.actor.to_string(),
+                &record.reason,
+            ),
+        )?;
+        Ok(())
+    }
+
+    /// Gets a conversation by ID
+    pub fn get_conversation(&self, id: &str) -> Result<Option<Conversation>> {
+        let conn = self.conn()?;
+        let conversation = conn.query_row(
+            &format!("{} WHERE id = ?1", Self::SELECT_CONVERSATION),
+            [id],
+            Self::conversation_from_row,
+        ).optional()?;
+        Ok(conversation)
+    }
+
+    /// Gets the most recent conversation with a phone number
+    pub fn find_conversation_by_phone(&self, phone_number: &str) -> Result<Option<Conversation>> {
+        let conn = self.conn()?;
+        let conversation = conn.query_row(
+            &format!("{} WHERE phone_number = ?1 ORDER BY updated_at DESC LIMIT 1", Self::SELECT_CONVERSATION),
+            [phone_number],
+            Self::conversation_from_row,
+        ).optional()?;
+        Ok(conversation)
+    }
+
+    /// Lists all conversations
+    pub fn list_conversations(&self) -> Result<Vec<Conversation>> {
+        let conn = self.conn()?;
+        let mut stmt = conn.prepare(&format!("{} ORDER BY created_at", Self::SELECT_CONVERSATION))?;
+        let conversations = stmt.query_map([], Self::conversation_from_row)?
+            .collect::<rusqlite::Result<Vec<_>>>()?;
+        Ok(conversations)
+    }
+
+    /// Claims (or renews) a conversation for an operator, unless another operator holds an active lease
+    /// A single conditional UPDATE, so two operators cannot both win; returns the conversation as stored
+    /// after the attempt (check `takeover.operator`), None if it does not exist
+    pub fn claim_takeover(
+        &self,
+        id: &str,
+        operator: &str,
+        lease: chrono::Duration,
+        now: DateTime<Utc>,
+    ) -> Result<Option<Conversation>> {
+        let conn = self.conn()?;
+        // A renewal keeps the original claim time
+        conn.execute(
+            "UPDATE conversations SET
+                takeover_claimed_at = CASE
+                    WHEN takeover_operator = ?2 AND takeover_expires_at > ?3 THEN takeover_claimed_at
+                    ELSE ?3
+                END,
+                takeover_operator = ?2, takeover_expires_at = ?4, updated_at = ?3
+             WHERE id = ?1 AND (takeover_operator IS NULL OR takeover_operator = ?2 OR takeover_expires_at <= ?3)",
+            (id, operator, now.timestamp(), (now + lease).timestamp()),
+        )?;
+        let conversation = conn.query_row(
+            &format!("{} WHERE id = ?1", Self::SELECT_CONVERSATION),
+            [id],
+            Self::conversation_from_row,
+        ).optional()?;
+        Ok(conversation)
+    }
+
+    /// Ends the takeover of `operator`; another operator's lease is left untouched
+    /// Returns the conversation as stored after the attempt, None if it does not exist
+    pub fn release_takeover(&self, id: &str, operator: &str, now: DateTime<Utc>) -> Result<Option<Conversation>> {
+        let conn = self.conn()?;
+        conn.execute(
+            "UPDATE conversations SET
+                takeover_operator = NULL, takeover_claimed_at = NULL, takeover_expires_at = NULL, updated_at = ?3
+             WHERE id = ?1 AND takeover_operator = ?2",
+            (id, operator, now.timestamp()),
+        )?;
+        let conversation = conn.query_row(
+            &format!("{} WHERE id = ?1", Self::SELECT_CONVERSATION),
+            [id],
+            Self::conversation_from_row,
+        ).optional()?;
+        Ok(conversation)
+    }
+
+    /// Lists the conversations whose takeover lease is still running
+    pub fn find_conversations_under_takeover(&self, now: DateTime<Utc>) -> Result<Vec<Conversation>> {
+        let conn = self.conn()?;
+        let mut stmt = conn.prepare(&format!(
+            "{} WHERE takeover_expires_at > ?1 ORDER BY takeover_expires_at",
+            Self::SELECT_CONVERSATION
+        ))?;
+        let conversations = stmt.query_map([now.timestamp()], Self::conversation_from_row)?
+            .collect::<rusqlite::Result<Vec<_>>>()?;
+        Ok(conversations)
+    }
+
+    /// Lists the conversations currently in a state
+    pub fn find_conversations_by_state(&self, state: &ConversationState) -> Result<Vec<Conversation>> {
+        let conn = self.conn()?;
+        let mut stmt = conn.prepare(&format!("{} WHERE state = ?1 ORDER BY created_at", Self::SELECT_CONVERSATION))?;
+        let conversations = stmt.query_map([state.to_string()], Self::conversation_from_row)?
+            .collect::<rusqlite::Result<Vec<_>>>()?;
+        Ok(conversations)
+    }
+
+    /// Transition history of a conversation, in the order it happened
+    pub fn get_transitions(&self, conversation_id: &str) -> Result<Vec<TransitionRecord>> {
+        self.query_transitions("WHERE conversation_id = ?1", [conversation_id])
+    }
+
+    /// Transition history of every conversation
+    pub fn get_all_transitions(&self) -> Result<Vec<TransitionRecord>> {
+        self.query_transitions("", [])
+    }
+
+    const SELECT_CONVERSATION: &'static str =
+        "SELECT id, phone_number, state, focus_product_id, misunderstanding_count, state_since, created_at, updated_at,
+                takeover_operator, takeover_claimed_at, takeover_expires_at
+         FROM conversations";
+
+    fn upsert_conversation(conn: &Connection, conversation: &Conversation) -> Result<()> {
+        conn.execute(
+            "INSERT INTO conversations
+                (id, phone_number, state, focus_product_id, misunderstanding_count, state_since, created_at, updated_at,
+                 takeover_operator, takeover_claimed_at, takeover_expires_at)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11)
+             ON CONFLICT(id) DO UPDATE SET
+                phone_number = excluded.phone_number, state = excluded.state,
+                focus_product_id = excluded.focus_product_id, misunderstanding_count = excluded.misunderstanding_count,
+                state_since = excluded.state_since, updated_at = excluded.updated_at,
+                takeover_operator = excluded.takeover_operator, takeover_claimed_at = excluded.takeover_claimed_at,
+                takeover_expires_at = excluded.takeover_expires_at",
+            (
+                &conversation.id,
+                &conversation.phone_number,
+                conversation.state.to_string(),
+                &conversation.focus_product_id,
+                conversation.misunderstanding_count,
+                conversation.state_since.timestamp(),
+                conversation.created_at.timestamp(),
+                conversation.updated_at.timestamp(),
+                conversation.takeover.as_ref().map(|t| t.operator.clone()),
+                conversation.takeover.as_ref().map(|t| t.claimed_at.timestamp()),
+                conversation.takeover.as_ref().map(|t| t.expires_at.timestamp()),
+            ),
+        )?;
+        Ok(())
+    }
+
+    fn conversation_from_row(row: &rusqlite::Row) -> rusqlite::Result<Conversation> {
+        let updated_at = Self::datetime_column(row, 7)?;
+        Ok(Conversation {
+            id: row.get(0)?,
+            phone_number: row.get(1)?,
+            state: Self::parsed_column(row, 2)?,
+            focus_product_id: row.get(3)?,
+            misunderstanding_count: row.get(4)?,
+            // Rows written before state_since existed: best estimate is the last update
+            state_since: match row.get::<_, Option<i64>>(5)? {
+                Some(_) => Self::datetime_column(row, 5)?,
+                None => updated_at,
+            },
+            takeover: match row.get::<_, Option<String>>(8)? {
+                Some(operator) => Some(HumanTakeover {
+                    operator,
+                    claimed_at: Self::datetime_column(row, 9)?,
+                    expires_at: Self::datetime_column(row, 10)?,
+                }),
+                None => None,
+            },
+            created_at: Self::datetime_column(row, 6)?,
+            updated_at,
+        })
+    }
+
+    fn query_transitions<P: rusqlite::Params>(&self, filter: &str, params: P) -> Result<Vec<TransitionRecord>> {
+        let conn = self.conn()?;
+        let mut stmt = conn.prepare(&format!(
+            "SELECT conversation_id, event, from_state, to_state, product_id, timestamp, actor, reason
+             FROM conversation_transitions {} ORDER BY id",
+            filter
+        ))?;
+        let records = stmt.query_map(params, |row| {
+            Ok(TransitionRecord {
+                conversation_id: row.get(0)?,
+                event: Self::parsed_column(row, 1)?,
+                from_state: Self::parsed_column(row, 2)?,
+                to_state: Self::parsed_column(row, 3)?,
+                product_id: row.get(4)?,
+                timestamp: Self::datetime_column(row, 5)?,
+                actor: Self::parsed_column(row, 6)?,
+                reason: row.get(7)?,
+            })
+        })?.collect::<rusqlite::Result<Vec<_>>>()?;
+        Ok(records)
+    }
+
+    /// Reads a text column through `FromStr` (states, events, actors)
+    fn parsed_column<T>(row: &rusqlite::Row, index: usize) -> rusqlite::Result<T>
+    where
+        T: std::str::FromStr<Err = anyhow::Error>,
+    {
+        let text: String = row.get(index)?;
+        text.parse().map_err(|e: anyhow::Error| {
+            rusqlite::Error::FromSqlConversionFailure(index, rusqlite::types::Type::Text, e.into())
+        })
+    }
+
+    /// Reads a timestamp column (seconds since epoch)
+    fn datetime_column(row: &rusqlite::Row, index: usize) -> rusqlite::Result<DateTime<Utc>> {
+        let timestamp: i64 = row.get(index)?;
+        DateTime::from_timestamp(timestamp, 0).ok_or_else(|| {
+            rusqlite::Error::FromSqlConversionFailure(
+                index,
+                rusqlite::types::Type::Integer,
+                anyhow!("Invalid timestamp in database: {}", timestamp).into(),
+            )
+        })
+    }
 }
 
 #[cfg(test)]
@@ -255,4 +918,161 @@ mod tests {
         
         assert!(engine.check_integrity().unwrap());
     }
+    
+    #[test]
+    fn test_conversation_persistence_and_history() {
+        use crate::engines::conversation::{ConversationEngine, ConversationEvent, ConversationState};
+        
+        let db_path = temp_dir().join(format!("test_conversations_{}.db", uuid::Uuid::new_v4()));
+        let mut engine = StorageEngine::new(db_path).unwrap();
+        engine.initialize().unwrap();
+        
+        let conversation_engine = ConversationEngine::new();
+        let mut conversation = Conversation::new("+33611111111", conversation_engine.initial_state());
+        engine.save_conversation(&conversation).unwrap();
+        
+        for event in [ConversationEvent::ProductQuestion, ConversationEvent::ThreatDetected] {
+            let record = conversation_engine.apply(&mut conversation, event);
+            engine.save_transition(&conversation, &record).unwrap();
+        }
+        
+        let stored = engine.get_conversation(&conversation.id).unwrap().unwrap();
+        assert_eq!(stored.state, ConversationState::Escalated);
+        assert_eq!(stored.phone_number, "+33611111111");
+        
+        let by_phone = engine.find_conversation_by_phone("+33611111111").unwrap().unwrap();
+        assert_eq!(by_phone.id, conversation.id);
+        
+        let history = engine.get_transitions(&conversation.id).unwrap();
+        assert_eq!(history.len(), 2);
+        assert_eq!(history[1].event, ConversationEvent::ThreatDetected);
+        assert_eq!(conversation_engine.replay(&history).unwrap(), ConversationState::Escalated);
+        
+        let claimed = engine.claim_takeover(&conversation.id, "alice", chrono::Duration::minutes(30), Utc::now()).unwrap().unwrap();
+        assert_eq!(claimed.takeover.unwrap().operator, "alice");
+        assert_eq!(engine.find_conversations_under_takeover(Utc::now()).unwrap().len(), 1);
+        assert!(engine.find_conversations_under_takeover(Utc::now() + chrono::Duration::hours(1)).unwrap().is_empty());
+        
+        assert!(engine.get_conversation("unknown").unwrap().is_none());
+        assert_eq!(engine.list_conversations().unwrap().len(), 1);
+    }
+    
+    #[test]
+    fn test_one_conversation_per_phone_and_atomic_updates() {
+        use crate::engines::conversation::{ConversationEngine, ConversationEvent, ConversationState};
+        
+        let db_path = temp_dir().join(format!("test_conversations_{}.db", uuid::Uuid::new_v4()));
+        let mut engine = StorageEngine::new(db_path).unwrap();
+        engine.initialize().unwrap();
+        let conversation_engine = ConversationEngine::new();
+        
+        let first = Conversation::new("+33622222222", conversation_engine.initial_state());
+        let (stored, inserted) = engine.create_conversation(&first).unwrap();
+        assert!(inserted);
+        let second = Conversation::new("+33622222222", conversation_engine.initial_state());
+        let (stored_again, inserted) = engine.create_conversation(&second).unwrap();
+        assert!(!inserted);
+        assert_eq!(stored_again.id, stored.id);
+        // A different conversation cannot take the number either
+        assert!(engine.save_conversation(&second).is_err());
+        
+        let (updated, transitions) = engine.update_conversation(&first.id, |conversation| {
+            Ok(vec![conversation_engine.apply(conversation, ConversationEvent::ProductQuestion)])
+        }).unwrap().unwrap();
+        assert_eq!(updated.state, ConversationState::Interest);
+        assert_eq!(transitions.len(), 1);
+        
+        // A failed change saves nothing
+        let failed = engine.update_conversation(&first.id, |conversation| {
+            conversation_engine.apply(conversation, ConversationEvent::ThreatDetected);
+            Err(anyhow!("rejected"))
+        });
+        assert!(failed.is_err());
+        assert_eq!(engine.get_conversation(&first.id).unwrap().unwrap().state, ConversationState::Interest);
+        assert_eq!(engine.get_transitions(&first.id).unwrap().len(), 1);
+        
+        assert!(engine.update_conversation("unknown", |_| Ok(vec![])).unwrap().is_none());
+    }
+    
+    #[test]
+    fn test_duplicate_phones_merged_before_unique_index() {
+        let db_path = temp_dir().join(format!("test_conversations_{}.db", uuid::Uuid::new_v4()));
+        {
+            // Schema of a database created before phone numbers were unique
+            let conn = Connection::open(&db_path).unwrap();
+            conn.execute_batch(
+                "CREATE TABLE conversations (
+                    id TEXT PRIMARY KEY,
+                    phone_number TEXT NOT NULL,
+                    state TEXT NOT NULL,
+                    created_at INTEGER NOT NULL,
+                    updated_at INTEGER NOT NULL
+                );
+                CREATE TABLE conversation_transitions (
+                    id INTEGER PRIMARY KEY AUTOINCREMENT,
+                    conversation_id TEXT NOT NULL,
+                    event TEXT NOT NULL,
+                    from_state TEXT NOT NULL,
+                    to_state TEXT NOT NULL,
+                    product_id TEXT,
+                    timestamp INTEGER NOT NULL
+                );
+                INSERT INTO conversations VALUES ('old', '+33633333333', 'Interest', 100, 200);
+                INSERT INTO conversations VALUES ('new', '+33633333333', 'Intent', 150, 300);
+                INSERT INTO conversations VALUES ('other', '+33644444444', 'Discovery', 100, 100);
+                INSERT INTO conversation_transitions (conversation_id, event, from_state, to_state, timestamp)
+                    VALUES ('old', 'ProductQuestion', 'Discovery', 'Interest', 200);
+                INSERT INTO conversation_transitions (conversation_id, event, from_state, to_state, timestamp)
+                    VALUES ('new', 'PriceInterest', 'Interest', 'Intent', 300);",
+            ).unwrap();
+        }
+        
+        let mut engine = StorageEngine::new(db_path).unwrap();
+        engine.initialize().unwrap();
+        
+        // The most recently updated conversation is kept, with the history of both
+        assert_eq!(engine.list_conversations().unwrap().len(), 2);
+        let kept = engine.find_conversation_by_phone("+33633333333").unwrap().unwrap();
+        assert_eq!(kept.id, "new");
+        assert_eq!(engine.get_transitions("new").unwrap().len(), 2);
+        assert!(engine.get_conversation("old").unwrap().is_none());
+        
+        let duplicate = Conversation::new("+33633333333", kept.state.clone());
+        assert!(engine.save_conversation(&duplicate).is_err());
+    }
+    
+    #[test]
+    fn test_takeover_lease() {
+        use crate::engines::conversation::ConversationEngine;
+        use chrono::Duration;
+        
+        let db_path = temp_dir().join(format!("test_conversations_{}.db", uuid::Uuid::new_v4()));
+        let mut engine = StorageEngine::new(db_path).unwrap();
+        engine.initialize().unwrap();
+        let conversation = Conversation::new("+33600000000", ConversationEngine::new().initial_state());
+        engine.save_conversation(&conversation).unwrap();
+        let id = conversation.id.as_str();
+        let now = DateTime::from_timestamp(Utc::now().timestamp(), 0).unwrap();
+        let holder = |c: Option<Conversation>| c.unwrap().takeover.map(|t| t.operator);
+        
+        assert_eq!(holder(engine.claim_takeover(id, "alice", Duration::minutes(30), now).unwrap()).as_deref(), Some("alice"));
+        // Bob does not win while Alice's lease runs
+        assert_eq!(holder(engine.claim_takeover(id, "bob", Duration::minutes(30), now).unwrap()).as_deref(), Some("alice"));
+        
+        // Renewal keeps the original claim time
+        let renewed = engine.claim_takeover(id, "alice", Duration::minutes(30), now + Duration::minutes(20)).unwrap().unwrap();
+        let takeover = renewed.takeover.clone().unwrap();
+        assert_eq!(takeover.claimed_at, now);
+        assert_eq!(takeover.expires_at, now + Duration::minutes(50));
+        
+        // Only the holder can release
+        assert_eq!(holder(engine.release_takeover(id, "bob", now).unwrap()).as_deref(), Some("alice"));
+        
+        // Expired lease: the bot is back in charge, another operator can claim
+        assert!(!renewed.is_under_takeover(now + Duration::minutes(51)));
+        assert_eq!(holder(engine.claim_takeover(id, "bob", Duration::minutes(30), now + Duration::minutes(51)).unwrap()).as_deref(), Some("bob"));
+        
+        assert_eq!(holder(engine.release_takeover(id, "bob", now).unwrap()), None);
+        assert!(engine.claim_takeover("unknown", "alice", Duration::minutes(30), now).unwrap().is_none());
+    }
 }
@@ -36,6 +36,7 @@ mod tests {
         let _kb = KnowledgeBaseEngine::new();
         let _conv = ConversationEngine::new();
         let _alert = AlertEngine::default();
+        let _sentiment = SentimentEngine::default();
         let _ia = IAGateway::default();
         let _anti_hal = AntiHallucinationEngine::new();
         let _audit = AuditEngine::new();